@@ -1,3 +1,47 @@
+use std::{
+    env,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 fn main() {
     println!("cargo:rerun-if-changed=migrations");
+
+    // GL_GIT_COMMIT can be given where the build has no git checkout, like in
+    // the docker image
+    println!("cargo:rerun-if-env-changed=GL_GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    let commit = env::var("GL_GIT_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(git_commit)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GL_GIT_COMMIT={}", commit);
+
+    // SOURCE_DATE_EPOCH pins the timestamp for reproducible builds
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    let built_at = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=GL_BUILD_TIMESTAMP={}", built_at);
+}
+
+/// git_commit is the commit that is checked out, if this is a git checkout
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|commit| commit.trim().to_string())
 }
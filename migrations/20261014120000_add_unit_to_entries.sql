@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE grocery_list_entries ADD COLUMN unit TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,61 @@
+-- Add migration script here
+
+CREATE TABLE IF NOT EXISTS lists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO lists (name)
+SELECT "default"
+WHERE NOT EXISTS (SELECT 1 FROM lists WHERE id = 1);
+
+-- categories are now unique by name within a list rather than globally, so the
+-- table has to be recreated to change the constraint
+CREATE TABLE IF NOT EXISTS categories_tmp (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    is_default_category BOOLEAN NOT NULL DEFAULT FALSE,
+    position INTEGER NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    list_id INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+    UNIQUE(list_id, name)
+);
+
+INSERT INTO categories_tmp
+    SELECT id, name, is_default_category, position, updated_at, 1
+    FROM categories;
+
+CREATE TABLE IF NOT EXISTS grocery_list_entries_tmp (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description TEXT NOT NULL,
+    completed_at TIMESTAMP,
+    archived_at TIMESTAMP,
+    position INTEGER,
+    quantity TEXT NOT NULL DEFAULT '',
+    notes TEXT NOT NULL DEFAULT '',
+    category_id INTEGER NOT NULL DEFAULT 1,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    unit TEXT NOT NULL DEFAULT '',
+    list_id INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY(category_id) REFERENCES categories_tmp(id) ON DELETE CASCADE
+    FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+    UNIQUE(category_id, position)
+    CHECK ((archived_at IS NULL) <> (position IS NULL))
+);
+
+-- copy data from old table to the new one, assigning everything to the default list
+INSERT INTO grocery_list_entries_tmp SELECT *, 1 FROM grocery_list_entries gle;
+
+-- drop the old table
+DROP TABLE grocery_list_entries;
+
+-- rename new table to the old one
+ALTER TABLE grocery_list_entries_tmp RENAME TO grocery_list_entries;
+
+-- drop old table
+DROP TABLE categories;
+
+-- rename new table to the old one. at this point foreign keys should point to new table
+ALTER TABLE categories_tmp RENAME TO categories;
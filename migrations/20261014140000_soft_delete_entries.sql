@@ -0,0 +1,32 @@
+-- Add migration script here
+
+-- deleted entries are taken out of their category's ordering like archived
+-- entries are, so the position check has to account for deleted_at and the
+-- table has to be recreated
+CREATE TABLE IF NOT EXISTS grocery_list_entries_tmp (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description TEXT NOT NULL,
+    completed_at TIMESTAMP,
+    archived_at TIMESTAMP,
+    position INTEGER,
+    quantity TEXT NOT NULL DEFAULT '',
+    notes TEXT NOT NULL DEFAULT '',
+    category_id INTEGER NOT NULL DEFAULT 1,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    unit TEXT NOT NULL DEFAULT '',
+    list_id INTEGER NOT NULL DEFAULT 1,
+    deleted_at TIMESTAMP,
+    FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
+    FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+    UNIQUE(category_id, position)
+    CHECK ((archived_at IS NULL AND deleted_at IS NULL) <> (position IS NULL))
+);
+
+-- copy data from old table to the new one
+INSERT INTO grocery_list_entries_tmp SELECT *, NULL FROM grocery_list_entries gle;
+
+-- drop the old table
+DROP TABLE grocery_list_entries;
+
+-- rename new table to the old one
+ALTER TABLE grocery_list_entries_tmp RENAME TO grocery_list_entries;
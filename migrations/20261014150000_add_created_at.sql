@@ -0,0 +1,10 @@
+-- Add migration script here
+
+-- sqlite does not allow a non-constant default when adding a column, so
+-- inserts set created_at explicitly and existing rows fall back to the last
+-- time they were updated
+ALTER TABLE grocery_list_entries ADD COLUMN created_at TIMESTAMP;
+UPDATE grocery_list_entries SET created_at = COALESCE(updated_at, CURRENT_TIMESTAMP);
+
+ALTER TABLE categories ADD COLUMN created_at TIMESTAMP;
+UPDATE categories SET created_at = COALESCE(updated_at, CURRENT_TIMESTAMP);
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE grocery_list_entries ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE categories ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
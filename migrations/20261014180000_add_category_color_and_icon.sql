@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE categories ADD COLUMN color TEXT;
+ALTER TABLE categories ADD COLUMN icon TEXT;
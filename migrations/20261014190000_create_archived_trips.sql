@@ -0,0 +1,13 @@
+-- Add migration script here
+
+-- archived_trips keeps what was on a list when it was archived at the end of a
+-- shopping trip, with the entries stored as a JSON array
+CREATE TABLE IF NOT EXISTS archived_trips (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    list_id INTEGER NOT NULL,
+    archived_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    entries TEXT NOT NULL,
+    FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS archived_trips_list_id ON archived_trips(list_id, archived_at);
@@ -0,0 +1,39 @@
+-- Add migration script here
+
+-- an entry without a note stores NULL rather than an empty string, so that
+-- "no note" can be told apart cleanly. NOT NULL can't be dropped from a
+-- column, so the table has to be recreated.
+CREATE TABLE IF NOT EXISTS grocery_list_entries_tmp (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description TEXT NOT NULL,
+    completed_at TIMESTAMP,
+    archived_at TIMESTAMP,
+    position INTEGER,
+    quantity TEXT NOT NULL DEFAULT '',
+    notes TEXT,
+    category_id INTEGER NOT NULL DEFAULT 1,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    unit TEXT NOT NULL DEFAULT '',
+    list_id INTEGER NOT NULL DEFAULT 1,
+    deleted_at TIMESTAMP,
+    created_at TIMESTAMP,
+    priority INTEGER NOT NULL DEFAULT 0,
+    version INTEGER NOT NULL DEFAULT 1,
+    FOREIGN KEY(category_id) REFERENCES categories(id) ON DELETE CASCADE
+    FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+    UNIQUE(category_id, position)
+    CHECK ((archived_at IS NULL AND deleted_at IS NULL) <> (position IS NULL))
+);
+
+-- copy data from old table to the new one, turning empty notes into NULL
+INSERT INTO grocery_list_entries_tmp
+    SELECT id, description, completed_at, archived_at, position, quantity,
+        NULLIF(TRIM(notes), ''), category_id, updated_at, unit, list_id,
+        deleted_at, created_at, priority, version
+    FROM grocery_list_entries gle;
+
+-- drop the old table
+DROP TABLE grocery_list_entries;
+
+-- rename new table to the old one
+ALTER TABLE grocery_list_entries_tmp RENAME TO grocery_list_entries;
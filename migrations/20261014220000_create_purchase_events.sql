@@ -0,0 +1,18 @@
+-- Add migration script here
+
+-- purchase_events records each time an entry was checked off, keeping what
+-- was bought even after the entry itself is archived or purged
+CREATE TABLE IF NOT EXISTS purchase_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entry_id INTEGER,
+    list_id INTEGER NOT NULL,
+    description TEXT NOT NULL,
+    quantity TEXT NOT NULL DEFAULT '',
+    unit TEXT NOT NULL DEFAULT '',
+    purchased_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(entry_id) REFERENCES grocery_list_entries(id) ON DELETE SET NULL
+    FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS purchase_events_description
+    ON purchase_events(description COLLATE NOCASE, purchased_at);
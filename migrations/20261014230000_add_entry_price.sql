@@ -0,0 +1,6 @@
+-- Add migration script here
+
+-- prices are whole cents so that adding them up never rounds, and either can
+-- be left out for an entry that hasn't been priced
+ALTER TABLE grocery_list_entries ADD COLUMN price_cents INTEGER;
+ALTER TABLE grocery_list_entries ADD COLUMN currency TEXT;
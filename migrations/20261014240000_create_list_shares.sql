@@ -0,0 +1,10 @@
+-- Add migration script here
+
+-- list_shares holds the token for each list that has been shared read-only,
+-- which goes away with the share when it is revoked or the list is deleted
+CREATE TABLE IF NOT EXISTS list_shares (
+    list_id INTEGER PRIMARY KEY,
+    token TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY(list_id) REFERENCES lists(id) ON DELETE CASCADE
+);
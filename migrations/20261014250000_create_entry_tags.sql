@@ -0,0 +1,18 @@
+-- Add migration script here
+
+-- tags are labels like "organic" or "sale" that cut across categories. Each
+-- name is stored once, lower cased, and linked to any number of entries.
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS entry_tags (
+    entry_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY(entry_id, tag_id),
+    FOREIGN KEY(entry_id) REFERENCES grocery_list_entries(id) ON DELETE CASCADE,
+    FOREIGN KEY(tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_entry_tags_tag_id ON entry_tags(tag_id);
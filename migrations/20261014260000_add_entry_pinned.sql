@@ -0,0 +1,5 @@
+-- Add migration script here
+
+-- pinned entries are staples that are shown first in their category, ahead
+-- of the manual order
+ALTER TABLE grocery_list_entries ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE;
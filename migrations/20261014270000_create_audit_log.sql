@@ -0,0 +1,12 @@
+-- Add migration script here
+
+-- audit_log records each change made through the api, with the fields that
+-- changed as a JSON object of {"field": {"before": ..., "after": ...}}
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    table_name TEXT NOT NULL,
+    row_id INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    diff TEXT NOT NULL
+);
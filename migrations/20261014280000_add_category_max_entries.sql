@@ -0,0 +1,5 @@
+-- Add migration script here
+
+-- max_entries caps how many active entries a category can hold, where null
+-- means there is no cap
+ALTER TABLE categories ADD COLUMN max_entries INTEGER;
@@ -0,0 +1,10 @@
+-- Add migration script here
+
+-- idempotency_keys remembers the entry each Idempotency-Key sent to create an
+-- entry resulted in, so a retried request gets that entry back instead of
+-- adding it again
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key TEXT PRIMARY KEY,
+    entry_id INTEGER NOT NULL REFERENCES grocery_list_entries(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,5 @@
+-- Add migration script here
+
+-- visible_from schedules an entry, which is left out of the list until then,
+-- where null means it is visible right away
+ALTER TABLE grocery_list_entries ADD COLUMN visible_from TIMESTAMP;
@@ -0,0 +1,40 @@
+-- Add migration script here
+
+-- entries from before units existed can still have theirs attached to the
+-- quantity, like 2lb. Split them the same way new entries are, into the
+-- amount (2) and the unit (lb), bumping the version so that clients holding
+-- the old quantity pick up the change.
+WITH RECURSIVE amount(id, len) AS (
+    SELECT id, 1 FROM grocery_list_entries
+    WHERE unit = ''
+    AND quantity GLOB '[0-9.-]*'
+    AND quantity GLOB '*[^0-9.-]*'
+    UNION ALL
+    SELECT amount.id, amount.len + 1 FROM amount
+    JOIN grocery_list_entries e ON e.id = amount.id
+    WHERE substr(e.quantity, amount.len + 1, 1) GLOB '[0-9.-]'
+)
+UPDATE grocery_list_entries
+SET
+    quantity = substr(quantity, 1, (SELECT MAX(len) FROM amount WHERE amount.id = grocery_list_entries.id)),
+    unit = trim(substr(quantity, (SELECT MAX(len) FROM amount WHERE amount.id = grocery_list_entries.id) + 1)),
+    updated_at = CURRENT_TIMESTAMP,
+    version = version + 1
+WHERE id IN (SELECT id FROM amount);
+
+-- purchases recorded from those entries carry the same attached units
+WITH RECURSIVE amount(id, len) AS (
+    SELECT id, 1 FROM purchase_events
+    WHERE unit = ''
+    AND quantity GLOB '[0-9.-]*'
+    AND quantity GLOB '*[^0-9.-]*'
+    UNION ALL
+    SELECT amount.id, amount.len + 1 FROM amount
+    JOIN purchase_events p ON p.id = amount.id
+    WHERE substr(p.quantity, amount.len + 1, 1) GLOB '[0-9.-]'
+)
+UPDATE purchase_events
+SET
+    quantity = substr(quantity, 1, (SELECT MAX(len) FROM amount WHERE amount.id = purchase_events.id)),
+    unit = trim(substr(quantity, (SELECT MAX(len) FROM amount WHERE amount.id = purchase_events.id) + 1))
+WHERE id IN (SELECT id FROM amount);
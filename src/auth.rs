@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::error::ApiError;
+
+/// API_PATH_PREFIX is where the routes that need the token live. The static
+/// site and the health probes stay open so the app can still load.
+const API_PATH_PREFIX: &str = "/api/";
+
+/// SHARED_PATH_PREFIX is where lists shared read-only are viewed, which the
+/// share's own token is enough for
+const SHARED_PATH_PREFIX: &str = "/api/shared/";
+
+/// ApiToken is the bearer token clients have to send to use the api
+#[derive(Clone)]
+pub struct ApiToken {
+    token: String,
+    /// protect_reads is whether reads need the token too, rather than just
+    /// changes
+    protect_reads: bool,
+}
+
+impl ApiToken {
+    pub fn new(token: String, protect_reads: bool) -> Self {
+        Self {
+            token,
+            protect_reads,
+        }
+    }
+
+    /// is_protected is whether a request needs the token
+    fn is_protected(&self, method: &Method, path: &str) -> bool {
+        let is_read = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+        if !path.starts_with(API_PATH_PREFIX) || (is_read && path.starts_with(SHARED_PATH_PREFIX)) {
+            return false;
+        }
+        self.protect_reads || !is_read
+    }
+
+    /// accepts is whether headers carry the token. The comparison doesn't stop
+    /// at the first wrong byte, so response times give nothing away about it.
+    fn accepts(&self, headers: &HeaderMap) -> bool {
+        let Some(sent) = bearer_token(headers) else {
+            return false;
+        };
+        sent.len() == self.token.len()
+            && sent
+                .bytes()
+                .zip(self.token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+/// bearer_token is the token from an `Authorization: Bearer <token>` header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// require_token rejects protected requests that don't carry the api token
+/// with 401
+pub async fn require_token(
+    State(token): State<ApiToken>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !token.is_protected(request.method(), request.uri().path())
+        || token.accepts(request.headers())
+    {
+        return next.run(request).await;
+    }
+
+    tracing::info!(
+        "rejected {} {} without a valid api token",
+        request.method(),
+        request.uri().path()
+    );
+    let mut response = ApiError::unauthorized().into_response();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::StatusCode,
+        middleware,
+        routing::{get, post},
+        Router,
+    };
+    use tower::Service;
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(None, bearer_token(&headers));
+
+        headers.insert(header::AUTHORIZATION, "Basic c2VjcmV0".parse().unwrap());
+        assert_eq!(None, bearer_token(&headers));
+
+        headers.insert(header::AUTHORIZATION, "bearer secret".parse().unwrap());
+        assert_eq!(Some("secret"), bearer_token(&headers));
+    }
+
+    #[tokio::test]
+    async fn test_require_token() {
+        let app = |protect_reads| {
+            Router::new()
+                .route("/api/entries", get(|| async { "entries" }))
+                .route("/api/entries", post(|| async { "created" }))
+                .route("/api/shared/:token", get(|| async { "shared" }))
+                .route("/health", post(|| async { "ok" }))
+                .layer(middleware::from_fn_with_state(
+                    ApiToken::new("secret".to_string(), protect_reads),
+                    require_token,
+                ))
+        };
+        let request = |method, uri, token: Option<&str>| {
+            let mut builder = axum::http::Request::builder().method(method).uri(uri);
+            if let Some(token) = token {
+                builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        let mut open_reads = app(false);
+        let response = open_reads
+            .call(request(Method::POST, "/api/entries", None))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        assert_eq!("Bearer", response.headers()[header::WWW_AUTHENTICATE]);
+
+        let response = open_reads
+            .call(request(Method::POST, "/api/entries", Some("wrong")))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        let response = open_reads
+            .call(request(Method::POST, "/api/entries", Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = open_reads
+            .call(request(Method::GET, "/api/entries", None))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = open_reads
+            .call(request(Method::POST, "/health", None))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let mut closed_reads = app(true);
+        let response = closed_reads
+            .call(request(Method::GET, "/api/entries", None))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        let response = closed_reads
+            .call(request(Method::GET, "/api/entries", Some("secret")))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        // a shared list can be viewed with just its own token
+        let response = closed_reads
+            .call(request(Method::GET, "/api/shared/abc", None))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+    }
+}
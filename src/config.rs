@@ -0,0 +1,485 @@
+use std::{
+    env, fs, io,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono_tz::Tz;
+use serde::Deserialize;
+
+use crate::database::{self, PoolConfig};
+use crate::timestamp;
+
+/// DEFAULT_DEMO_RESET_SECS is how often the demo database is reset when
+/// GL_DEMO_RESET_SECS is not set
+const DEFAULT_DEMO_RESET_SECS: u64 = 15 * 60;
+
+/// DEFAULT_RATE_LIMIT_PER_MINUTE is how many requests a client can make per
+/// minute when GL_RATE_LIMIT_PER_MINUTE is not set
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+
+/// DEFAULT_REQUEST_TIMEOUT_SECS is how long a request can take before it is
+/// failed when GL_REQUEST_TIMEOUT_SECS is not set
+const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// DEFAULT_MAX_BODY_BYTES is the largest request body that is accepted when
+/// GL_MAX_BODY_BYTES is not set. That is plenty for importing a large list,
+/// but stops a client from making the server buffer an unbounded body.
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// DEFAULT_MAX_CONCURRENT_WRITES is how many requests that change something
+/// are handled at once when GL_MAX_CONCURRENT_WRITES is not set. sqlite only
+/// writes one at a time, so a few are enough to keep it busy.
+const DEFAULT_MAX_CONCURRENT_WRITES: usize = 4;
+
+/// DEFAULT_CONFIG_PATH is where the config file is looked for when GL_CONFIG
+/// doesn't point somewhere else
+const DEFAULT_CONFIG_PATH: &str = "gl.toml";
+
+/// Config is everything gl reads from its config file and the environment at
+/// startup
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub database_url: String,
+    /// bind_addr is the address the server listens on, like 127.0.0.1 to only
+    /// be reachable from the same machine
+    pub bind_addr: IpAddr,
+    pub port: String,
+    /// busy_timeout is how long a connection waits on a locked database
+    pub busy_timeout: Duration,
+    /// pool is how many database connections are kept and how long a request
+    /// waits for one
+    pub pool: PoolConfig,
+    /// is_demo is whether gl is running as the public demo, which resets its
+    /// database from demo_db_path every demo_reset_interval
+    pub is_demo: bool,
+    /// demo_db_path is the seed database the demo is reset to
+    pub demo_db_path: PathBuf,
+    pub demo_reset_interval: Duration,
+    /// metrics_enabled is whether /metrics is served
+    pub metrics_enabled: bool,
+    /// static_dir is a directory to serve the frontend from instead of the
+    /// copy embedded at build time, so a frontend rebuild shows up without
+    /// rebuilding gl
+    pub static_dir: Option<PathBuf>,
+    /// cors_origins is a comma separated list of allowed origins, or every
+    /// origin when unset
+    pub cors_origins: Option<String>,
+    /// rate_limit_per_minute is how many requests each client ip can make per
+    /// minute, where 0 turns rate limiting off
+    pub rate_limit_per_minute: u32,
+    /// trust_proxy is whether gl runs behind a reverse proxy that sets
+    /// X-Forwarded-For, so that clients are told apart by the address the
+    /// proxy puts last in it rather than by the proxy's own. Off, the header
+    /// is ignored, since any client could send one.
+    pub trust_proxy: bool,
+    /// request_timeout is how long a request can take to respond before it
+    /// is failed with 503
+    pub request_timeout: Duration,
+    /// max_body_bytes is the largest request body that is accepted, larger
+    /// ones are rejected with 413
+    pub max_body_bytes: usize,
+    /// max_concurrent_writes is how many requests that change something are
+    /// handled at once, with the rest waiting their turn, where 0 turns the
+    /// limit off
+    pub max_concurrent_writes: usize,
+    /// api_token is the bearer token that changes through the api need, or
+    /// none to leave the api open
+    pub api_token: Option<String>,
+    /// api_token_protects_reads is whether reads need api_token too
+    pub api_token_protects_reads: bool,
+    /// timezone is where the local times sent alongside UTC ones, like the
+    /// summary's generated_at_local, are given in. Without one they are left
+    /// out.
+    pub timezone: Option<Tz>,
+}
+
+/// FileConfig is the layout of gl.toml. Every setting is optional, and the
+/// ones that are left out keep their defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    bind_addr: Option<IpAddr>,
+    port: Option<u16>,
+    database_url: Option<String>,
+    busy_timeout_ms: Option<u64>,
+    db_max_connections: Option<u32>,
+    db_min_connections: Option<u32>,
+    db_acquire_timeout_ms: Option<u64>,
+    db_connect_attempts: Option<u32>,
+    db_connect_retry_delay_ms: Option<u64>,
+    demo: Option<bool>,
+    demo_db: Option<PathBuf>,
+    demo_reset_secs: Option<u64>,
+    metrics: Option<bool>,
+    static_dir: Option<PathBuf>,
+    cors_origins: Option<Vec<String>>,
+    rate_limit_per_minute: Option<u32>,
+    trust_proxy: Option<bool>,
+    request_timeout_secs: Option<u64>,
+    max_body_bytes: Option<usize>,
+    max_concurrent_writes: Option<usize>,
+    api_token: Option<String>,
+    api_token_protects_reads: Option<bool>,
+    timezone: Option<Tz>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database_url: "sqlite:grocery.db".to_string(),
+            bind_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: "3001".to_string(),
+            busy_timeout: Duration::from_millis(database::DEFAULT_BUSY_TIMEOUT_MS),
+            pool: PoolConfig::default(),
+            is_demo: false,
+            demo_db_path: PathBuf::from("grocery_demo.db"),
+            demo_reset_interval: Duration::from_secs(DEFAULT_DEMO_RESET_SECS),
+            metrics_enabled: false,
+            static_dir: None,
+            cors_origins: None,
+            rate_limit_per_minute: DEFAULT_RATE_LIMIT_PER_MINUTE,
+            trust_proxy: false,
+            request_timeout: Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_concurrent_writes: DEFAULT_MAX_CONCURRENT_WRITES,
+            api_token: None,
+            api_token_protects_reads: false,
+            timezone: None,
+        }
+    }
+}
+
+impl Config {
+    /// load reads the config file named by GL_CONFIG, or gl.toml, and then
+    /// applies any overrides from the environment. A missing file just means
+    /// the defaults are used, but one that can't be parsed is an error.
+    pub fn load() -> anyhow::Result<Self> {
+        let path = env::var("GL_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string());
+
+        let file = match fs::read_to_string(&path) {
+            Ok(contents) => {
+                tracing::info!("Loading config from {}", path);
+                parse_file(&contents).with_context(|| format!("invalid config file {}", path))?
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => FileConfig::default(),
+            Err(e) => {
+                return Err(e).with_context(|| format!("failed to read config file {}", path))
+            }
+        };
+
+        let config = Self::default().with_file(file).with_env()?;
+        config.check_demo_db()?;
+        Ok(config)
+    }
+
+    /// check_demo_db makes sure the demo has a seed database to reset to, so
+    /// that a missing one stops gl from starting rather than failing the
+    /// first reset
+    fn check_demo_db(&self) -> anyhow::Result<()> {
+        if self.is_demo && !self.demo_db_path.is_file() {
+            anyhow::bail!(
+                "demo database {} does not exist, set GL_DEMO_DB to the seed database",
+                self.demo_db_path.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// with_file applies the settings from the config file
+    fn with_file(self, file: FileConfig) -> Self {
+        Self {
+            database_url: file.database_url.unwrap_or(self.database_url),
+            bind_addr: file.bind_addr.unwrap_or(self.bind_addr),
+            port: file.port.map(|port| port.to_string()).unwrap_or(self.port),
+            busy_timeout: file
+                .busy_timeout_ms
+                .map(Duration::from_millis)
+                .unwrap_or(self.busy_timeout),
+            pool: PoolConfig {
+                max_connections: file
+                    .db_max_connections
+                    .filter(|max| *max > 0)
+                    .unwrap_or(self.pool.max_connections),
+                min_connections: file.db_min_connections.unwrap_or(self.pool.min_connections),
+                acquire_timeout: file
+                    .db_acquire_timeout_ms
+                    .filter(|ms| *ms > 0)
+                    .map(Duration::from_millis)
+                    .unwrap_or(self.pool.acquire_timeout),
+                connect_attempts: file
+                    .db_connect_attempts
+                    .filter(|attempts| *attempts > 0)
+                    .unwrap_or(self.pool.connect_attempts),
+                connect_retry_delay: file
+                    .db_connect_retry_delay_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(self.pool.connect_retry_delay),
+            },
+            is_demo: file.demo.unwrap_or(self.is_demo),
+            demo_db_path: file.demo_db.unwrap_or(self.demo_db_path),
+            demo_reset_interval: file
+                .demo_reset_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(self.demo_reset_interval),
+            metrics_enabled: file.metrics.unwrap_or(self.metrics_enabled),
+            static_dir: file.static_dir.or(self.static_dir),
+            cors_origins: file
+                .cors_origins
+                .map(|origins| origins.join(","))
+                .or(self.cors_origins),
+            rate_limit_per_minute: file
+                .rate_limit_per_minute
+                .unwrap_or(self.rate_limit_per_minute),
+            trust_proxy: file.trust_proxy.unwrap_or(self.trust_proxy),
+            request_timeout: file
+                .request_timeout_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(self.request_timeout),
+            max_body_bytes: file.max_body_bytes.unwrap_or(self.max_body_bytes),
+            max_concurrent_writes: file
+                .max_concurrent_writes
+                .unwrap_or(self.max_concurrent_writes),
+            api_token: file
+                .api_token
+                .filter(|token| !token.is_empty())
+                .or(self.api_token),
+            api_token_protects_reads: file
+                .api_token_protects_reads
+                .unwrap_or(self.api_token_protects_reads),
+            timezone: file.timezone.or(self.timezone),
+        }
+    }
+
+    /// with_env applies the overrides from environment variables that are set
+    /// and parse. A bind address that doesn't parse is an error rather than
+    /// being ignored, since falling back to every interface could expose the
+    /// server where it wasn't meant to be. So is a timezone, which would
+    /// otherwise quietly leave the local times out.
+    fn with_env(self) -> anyhow::Result<Self> {
+        let bind_addr = match env::var("GL_BIND_ADDR") {
+            Ok(addr) => parse_bind_addr(&addr)?,
+            Err(_) => self.bind_addr,
+        };
+        let timezone = match env::var("GL_TZ") {
+            Ok(name) if !name.is_empty() => Some(timestamp::parse_timezone(&name)?),
+            _ => self.timezone,
+        };
+
+        Ok(Self {
+            database_url: env::var("DATABASE_URL").unwrap_or(self.database_url),
+            bind_addr,
+            port: env::var("PORT").unwrap_or(self.port),
+            busy_timeout: env_parse::<u64>("GL_DB_BUSY_TIMEOUT_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(self.busy_timeout),
+            // a pool without connections or a timeout of zero would fail every
+            // query, and no attempts would never open it, so treat them as unset
+            pool: PoolConfig {
+                max_connections: env_parse::<u32>("GL_DB_MAX_CONNECTIONS")
+                    .filter(|max| *max > 0)
+                    .unwrap_or(self.pool.max_connections),
+                min_connections: env_parse("GL_DB_MIN_CONNECTIONS")
+                    .unwrap_or(self.pool.min_connections),
+                acquire_timeout: env_parse::<u64>("GL_DB_ACQUIRE_TIMEOUT_MS")
+                    .filter(|ms| *ms > 0)
+                    .map(Duration::from_millis)
+                    .unwrap_or(self.pool.acquire_timeout),
+                connect_attempts: env_parse::<u32>("GL_DB_CONNECT_ATTEMPTS")
+                    .filter(|attempts| *attempts > 0)
+                    .unwrap_or(self.pool.connect_attempts),
+                connect_retry_delay: env_parse::<u64>("GL_DB_CONNECT_RETRY_DELAY_MS")
+                    .map(Duration::from_millis)
+                    .unwrap_or(self.pool.connect_retry_delay),
+            },
+            is_demo: env_parse("GL_DEMO").unwrap_or(self.is_demo),
+            demo_db_path: env::var("GL_DEMO_DB")
+                .ok()
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or(self.demo_db_path),
+            // an interval of zero would make the ticker panic, so treat it as
+            // unset
+            demo_reset_interval: env_parse::<u64>("GL_DEMO_RESET_SECS")
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(self.demo_reset_interval),
+            metrics_enabled: env_parse("GL_METRICS").unwrap_or(self.metrics_enabled),
+            static_dir: env::var("GL_STATIC_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .or(self.static_dir),
+            cors_origins: env::var("GL_CORS_ORIGINS").ok().or(self.cors_origins),
+            rate_limit_per_minute: env_parse("GL_RATE_LIMIT_PER_MINUTE")
+                .unwrap_or(self.rate_limit_per_minute),
+            trust_proxy: env_parse("GL_TRUST_PROXY").unwrap_or(self.trust_proxy),
+            // a timeout of zero would fail every request, so treat it as unset
+            request_timeout: env_parse::<u64>("GL_REQUEST_TIMEOUT_SECS")
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(self.request_timeout),
+            max_body_bytes: env_parse("GL_MAX_BODY_BYTES").unwrap_or(self.max_body_bytes),
+            max_concurrent_writes: env_parse("GL_MAX_CONCURRENT_WRITES")
+                .unwrap_or(self.max_concurrent_writes),
+            // an empty token would let anyone in, so treat it as unset
+            api_token: env::var("GL_API_TOKEN")
+                .ok()
+                .filter(|token| !token.is_empty())
+                .or(self.api_token),
+            api_token_protects_reads: env_parse("GL_API_TOKEN_PROTECTS_READS")
+                .unwrap_or(self.api_token_protects_reads),
+            timezone,
+        })
+    }
+}
+
+/// parse_bind_addr parses the ip address given as GL_BIND_ADDR
+fn parse_bind_addr(addr: &str) -> anyhow::Result<IpAddr> {
+    addr.trim().parse().with_context(|| {
+        format!(
+            "GL_BIND_ADDR {:?} is not an ip address like 127.0.0.1 or ::1",
+            addr
+        )
+    })
+}
+
+/// parse_file parses the contents of a config file
+fn parse_file(contents: &str) -> Result<FileConfig, toml::de::Error> {
+    toml::from_str(contents)
+}
+
+/// env_parse reads an environment variable, if it is set and parses
+fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok().and_then(|value| value.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[test]
+    fn test_file_overrides_defaults() {
+        let file = parse_file(
+            r#"
+            port = 8080
+            demo = true
+            static_dir = "ts/dist"
+            trust_proxy = true
+            cors_origins = ["https://gl.example.com", "http://localhost:5173"]
+            "#,
+        )
+        .unwrap();
+        let config = Config::default().with_file(file);
+
+        assert_eq!("8080", config.port);
+        assert!(config.is_demo);
+        assert_eq!(Some(PathBuf::from("ts/dist")), config.static_dir);
+        assert!(config.trust_proxy);
+        assert_eq!(
+            Some("https://gl.example.com,http://localhost:5173".to_string()),
+            config.cors_origins
+        );
+        assert_eq!(Config::default().database_url, config.database_url);
+    }
+
+    #[test]
+    fn test_parse_bind_addr() {
+        assert_eq!(
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            parse_bind_addr("127.0.0.1").unwrap()
+        );
+        assert!(parse_bind_addr("::1").unwrap().is_loopback());
+        assert!(parse_bind_addr("localhost").is_err());
+        assert!(parse_bind_addr("127.0.0.1:3001").is_err());
+
+        let file = parse_file("bind_addr = \"127.0.0.1\"").unwrap();
+        assert!(Config::default().with_file(file).bind_addr.is_loopback());
+        assert!(parse_file("bind_addr = \"nowhere\"").is_err());
+    }
+
+    #[test]
+    fn test_timezone_from_file() {
+        let file = parse_file("timezone = \"Europe/London\"").unwrap();
+        assert_eq!(
+            Some(Tz::Europe__London),
+            Config::default().with_file(file).timezone
+        );
+        assert_eq!(None, Config::default().timezone);
+        assert!(parse_file("timezone = \"Europe/Atlantis\"").is_err());
+    }
+
+    #[test]
+    fn test_check_demo_db() {
+        let seed = TempPath::new("seed");
+        let demo = |path: &std::path::Path| Config {
+            is_demo: true,
+            demo_db_path: path.to_path_buf(),
+            ..Config::default()
+        };
+
+        assert!(demo(&seed).check_demo_db().is_err());
+        fs::write(&*seed, b"").unwrap();
+        let found = demo(&seed).check_demo_db();
+
+        assert!(found.is_ok());
+        // the seed isn't needed outside of the demo
+        assert!(Config {
+            demo_db_path: seed.to_path_buf(),
+            ..Config::default()
+        }
+        .check_demo_db()
+        .is_ok());
+
+        let file = parse_file("demo_db = \"curated.db\"").unwrap();
+        assert_eq!(
+            PathBuf::from("curated.db"),
+            Config::default().with_file(file).demo_db_path
+        );
+    }
+
+    #[test]
+    fn test_pool_from_file() {
+        let file = parse_file(
+            r#"
+            db_max_connections = 4
+            db_min_connections = 1
+            db_acquire_timeout_ms = 2500
+            db_connect_attempts = 10
+            db_connect_retry_delay_ms = 100
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            PoolConfig {
+                max_connections: 4,
+                min_connections: 1,
+                acquire_timeout: Duration::from_millis(2500),
+                connect_attempts: 10,
+                connect_retry_delay: Duration::from_millis(100),
+            },
+            Config::default().with_file(file).pool
+        );
+
+        let file = parse_file(
+            "db_max_connections = 0\ndb_acquire_timeout_ms = 0\ndb_connect_attempts = 0",
+        )
+        .unwrap();
+        assert_eq!(
+            PoolConfig::default(),
+            Config::default().with_file(file).pool
+        );
+    }
+
+    #[test]
+    fn test_invalid_file() {
+        assert!(parse_file("port = ").is_err());
+        assert!(parse_file("port = \"not a number\"").is_err());
+        assert!(parse_file("prot = 8080").is_err());
+    }
+}
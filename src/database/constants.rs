@@ -1,3 +1,7 @@
+/// DEFAULT_LIST_ID is the id of the default list, which must exist and is used
+/// whenever a request does not specify a list
+pub const DEFAULT_LIST_ID: i64 = 1;
+
 /// DEFAULT_CATEGORY_ID is the category of the default category, which must exist and must be the first category order by id
 pub const DEFAULT_CATEGORY_ID: i64 = 1;
 pub const DEFAULT_CATEGORY_NAME: &str = "Uncategorized";
@@ -14,9 +18,84 @@ pub const ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION: i64 =
 /// value will not overflow.
 pub const MAX_NUM_POSITIONED_GROCERY_ITEMS: i64 = 100000;
 
+/// DEFAULT_BUSY_TIMEOUT_MS is how many milliseconds a connection waits on a
+/// locked database before giving up with SQLITE_BUSY, unless configured
+/// otherwise
+pub const DEFAULT_BUSY_TIMEOUT_MS: u64 = 5000;
+
+/// DEFAULT_MAX_CONNECTIONS, DEFAULT_MIN_CONNECTIONS and
+/// DEFAULT_ACQUIRE_TIMEOUT_MS size the connection pool unless configured
+/// otherwise. They are the sqlx defaults.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+pub const DEFAULT_MIN_CONNECTIONS: u32 = 0;
+pub const DEFAULT_ACQUIRE_TIMEOUT_MS: u64 = 30_000;
+
+/// DEFAULT_CONNECT_ATTEMPTS is how many times opening the database is tried
+/// before giving up, and DEFAULT_CONNECT_RETRY_DELAY_MS how long to wait
+/// before the first retry, doubling for each one after, unless configured
+/// otherwise. That rides out a volume that takes a few seconds to be mounted.
+pub const DEFAULT_CONNECT_ATTEMPTS: u32 = 5;
+pub const DEFAULT_CONNECT_RETRY_DELAY_MS: u64 = 250;
+
+/// TRASH_RETENTION_DAYS is how long a deleted entry stays in the trash, and
+/// can be restored, before it is purged for good
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+
+/// MAX_ENTRY_PRIORITY is the most urgent priority an entry can have. Entries
+/// default to priority 0, which is normal.
+pub const MAX_ENTRY_PRIORITY: i64 = 3;
+
+/// MAX_ENTRY_DESCRIPTION_LEN is the most characters an entry's description
+/// can have
+pub const MAX_ENTRY_DESCRIPTION_LEN: usize = 200;
+
+/// MAX_ENTRY_PRICE_CENTS is the most an entry's price can be, which is far
+/// more than any grocery costs but keeps a list's total from overflowing
+pub const MAX_ENTRY_PRICE_CENTS: i64 = 100_000_000;
+
+/// MAX_ENTRY_NOTES_LEN is the most characters an entry's notes can have
+pub const MAX_ENTRY_NOTES_LEN: usize = 1000;
+
+/// MAX_TAG_LEN is the most characters a tag can have
+pub const MAX_TAG_LEN: usize = 50;
+
+/// DEFAULT_SUGGESTIONS_LIMIT is how many suggestions are returned when the
+/// request does not ask for a number, and MAX_SUGGESTIONS_LIMIT is the most it
+/// can ask for
+pub const DEFAULT_SUGGESTIONS_LIMIT: i64 = 10;
+pub const MAX_SUGGESTIONS_LIMIT: i64 = 50;
+
+/// DEFAULT_AUDIT_LIMIT is how many audit log entries are returned when the
+/// request does not ask for a number, and MAX_AUDIT_LIMIT is the most it can
+/// ask for
+pub const DEFAULT_AUDIT_LIMIT: i64 = 50;
+pub const MAX_AUDIT_LIMIT: i64 = 500;
+
+/// IDEMPOTENCY_KEY_TTL_HOURS is how long an Idempotency-Key is remembered, so
+/// that retrying a request with it returns the entry it created the first
+/// time rather than a duplicate
+pub const IDEMPOTENCY_KEY_TTL_HOURS: i64 = 24;
+
+/// MAX_IDEMPOTENCY_KEY_LEN is the most characters an Idempotency-Key can have
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// MIN_FUZZY_SUGGESTIONS_QUERY_LEN is how many characters have to be typed
+/// before suggestions tolerate typos, since anything shorter is within a typo
+/// or two of nearly everything
+pub const MIN_FUZZY_SUGGESTIONS_QUERY_LEN: usize = 3;
+
 
 pub const TABLE_NAME_GROCERY_LIST_ENTRIES: &str = "grocery_list_entries";
 pub const TABLE_NAME_CATEGORIES: &str = "categories";
+pub const TABLE_NAME_LISTS: &str = "lists";
+pub const TABLE_NAME_ARCHIVED_TRIPS: &str = "archived_trips";
+pub const TABLE_NAME_TEMPLATES: &str = "templates";
+pub const TABLE_NAME_PURCHASE_EVENTS: &str = "purchase_events";
+pub const TABLE_NAME_LIST_SHARES: &str = "list_shares";
+pub const TABLE_NAME_TAGS: &str = "tags";
+pub const TABLE_NAME_ENTRY_TAGS: &str = "entry_tags";
+pub const TABLE_NAME_AUDIT_LOG: &str = "audit_log";
+pub const TABLE_NAME_IDEMPOTENCY_KEYS: &str = "idempotency_keys";
 
 pub const GROCERY_LIST_ENTRIES_ID: &str = "id";
 pub const GROCERY_LIST_ENTRIES_DESCRIPTION: &str = "description";
@@ -24,20 +103,40 @@ pub const GROCERY_LIST_ENTRIES_COMPLETED_AT: &str = "completed_at";
 pub const GROCERY_LIST_ENTRIES_ARCHIVED_AT: &str = "archived_at";
 pub const GROCERY_LIST_ENTRIES_POSITION: &str = "position";
 pub const GROCERY_LIST_ENTRIES_QUANTITY: &str = "quantity";
+pub const GROCERY_LIST_ENTRIES_UNIT: &str = "unit";
 pub const GROCERY_LIST_ENTRIES_NOTES: &str = "notes";
 pub const GROCERY_LIST_ENTRIES_CATEGORY_ID: &str = "category_id";
 pub const GROCERY_LIST_ENTRIES_UPDATED_AT: &str = "updated_at";
+pub const GROCERY_LIST_ENTRIES_LIST_ID: &str = "list_id";
+pub const GROCERY_LIST_ENTRIES_DELETED_AT: &str = "deleted_at";
+pub const GROCERY_LIST_ENTRIES_CREATED_AT: &str = "created_at";
+pub const GROCERY_LIST_ENTRIES_PRIORITY: &str = "priority";
+pub const GROCERY_LIST_ENTRIES_VERSION: &str = "version";
+pub const GROCERY_LIST_ENTRIES_PRICE_CENTS: &str = "price_cents";
+pub const GROCERY_LIST_ENTRIES_CURRENCY: &str = "currency";
+pub const GROCERY_LIST_ENTRIES_PINNED: &str = "pinned";
+pub const GROCERY_LIST_ENTRIES_VISIBLE_FROM: &str = "visible_from";
 
-pub const GROCERY_LIST_ENTRIES_FIELDS: [&'static str; 9] = [
+pub const GROCERY_LIST_ENTRIES_FIELDS: [&str; 19] = [
     GROCERY_LIST_ENTRIES_ID,
     GROCERY_LIST_ENTRIES_DESCRIPTION,
     GROCERY_LIST_ENTRIES_COMPLETED_AT,
     GROCERY_LIST_ENTRIES_ARCHIVED_AT,
     GROCERY_LIST_ENTRIES_POSITION,
     GROCERY_LIST_ENTRIES_QUANTITY,
+    GROCERY_LIST_ENTRIES_UNIT,
     GROCERY_LIST_ENTRIES_NOTES,
     GROCERY_LIST_ENTRIES_CATEGORY_ID,
     GROCERY_LIST_ENTRIES_UPDATED_AT,
+    GROCERY_LIST_ENTRIES_LIST_ID,
+    GROCERY_LIST_ENTRIES_DELETED_AT,
+    GROCERY_LIST_ENTRIES_CREATED_AT,
+    GROCERY_LIST_ENTRIES_PRIORITY,
+    GROCERY_LIST_ENTRIES_VERSION,
+    GROCERY_LIST_ENTRIES_PRICE_CENTS,
+    GROCERY_LIST_ENTRIES_CURRENCY,
+    GROCERY_LIST_ENTRIES_PINNED,
+    GROCERY_LIST_ENTRIES_VISIBLE_FROM,
 ];
 
 pub const CATEGORIES_ID: &str = "id";
@@ -45,11 +144,109 @@ pub const CATEGORIES_NAME: &str = "name";
 pub const CATEGORIES_IS_DEFAULT_CATEGORY: &str = "is_default_category";
 pub const CATEGORIES_POSITION: &str = "position";
 pub const CATEGORIES_UPDATED_AT: &str = "updated_at";
+pub const CATEGORIES_LIST_ID: &str = "list_id";
+pub const CATEGORIES_CREATED_AT: &str = "created_at";
+pub const CATEGORIES_VERSION: &str = "version";
+pub const CATEGORIES_COLOR: &str = "color";
+pub const CATEGORIES_ICON: &str = "icon";
+pub const CATEGORIES_MAX_ENTRIES: &str = "max_entries";
 
-pub const CATEGORIES_FIELDS: [&'static str; 5] = [
+pub const CATEGORIES_FIELDS: [&str; 11] = [
     CATEGORIES_ID,
     CATEGORIES_NAME,
     CATEGORIES_IS_DEFAULT_CATEGORY,
     CATEGORIES_POSITION,
     CATEGORIES_UPDATED_AT,
+    CATEGORIES_LIST_ID,
+    CATEGORIES_CREATED_AT,
+    CATEGORIES_VERSION,
+    CATEGORIES_COLOR,
+    CATEGORIES_ICON,
+    CATEGORIES_MAX_ENTRIES,
 ];
+
+pub const LISTS_ID: &str = "id";
+pub const LISTS_NAME: &str = "name";
+pub const LISTS_UPDATED_AT: &str = "updated_at";
+
+pub const LISTS_FIELDS: [&str; 3] = [LISTS_ID, LISTS_NAME, LISTS_UPDATED_AT];
+
+pub const LIST_SHARES_LIST_ID: &str = "list_id";
+pub const LIST_SHARES_TOKEN: &str = "token";
+pub const LIST_SHARES_CREATED_AT: &str = "created_at";
+
+pub const LIST_SHARES_FIELDS: [&str; 3] = [
+    LIST_SHARES_LIST_ID,
+    LIST_SHARES_TOKEN,
+    LIST_SHARES_CREATED_AT,
+];
+
+pub const ARCHIVED_TRIPS_ID: &str = "id";
+pub const ARCHIVED_TRIPS_LIST_ID: &str = "list_id";
+pub const ARCHIVED_TRIPS_ARCHIVED_AT: &str = "archived_at";
+pub const ARCHIVED_TRIPS_ENTRIES: &str = "entries";
+
+pub const ARCHIVED_TRIPS_FIELDS: [&str; 4] = [
+    ARCHIVED_TRIPS_ID,
+    ARCHIVED_TRIPS_LIST_ID,
+    ARCHIVED_TRIPS_ARCHIVED_AT,
+    ARCHIVED_TRIPS_ENTRIES,
+];
+
+pub const TEMPLATES_ID: &str = "id";
+pub const TEMPLATES_NAME: &str = "name";
+pub const TEMPLATES_ITEMS: &str = "items";
+pub const TEMPLATES_CREATED_AT: &str = "created_at";
+pub const TEMPLATES_UPDATED_AT: &str = "updated_at";
+
+pub const TEMPLATES_FIELDS: [&str; 5] = [
+    TEMPLATES_ID,
+    TEMPLATES_NAME,
+    TEMPLATES_ITEMS,
+    TEMPLATES_CREATED_AT,
+    TEMPLATES_UPDATED_AT,
+];
+
+pub const PURCHASE_EVENTS_ID: &str = "id";
+pub const PURCHASE_EVENTS_ENTRY_ID: &str = "entry_id";
+pub const PURCHASE_EVENTS_LIST_ID: &str = "list_id";
+pub const PURCHASE_EVENTS_DESCRIPTION: &str = "description";
+pub const PURCHASE_EVENTS_QUANTITY: &str = "quantity";
+pub const PURCHASE_EVENTS_UNIT: &str = "unit";
+pub const PURCHASE_EVENTS_PURCHASED_AT: &str = "purchased_at";
+
+pub const PURCHASE_EVENTS_FIELDS: [&str; 7] = [
+    PURCHASE_EVENTS_ID,
+    PURCHASE_EVENTS_ENTRY_ID,
+    PURCHASE_EVENTS_LIST_ID,
+    PURCHASE_EVENTS_DESCRIPTION,
+    PURCHASE_EVENTS_QUANTITY,
+    PURCHASE_EVENTS_UNIT,
+    PURCHASE_EVENTS_PURCHASED_AT,
+];
+
+pub const TAGS_ID: &str = "id";
+pub const TAGS_NAME: &str = "name";
+
+pub const ENTRY_TAGS_ENTRY_ID: &str = "entry_id";
+pub const ENTRY_TAGS_TAG_ID: &str = "tag_id";
+
+pub const AUDIT_LOG_ID: &str = "id";
+pub const AUDIT_LOG_CREATED_AT: &str = "created_at";
+pub const AUDIT_LOG_TABLE_NAME: &str = "table_name";
+pub const AUDIT_LOG_ROW_ID: &str = "row_id";
+pub const AUDIT_LOG_ACTION: &str = "action";
+pub const AUDIT_LOG_DIFF: &str = "diff";
+
+pub const AUDIT_LOG_FIELDS: [&str; 6] = [
+    AUDIT_LOG_ID,
+    AUDIT_LOG_CREATED_AT,
+    AUDIT_LOG_TABLE_NAME,
+    AUDIT_LOG_ROW_ID,
+    AUDIT_LOG_ACTION,
+    AUDIT_LOG_DIFF,
+];
+
+pub const IDEMPOTENCY_KEYS_KEY: &str = "key";
+pub const IDEMPOTENCY_KEYS_ENTRY_ID: &str = "entry_id";
+pub const IDEMPOTENCY_KEYS_CREATED_AT: &str = "created_at";
@@ -1,50 +1,450 @@
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
 
 use crate::models::{
-    category::{Category, CreateCategory, ReorderCategory, UpdateCategory},
+    archive::ArchivedTrip,
+    audit::{AuditAction, AuditEntry, AuditTable},
+    category::{Category, CreateCategory, OrderCategories, ReorderCategory, UpdateCategory},
+    export::{ExportCategory, ExportDocument, ExportList, ImportMode, ImportSummary},
     grocery_entry::{
-        CreateGroceryListEntry, GroceryListEntry, ReorderEntry, UpdateGroceryListEntry,
+        ApiGroceryListEntry, CreateGroceryListEntry, EntrySort, GroceryListEntry, MoveEntry,
+        OrderEntries, ReorderEntry, UpdateGroceryListEntry,
     },
+    list::{CreateList, List, ListShare, UpdateList},
+    purchase::PurchaseEvent,
+    summary::{CostSummary, CostTotal, Summary},
+    template::{CreateTemplate, Template},
 };
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use sqlx::{
     migrate::MigrateDatabase,
-    sqlite::{SqliteConnectOptions, SqlitePool},
+    sqlite::{
+        SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePool, SqlitePoolOptions,
+    },
     ConnectOptions, FromRow, Row, Sqlite,
 };
 
 mod constants;
-pub use constants::DEFAULT_CATEGORY_ID;
 use constants::*;
+pub use constants::{
+    DEFAULT_ACQUIRE_TIMEOUT_MS, DEFAULT_AUDIT_LIMIT, DEFAULT_BUSY_TIMEOUT_MS, DEFAULT_CATEGORY_ID,
+    DEFAULT_CONNECT_ATTEMPTS, DEFAULT_CONNECT_RETRY_DELAY_MS, DEFAULT_LIST_ID,
+    DEFAULT_MAX_CONNECTIONS, DEFAULT_MIN_CONNECTIONS, DEFAULT_SUGGESTIONS_LIMIT, MAX_AUDIT_LIMIT,
+    MAX_ENTRY_DESCRIPTION_LEN, MAX_ENTRY_NOTES_LEN, MAX_ENTRY_PRICE_CENTS, MAX_ENTRY_PRIORITY,
+    MAX_IDEMPOTENCY_KEY_LEN, MAX_SUGGESTIONS_LIMIT, MAX_TAG_LEN,
+};
+use tokio_stream::Stream;
 use tracing::log;
 
 pub struct Database {
     pub pool: SqlitePool,
 }
 
+/// PoolConfig sizes the connection pool. The pool opens up to max_connections,
+/// keeps at least min_connections open, and a query waits up to
+/// acquire_timeout for a free one. Opening the pool is tried connect_attempts
+/// times, waiting connect_retry_delay before the first retry and twice as long
+/// before each one after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    pub connect_attempts: u32,
+    pub connect_retry_delay: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            min_connections: DEFAULT_MIN_CONNECTIONS,
+            acquire_timeout: Duration::from_millis(DEFAULT_ACQUIRE_TIMEOUT_MS),
+            connect_attempts: DEFAULT_CONNECT_ATTEMPTS,
+            connect_retry_delay: Duration::from_millis(DEFAULT_CONNECT_RETRY_DELAY_MS),
+        }
+    }
+}
+
+/// VersionedUpdate is the outcome of an update that only applies if the row is
+/// still at the version the client last saw
+#[derive(Debug)]
+pub enum VersionedUpdate<T> {
+    Updated(T),
+    /// Conflict means the row has changed since the client saw it, it holds
+    /// the row as it currently is so the client can merge
+    Conflict(T),
+    NotFound,
+}
+
+/// ReorderOutcome is the outcome of a reorder, which is refused without
+/// changing anything if it names ids that don't belong to what is being
+/// reordered
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReorderOutcome {
+    Reordered,
+    /// InvalidIds describes which of the submitted ids were wrong
+    InvalidIds(String),
+}
+
+/// BatchUpdate is the outcome of updating several entries at once, which is
+/// all or nothing
+#[derive(Debug)]
+pub enum BatchUpdate {
+    Updated(Vec<GroceryListEntry>),
+    /// Conflict means the entry with this id has changed since the client saw
+    /// it, it holds the entry as it currently is
+    Conflict(i64, Box<GroceryListEntry>),
+    NotFound(i64),
+}
+
+/// EntryFilter narrows down which of a list's active entries are fetched. The
+/// default is every one of them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EntryFilter<'a> {
+    /// tag keeps only the entries with it
+    pub tag: Option<&'a str>,
+    /// completed keeps only the done, or the outstanding, entries
+    pub completed: Option<bool>,
+    /// hide_scheduled leaves out the entries that aren't visible_from yet
+    pub hide_scheduled: bool,
+}
+
+/// CategoryFull is the error from adding or moving entries into a category
+/// when that would take it past its max_entries. count is how many active
+/// entries it had without them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CategoryFull {
+    pub category_id: i64,
+    pub max_entries: i64,
+    pub count: i64,
+}
+
+impl std::fmt::Display for CategoryFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "category {} already has {} of its at most {} entries",
+            self.category_id, self.count, self.max_entries
+        )
+    }
+}
+
+impl std::error::Error for CategoryFull {}
+
+/// IdempotencyKeyUsed is the error from creating an entry with an
+/// Idempotency-Key that another request already used, which the caller
+/// answers with get_idempotent_entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdempotencyKeyUsed;
+
+impl std::fmt::Display for IdempotencyKeyUsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "idempotency key was already used")
+    }
+}
+
+impl std::error::Error for IdempotencyKeyUsed {}
+
+/// ImportChanges are the rows an import added and, when replacing, removed,
+/// for the audit log and the events sent to connected clients
+#[derive(Debug, Default)]
+pub struct ImportChanges {
+    pub removed_entries: Vec<GroceryListEntry>,
+    pub removed_categories: Vec<Category>,
+    pub removed_lists: Vec<List>,
+    pub lists: Vec<List>,
+    pub categories: Vec<Category>,
+    pub entries: Vec<GroceryListEntry>,
+}
+
+/// EntryMove is the outcome of moving an entry to another category
+#[derive(Debug)]
+pub enum EntryMove {
+    Moved(Box<GroceryListEntry>),
+    EntryNotFound,
+    /// CategoryNotFound means the category doesn't exist or belongs to a
+    /// different list than the entry
+    CategoryNotFound,
+}
+
+/// Nudge is which way an entry moves when it is nudged one slot along its
+/// category
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nudge {
+    Up,
+    Down,
+}
+
+/// CategoryDeletion is the outcome of deleting a category
+#[derive(Debug, PartialEq, Eq)]
+pub enum CategoryDeletion {
+    Deleted,
+    NotFound,
+    /// IsDefault means the category is its list's default, which can't be
+    /// deleted
+    IsDefault,
+    /// NotEmpty means the category was kept because it still has this many
+    /// active entries
+    NotEmpty(i64),
+}
+
 impl Database {
-    pub async fn new(database_url: &str) -> Result<Self> {
+    /// new opens the database, creating it if needed, and brings its schema up
+    /// to date. Connections use WAL so that readers don't block the writer,
+    /// enforce foreign keys, and wait up to busy_timeout for a lock.
+    pub async fn new(database_url: &str, busy_timeout: Duration) -> Result<Self> {
+        Self::with_pool(database_url, busy_timeout, PoolConfig::default()).await
+    }
+
+    /// with_pool opens the database like new, with a pool sized by pool. A
+    /// min_connections above max_connections is lowered to it. Opening is
+    /// retried with backoff, as set in pool, in case the storage isn't ready
+    /// yet, and the last error is returned once the attempts run out.
+    pub async fn with_pool(
+        database_url: &str,
+        busy_timeout: Duration,
+        pool: PoolConfig,
+    ) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .log_statements(log::LevelFilter::Trace)
+            .journal_mode(SqliteJournalMode::Wal)
+            .foreign_keys(true)
+            .busy_timeout(busy_timeout);
+
+        let attempts = pool.connect_attempts.max(1);
+        let mut delay = pool.connect_retry_delay;
+        let mut attempt = 1;
+        let pool = loop {
+            match Self::connect(database_url, options.clone(), pool).await {
+                Ok(pool) => break pool,
+                Err(e) if attempt < attempts => {
+                    tracing::warn!(
+                        "Failed to open the database on attempt {} of {}, retrying in {}ms: {}",
+                        attempt,
+                        attempts,
+                        delay.as_millis(),
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay = delay.saturating_mul(2);
+                    attempt += 1;
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to open the database on attempt {} of {}, giving up: {}",
+                        attempt,
+                        attempts,
+                        e
+                    );
+                    return Err(e);
+                }
+            }
+        };
+        tracing::info!(
+            "Database pool has {} to {} connections, waiting up to {}ms for one",
+            pool.options().get_min_connections(),
+            pool.options().get_max_connections(),
+            pool.options().get_acquire_timeout().as_millis()
+        );
+
+        Self::from_pool(pool).await
+    }
+
+    /// connect makes one attempt at creating the database if it doesn't exist
+    /// yet and opening the pool, for with_pool to retry
+    async fn connect(
+        database_url: &str,
+        options: SqliteConnectOptions,
+        pool: PoolConfig,
+    ) -> Result<SqlitePool> {
         if !Sqlite::database_exists(database_url).await.unwrap_or(false) {
             Sqlite::create_database(database_url).await?;
         }
 
-        let options =
-            SqliteConnectOptions::from_str(database_url)?.log_statements(log::LevelFilter::Trace).foreign_keys(true);
-
-        let pool = SqlitePool::connect_with(options).await?;
+        Ok(SqlitePoolOptions::new()
+            .max_connections(pool.max_connections)
+            .min_connections(pool.min_connections.min(pool.max_connections))
+            .acquire_timeout(pool.acquire_timeout)
+            .connect_with(options)
+            .await?)
+    }
 
+    /// from_pool uses a pool that is already open, like an in-memory one,
+    /// bringing its schema up to date
+    pub async fn from_pool(pool: SqlitePool) -> Result<Self> {
         let db = Database { pool };
         db.migrate().await?;
+        db.ensure_default_categories().await?;
 
         Ok(db)
     }
 
+    /// migrate applies any migrations the database doesn't have yet, logging
+    /// each one that is applied and the version the schema ends up at
     async fn migrate(&self) -> Result<()> {
-        sqlx::migrate!("./migrations").run(&self.pool).await?;
+        let migrator = sqlx::migrate!("./migrations");
+
+        // a new database doesn't have the migrations table until the first run
+        let applied_before = self.applied_migrations().await.unwrap_or_default();
+
+        migrator.run(&self.pool).await?;
+
+        let applied = self.applied_migrations().await?;
+        let newly_applied = migrator
+            .iter()
+            .filter(|m| applied.contains(&m.version) && !applied_before.contains(&m.version));
+        for migration in newly_applied {
+            tracing::info!(
+                "applied migration {} {}",
+                migration.version,
+                migration.description
+            );
+        }
+        tracing::info!(
+            "database schema is at version {}",
+            applied.last().copied().unwrap_or_default()
+        );
+
+        Ok(())
+    }
+
+    /// applied_migrations gets the versions of the migrations that have been
+    /// applied to the database, in order
+    async fn applied_migrations(&self) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar(
+            "SELECT version FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// ensure_default_categories gives every list that is missing one an
+    /// Uncategorized category for entries to fall back to, at the end of its
+    /// categories
+    async fn ensure_default_categories(&self) -> Result<()> {
+        let result = sqlx::query(&format!(
+            "INSERT OR IGNORE INTO {TABLE_NAME_CATEGORIES}
+            ({CATEGORIES_NAME}, {CATEGORIES_IS_DEFAULT_CATEGORY}, {CATEGORIES_POSITION}, {CATEGORIES_LIST_ID}, {CATEGORIES_CREATED_AT})
+            SELECT ?, TRUE,
+                (SELECT COALESCE(MAX(c.{CATEGORIES_POSITION}), 0) + 1 FROM {TABLE_NAME_CATEGORIES} c WHERE c.{CATEGORIES_LIST_ID} = l.{LISTS_ID}),
+                l.{LISTS_ID}, CURRENT_TIMESTAMP
+            FROM {TABLE_NAME_LISTS} l
+            WHERE NOT EXISTS (
+                SELECT 1 FROM {TABLE_NAME_CATEGORIES} c
+                WHERE c.{CATEGORIES_LIST_ID} = l.{LISTS_ID} AND c.{CATEGORIES_IS_DEFAULT_CATEGORY}
+            )"
+        ))
+        .bind(DEFAULT_CATEGORY_NAME)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            tracing::info!(
+                "created {} missing default categories",
+                result.rows_affected()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// ping checks that a connection can be acquired from the pool and used
+    /// to run a trivial query
+    pub async fn ping(&self) -> Result<()> {
+        let mut conn = self.pool.acquire().await?;
+        sqlx::query("SELECT 1").execute(&mut *conn).await?;
 
         Ok(())
     }
 
+    /// count_entries_and_categories counts the active entries and the
+    /// categories across every list
+    pub async fn count_entries_and_categories(&self) -> Result<(i64, i64)> {
+        let row = sqlx::query(&format!(
+            "SELECT
+                (SELECT COUNT(*) FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+                WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+                AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL) AS entries,
+                (SELECT COUNT(*) FROM {TABLE_NAME_CATEGORIES}) AS categories"
+        ))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((row.get("entries"), row.get("categories")))
+    }
+
+    /// get_summary counts everything in one query, without reading any of the
+    /// rows themselves
+    pub async fn get_summary(&self) -> Result<Summary> {
+        let row = sqlx::query(&format!(
+            "SELECT
+                COUNT(*) AS entries,
+                COUNT({GROCERY_LIST_ENTRIES_COMPLETED_AT}) AS completed_entries,
+                (SELECT COUNT(*) FROM {TABLE_NAME_CATEGORIES}) AS categories,
+                (SELECT COUNT(*) FROM {TABLE_NAME_LISTS}) AS lists
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL"
+        ))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Summary {
+            entries: row.try_get("entries")?,
+            completed_entries: row.try_get("completed_entries")?,
+            categories: row.try_get("categories")?,
+            lists: row.try_get("lists")?,
+            generated_at: Utc::now(),
+            generated_at_local: None,
+        })
+    }
+
+    /// get_cost_summary adds up the prices of the list's entries that are yet
+    /// to be checked off
+    pub async fn get_cost_summary(&self, list_id: i64) -> Result<CostSummary> {
+        let rows = sqlx::query(&format!(
+            "SELECT
+                {GROCERY_LIST_ENTRIES_CURRENCY} AS currency,
+                COALESCE(SUM({GROCERY_LIST_ENTRIES_PRICE_CENTS}), 0) AS total_cents,
+                COUNT({GROCERY_LIST_ENTRIES_PRICE_CENTS}) AS priced,
+                COUNT(*) - COUNT({GROCERY_LIST_ENTRIES_PRICE_CENTS}) AS unpriced
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            AND {GROCERY_LIST_ENTRIES_COMPLETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            GROUP BY {GROCERY_LIST_ENTRIES_CURRENCY}
+            ORDER BY {GROCERY_LIST_ENTRIES_CURRENCY}"
+        ))
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut summary = CostSummary {
+            list_id,
+            totals: Vec::new(),
+            unpriced_entries: 0,
+        };
+        for row in rows {
+            summary.unpriced_entries += row.try_get::<i64, _>("unpriced")?;
+
+            let entries: i64 = row.try_get("priced")?;
+            if entries > 0 {
+                summary.totals.push(CostTotal {
+                    currency: row.try_get("currency")?,
+                    total_cents: row.try_get("total_cents")?,
+                    entries,
+                });
+            }
+        }
+
+        Ok(summary)
+    }
+
     pub async fn get_entry(&self, id: i64) -> Result<GroceryListEntry> {
         let entries = sqlx::query_as::<_, GroceryListEntry>(
         &format!("SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} WHERE {GROCERY_LIST_ENTRIES_ID} = ? LIMIT 1",
@@ -58,23 +458,326 @@ impl Database {
         Ok(entries)
     }
 
-    pub async fn get_active_entries(&self) -> Result<Vec<GroceryListEntry>> {
+    /// get_active_entries gets the active entries in the list that pass
+    /// filter
+    pub async fn get_active_entries(
+        &self,
+        list_id: i64,
+        sort: EntrySort,
+        filter: EntryFilter<'_>,
+    ) -> Result<Vec<GroceryListEntry>> {
         let entries = sqlx::query_as::<_, GroceryListEntry>(&format!(
             "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} 
             WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
-            ORDER BY position",
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            AND {}
+            AND {}
+            AND {}
+            ORDER BY {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_tag_filter(),
+            entry_completed_filter(),
+            entry_visible_filter(),
+            entry_order_by(sort),
+        ))
+        .bind(list_id)
+        .bind(filter.tag)
+        .bind(filter.tag)
+        .bind(filter.completed)
+        .bind(filter.completed)
+        .bind(filter.hide_scheduled)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// get_active_category_entries gets the active entries in the category in
+    /// their manual order
+    pub async fn get_active_category_entries(
+        &self,
+        category_id: i64,
+    ) -> Result<Vec<GroceryListEntry>> {
+        let entries = sqlx::query_as::<_, GroceryListEntry>(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?
+            ORDER BY {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_order_by(EntrySort::Position),
+        ))
+        .bind(category_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// get_active_entries_page gets up to limit of the active entries in the
+    /// list, skipping the first offset, along with how many active entries
+    /// the list has in total. Without a limit everything after offset is
+    /// returned. Only the entries that pass filter are counted and paged.
+    pub async fn get_active_entries_page(
+        &self,
+        list_id: i64,
+        sort: EntrySort,
+        filter: EntryFilter<'_>,
+        limit: Option<i64>,
+        offset: i64,
+    ) -> Result<(Vec<GroceryListEntry>, i64)> {
+        let mut tx = self.pool.begin().await?;
+
+        let total: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            AND {}
+            AND {}
+            AND {}",
+            entry_tag_filter(),
+            entry_completed_filter(),
+            entry_visible_filter(),
+        ))
+        .bind(list_id)
+        .bind(filter.tag)
+        .bind(filter.tag)
+        .bind(filter.completed)
+        .bind(filter.completed)
+        .bind(filter.hide_scheduled)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // sqlite treats a negative limit as no limit
+        let entries = sqlx::query_as::<_, GroceryListEntry>(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            AND {}
+            AND {}
+            AND {}
+            ORDER BY {}
+            LIMIT ? OFFSET ?",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_tag_filter(),
+            entry_completed_filter(),
+            entry_visible_filter(),
+            entry_order_by(sort),
+        ))
+        .bind(list_id)
+        .bind(filter.tag)
+        .bind(filter.tag)
+        .bind(filter.completed)
+        .bind(filter.completed)
+        .bind(filter.hide_scheduled)
+        .bind(limit.unwrap_or(-1))
+        .bind(offset)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((entries, total))
+    }
+
+    /// get_entries_changed_since gets the active entries in the list that were
+    /// updated at or after since, along with the ids of entries that left the
+    /// list at or after since, either by being deleted or archived, and the
+    /// database's time before it looked, which is the since for the next
+    /// call. Timestamps are only kept to the second, so changes made in the
+    /// same second as since are sent again rather than missed. With
+    /// hide_scheduled an entry that became visible since counts as changed,
+    /// and one that was scheduled for later since counts as having left.
+    pub async fn get_entries_changed_since(
+        &self,
+        list_id: i64,
+        since: DateTime<Utc>,
+        hide_scheduled: bool,
+    ) -> Result<(Vec<GroceryListEntry>, Vec<i64>, DateTime<Utc>)> {
+        let since = since.to_rfc3339();
+        // one transaction so that everything is as of the same moment
+        let mut tx = self.pool.begin().await?;
+
+        let as_of: DateTime<Utc> = sqlx::query_scalar("SELECT CURRENT_TIMESTAMP")
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let entries = sqlx::query_as::<_, GroceryListEntry>(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            AND {}
+            AND (
+                datetime({GROCERY_LIST_ENTRIES_UPDATED_AT}) >= datetime(?)
+                OR (? AND datetime({GROCERY_LIST_ENTRIES_VISIBLE_FROM}) >= datetime(?))
+            )
+            ORDER BY {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_visible_filter(),
+            entry_order_by(EntrySort::Position),
+        ))
+        .bind(list_id)
+        .bind(hide_scheduled)
+        .bind(&since)
+        .bind(hide_scheduled)
+        .bind(&since)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let removed_ids = sqlx::query_scalar(&format!(
+            "SELECT {GROCERY_LIST_ENTRIES_ID} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            AND (
+                datetime({GROCERY_LIST_ENTRIES_DELETED_AT}) >= datetime(?)
+                OR datetime({GROCERY_LIST_ENTRIES_ARCHIVED_AT}) >= datetime(?)
+                OR (
+                    ?
+                    AND {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+                    AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+                    AND datetime({GROCERY_LIST_ENTRIES_UPDATED_AT}) >= datetime(?)
+                    AND datetime({GROCERY_LIST_ENTRIES_VISIBLE_FROM}) > datetime('now')
+                )
+            )
+            ORDER BY {GROCERY_LIST_ENTRIES_ID}"
+        ))
+        .bind(list_id)
+        .bind(&since)
+        .bind(&since)
+        .bind(hide_scheduled)
+        .bind(&since)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok((entries, removed_ids, as_of))
+    }
+
+    /// get_scheduled_entries gets the active entries in the list that aren't
+    /// visible_from yet, soonest first
+    pub async fn get_scheduled_entries(&self, list_id: i64) -> Result<Vec<GroceryListEntry>> {
+        let entries = sqlx::query_as::<_, GroceryListEntry>(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            AND datetime({GROCERY_LIST_ENTRIES_VISIBLE_FROM}) > datetime('now')
+            ORDER BY datetime({GROCERY_LIST_ENTRIES_VISIBLE_FROM}), {GROCERY_LIST_ENTRIES_ID}",
             all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
         ))
+        .bind(list_id)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(entries)
     }
 
+    /// search_entries finds the active, visible entries of the list that match
+    /// every one of the terms, where a term matches an entry if its
+    /// description, notes, one of its tags or its category's name contains it.
+    /// Matching is case-insensitive. Entries with more of the terms in their
+    /// description come first, and otherwise they are in the order the list
+    /// shows them.
+    pub async fn search_entries(
+        &self,
+        list_id: i64,
+        terms: &[String],
+    ) -> Result<Vec<GroceryListEntry>> {
+        let patterns: Vec<String> = terms
+            .iter()
+            .map(|term| format!("%{}%", escape_like(term)))
+            .collect();
+
+        let mut query_builder = sqlx::QueryBuilder::new(format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+        ));
+        // the builder binds where it pushes, so the one placeholder of the
+        // visible filter is swapped for the hidden flag's bind
+        let visible = entry_visible_filter();
+        let (before_hide, after_hide) = visible.split_once('?').unwrap();
+        query_builder
+            .push_bind(list_id)
+            .push(format!(" AND {before_hide}"))
+            .push_bind(true)
+            .push(after_hide);
+        for pattern in &patterns {
+            query_builder
+                .push(format!(" AND ({GROCERY_LIST_ENTRIES_DESCRIPTION} LIKE "))
+                .push_bind(pattern)
+                .push(format!(
+                    " ESCAPE '\\' OR {GROCERY_LIST_ENTRIES_NOTES} LIKE "
+                ))
+                .push_bind(pattern)
+                .push(format!(
+                    " ESCAPE '\\' OR {GROCERY_LIST_ENTRIES_ID} IN (
+                        SELECT et.{ENTRY_TAGS_ENTRY_ID} FROM {TABLE_NAME_ENTRY_TAGS} et
+                        JOIN {TABLE_NAME_TAGS} t ON t.{TAGS_ID} = et.{ENTRY_TAGS_TAG_ID}
+                        WHERE t.{TAGS_NAME} LIKE "
+                ))
+                .push_bind(pattern)
+                .push(format!(
+                    " ESCAPE '\\'
+                    ) OR {GROCERY_LIST_ENTRIES_CATEGORY_ID} IN (
+                        SELECT {CATEGORIES_ID} FROM {TABLE_NAME_CATEGORIES}
+                        WHERE {CATEGORIES_LIST_ID} = "
+                ))
+                .push_bind(list_id)
+                .push(format!(" AND {CATEGORIES_NAME} LIKE "))
+                .push_bind(pattern)
+                .push(" ESCAPE '\\'))");
+        }
+
+        query_builder.push(" ORDER BY 0");
+        for pattern in &patterns {
+            query_builder
+                .push(format!(" + ({GROCERY_LIST_ENTRIES_DESCRIPTION} LIKE "))
+                .push_bind(pattern)
+                .push(" ESCAPE '\\')");
+        }
+        query_builder.push(format!(
+            " DESC,
+                (
+                    SELECT {CATEGORIES_POSITION} FROM {TABLE_NAME_CATEGORIES}
+                    WHERE {CATEGORIES_ID} = {GROCERY_LIST_ENTRIES_CATEGORY_ID}
+                ),
+                {}",
+            entry_order_by(EntrySort::Position)
+        ));
+
+        let entries = query_builder
+            .build_query_as::<GroceryListEntry>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(entries)
+    }
+
     /// get_next_position_for_item_in_category gets the next position available
     /// for an item going into the specified category (i.e. what position to
     /// append it to the end of the list)
     pub async fn get_next_position_for_item_in_category(&self, category_id: i64) -> Result<i64> {
+        let mut conn = self.pool.acquire().await?;
+
+        self.next_position_for_item_in_category(category_id, &mut conn).await
+    }
+
+    /// next_position_for_item_in_category is a helper for
+    /// get_next_position_for_item_in_category which can also be used from
+    /// within a transaction, so that entries inserted earlier in the
+    /// transaction are accounted for
+    async fn next_position_for_item_in_category(
+        &self,
+        category_id: i64,
+        conn: &mut SqliteConnection,
+    ) -> Result<i64> {
         let next_position = "next_position";
 
         Ok(sqlx::query(&format!(
@@ -86,573 +789,2819 @@ impl Database {
             LIMIT 1",
         ))
         .bind(category_id)
-        .fetch_optional(&self.pool)
+        .fetch_optional(conn)
         .await?
         .map(|r| r.get(next_position))
         .unwrap_or(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION))
     }
 
-    pub async fn get_last_category_for_description(
+    async fn last_category_for_description(
         &self,
         description: &str,
+        list_id: i64,
+        conn: &mut SqliteConnection,
     ) -> Result<Option<i64>> {
         Ok(sqlx::query(&format!(
             "SELECT {GROCERY_LIST_ENTRIES_CATEGORY_ID} 
             FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} 
             WHERE {GROCERY_LIST_ENTRIES_DESCRIPTION} = ? 
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
             ORDER BY {GROCERY_LIST_ENTRIES_UPDATED_AT} DESC 
             LIMIT 1",
         ))
         .bind(description)
-        .fetch_optional(&self.pool)
+        .bind(list_id)
+        .fetch_optional(conn)
         .await?
         .map(|r| r.get(GROCERY_LIST_ENTRIES_CATEGORY_ID)))
     }
 
+    /// default_category_for_list gets the id of the list's default category,
+    /// or None if the list does not exist
+    async fn default_category_for_list(
+        &self,
+        list_id: i64,
+        conn: &mut SqliteConnection,
+    ) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar(&format!(
+            "SELECT {CATEGORIES_ID}
+            FROM {TABLE_NAME_CATEGORIES}
+            WHERE {CATEGORIES_LIST_ID} = ? AND {CATEGORIES_IS_DEFAULT_CATEGORY}
+            ORDER BY {CATEGORIES_ID}
+            LIMIT 1",
+        ))
+        .bind(list_id)
+        .fetch_optional(conn)
+        .await?)
+    }
+
+    /// get_category_for_new_entry picks the category for a new entry in the
+    /// given list: the category the description was last used in within that
+    /// list, falling back to the list's default category. Returns None if the
+    /// list does not exist.
+    pub async fn get_category_for_new_entry(
+        &self,
+        description: &str,
+        list_id: i64,
+    ) -> Result<Option<i64>> {
+        let mut conn = self.pool.acquire().await?;
+
+        self.category_for_new_entry(description, list_id, &mut conn)
+            .await
+    }
+
+    /// category_for_new_entry is get_category_for_new_entry on conn, so that it
+    /// can be used from within a transaction without waiting on a second
+    /// connection from the pool
+    async fn category_for_new_entry(
+        &self,
+        description: &str,
+        list_id: i64,
+        conn: &mut SqliteConnection,
+    ) -> Result<Option<i64>> {
+        match self
+            .last_category_for_description(description, list_id, conn)
+            .await?
+        {
+            Some(category_id) => Ok(Some(category_id)),
+            None => self.default_category_for_list(list_id, conn).await,
+        }
+    }
+
     pub async fn create_entry(&self, entry: CreateGroceryListEntry) -> Result<GroceryListEntry> {
-        let quantity = entry.quantity.unwrap_or_default();
-        let notes = entry.notes.unwrap_or_default();
         let category_id = match entry.category_id {
             Some(c) => c,
             None => self
-                .get_last_category_for_description(&entry.description)
+                .get_category_for_new_entry(
+                    &entry.description,
+                    entry.list_id.unwrap_or(DEFAULT_LIST_ID),
+                )
                 .await?
-                .unwrap_or(1),
+                .unwrap_or(DEFAULT_CATEGORY_ID),
         };
 
-        let entry = sqlx::query_as(&format!(
-            "INSERT INTO {TABLE_NAME_GROCERY_LIST_ENTRIES} 
-                (
-                    {GROCERY_LIST_ENTRIES_DESCRIPTION},
-                    {GROCERY_LIST_ENTRIES_POSITION}, 
-                    {GROCERY_LIST_ENTRIES_QUANTITY}, 
-                    {GROCERY_LIST_ENTRIES_NOTES}, 
-                    {GROCERY_LIST_ENTRIES_CATEGORY_ID}
-                )
-            VALUES (?, ?, ?, ?, ?)
-            RETURNING {}",
-            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
-        ))
-        .bind(&entry.description)
-        .bind(entry.position)
-        .bind(&quantity)
-        .bind(&notes)
-        .bind(category_id)
-        .fetch_one(&self.pool)
-        .await?;
+        let mut tx = self.pool.begin().await?;
 
-        Ok(entry)
+        let created = self
+            .insert_entry(
+                CreateGroceryListEntry {
+                    category_id: Some(category_id),
+                    ..entry
+                },
+                &mut tx,
+            )
+            .await?;
+        self.ensure_category_room(category_id, 1, &mut tx).await?;
+
+        tx.commit().await?;
+
+        Ok(created)
     }
 
-    pub async fn update_entry(
+    /// create_entry_at_bottom adds the entry last in its category, recording
+    /// the idempotency_key along with it
+    pub async fn create_entry_at_bottom(
         &self,
-        id: i64,
-        entry: UpdateGroceryListEntry,
-    ) -> Result<Option<GroceryListEntry>> {
-        let mut query_builder =
-            sqlx::QueryBuilder::new(&format!("UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES} SET "));
-        let mut separated = query_builder.separated(",");
-        let mut must_reorder = false;
+        entry: CreateGroceryListEntry,
+        idempotency_key: Option<&str>,
+    ) -> Result<GroceryListEntry> {
+        let category_id = entry.category_id.unwrap_or(DEFAULT_CATEGORY_ID);
+        let mut tx = self.pool.begin().await?;
 
-        if let Some(description) = &entry.description {
-            separated
-                .push(&format!("{GROCERY_LIST_ENTRIES_DESCRIPTION} = "))
-                .push_bind_unseparated(description);
+        if let Some(key) = idempotency_key {
+            self.release_expired_idempotency_key(key, &mut tx).await?;
         }
 
-        if let Some(is_completed) = entry.completed {
-            separated.push(GROCERY_LIST_ENTRIES_COMPLETED_AT);
-            if is_completed {
-                separated.push_unseparated(" = CURRENT_TIMESTAMP");
-            } else {
-                separated.push_unseparated(" = NULL");
-            }
-        }
-        if let Some(quantity) = &entry.quantity {
-            separated
-                .push(&format!("{GROCERY_LIST_ENTRIES_QUANTITY} = "))
-                .push_bind_unseparated(quantity);
-        }
-        if let Some(notes) = &entry.notes {
-            separated
-                .push(&format!("{GROCERY_LIST_ENTRIES_NOTES} = "))
-                .push_bind_unseparated(notes);
-        }
-        if entry.category_id.is_some() || entry.position.is_some() {
-            must_reorder = true;
+        let position = self
+            .next_position_for_item_in_category(category_id, &mut tx)
+            .await?;
+        let created = self
+            .insert_entry(
+                CreateGroceryListEntry {
+                    category_id: Some(category_id),
+                    position: Some(position),
+                    ..entry
+                },
+                &mut tx,
+            )
+            .await?;
+        self.ensure_category_room(category_id, 1, &mut tx).await?;
+
+        if let Some(key) = idempotency_key {
+            self.claim_idempotency_key(key, created.id, &mut tx).await?;
         }
 
-        separated.push(&format!(
-            "{GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP"
-        ));
+        tx.commit().await?;
 
-        query_builder
-            .push(&format!(" WHERE {GROCERY_LIST_ENTRIES_ID} = "))
-            .push_bind(id);
-        query_builder.push(&format!(
-            " RETURNING {}",
-            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        Ok(created)
+    }
+
+    /// create_entry_at_top adds the entry first in its category, moving every
+    /// other entry in the category down one, and records the idempotency_key
+    /// along with it
+    pub async fn create_entry_at_top(
+        &self,
+        entry: CreateGroceryListEntry,
+        idempotency_key: Option<&str>,
+    ) -> Result<GroceryListEntry> {
+        let category_id = entry.category_id.unwrap_or(DEFAULT_CATEGORY_ID);
+        let mut tx = self.pool.begin().await?;
+
+        if let Some(key) = idempotency_key {
+            self.release_expired_idempotency_key(key, &mut tx).await?;
+        }
+
+        self.increment_entry_positions_ge(
+            ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION,
+            category_id,
+            &mut tx,
+        )
+        .await?;
+
+        let created = self
+            .insert_entry(
+                CreateGroceryListEntry {
+                    category_id: Some(category_id),
+                    position: Some(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION),
+                    ..entry
+                },
+                &mut tx,
+            )
+            .await?;
+        self.ensure_category_room(category_id, 1, &mut tx).await?;
+
+        if let Some(key) = idempotency_key {
+            self.claim_idempotency_key(key, created.id, &mut tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    /// merge_entry looks for an outstanding entry in the category with the
+    /// same description, ignoring case and surrounding whitespace, and the
+    /// same unit. If there is one, entry's quantity is added to it and it is
+    /// returned as it was and as it is now. A missing quantity counts as 1. Entries that are checked off,
+    /// in the trash or scheduled for later aren't merged into, and neither is
+    /// one whose quantity isn't a number, like a "2lb" that was never split,
+    /// so the caller adds a new entry instead. The idempotency_key is recorded
+    /// along with a merge.
+    pub async fn merge_entry(
+        &self,
+        category_id: i64,
+        entry: &CreateGroceryListEntry,
+        idempotency_key: Option<&str>,
+    ) -> Result<Option<(GroceryListEntry, GroceryListEntry)>> {
+        let mut tx = self.pool.begin().await?;
+
+        if let Some(key) = idempotency_key {
+            self.release_expired_idempotency_key(key, &mut tx).await?;
+        }
+
+        let existing: Option<GroceryListEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL
+            AND {GROCERY_LIST_ENTRIES_COMPLETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {}
+            AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?
+            AND lower(trim({GROCERY_LIST_ENTRIES_DESCRIPTION})) = lower(trim(?))
+            AND {GROCERY_LIST_ENTRIES_UNIT} = ?
+            ORDER BY {GROCERY_LIST_ENTRIES_POSITION}
+            LIMIT 1",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_visible_filter(),
+        ))
+        .bind(true)
+        .bind(category_id)
+        .bind(&entry.description)
+        .bind(entry.unit.as_deref().unwrap_or_default())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(existing) = existing else {
+            return Ok(None);
+        };
+
+        let Some(quantity) = add_quantities(
+            &existing.quantity,
+            entry.quantity.as_deref().unwrap_or_default(),
+        ) else {
+            return Ok(None);
+        };
+
+        let merged = sqlx::query_as(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_QUANTITY} = ?,
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP,
+                {GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ?
+            RETURNING {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(quantity)
+        .bind(existing.id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(key) = idempotency_key {
+            self.claim_idempotency_key(key, existing.id, &mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some((existing, merged)))
+    }
+
+    /// create_entries creates all of the given entries in a single
+    /// transaction, so either every entry is created or none are. Entries
+    /// without a category go into the category their description was last
+    /// used in, and entries without a position are appended to the end of
+    /// their category. A category that doesn't have room for all of its new
+    /// entries fails the whole batch with CategoryFull.
+    pub async fn create_entries(
+        &self,
+        entries: Vec<CreateGroceryListEntry>,
+    ) -> Result<Vec<GroceryListEntry>> {
+        let mut tx = self.pool.begin().await?;
+        let mut created = Vec::with_capacity(entries.len());
+        let mut added: HashMap<i64, i64> = HashMap::new();
+
+        for entry in entries {
+            let category_id = match entry.category_id {
+                Some(c) => c,
+                None => {
+                    let list_id = entry.list_id.unwrap_or(DEFAULT_LIST_ID);
+                    self.category_for_new_entry(&entry.description, list_id, &mut tx)
+                        .await?
+                        .ok_or_else(|| anyhow::anyhow!("list {} does not exist", list_id))?
+                }
+            };
+
+            let position = match entry.position {
+                Some(p) => p,
+                None => {
+                    self.next_position_for_item_in_category(category_id, &mut tx)
+                        .await?
+                }
+            };
+
+            let entry = CreateGroceryListEntry {
+                category_id: Some(category_id),
+                position: Some(position),
+                ..entry
+            };
+
+            *added.entry(category_id).or_default() += 1;
+            created.push(self.insert_entry(entry, &mut tx).await?);
+        }
+
+        for (category_id, added) in added {
+            self.ensure_category_room(category_id, added, &mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    async fn insert_entry(
+        &self,
+        entry: CreateGroceryListEntry,
+        conn: &mut SqliteConnection,
+    ) -> Result<GroceryListEntry> {
+        let quantity = entry.quantity.unwrap_or_default();
+        let unit = entry.unit.unwrap_or_default();
+        let priority = entry.priority.unwrap_or_default();
+
+        let entry = sqlx::query_as(&format!(
+            "INSERT INTO {TABLE_NAME_GROCERY_LIST_ENTRIES} 
+                (
+                    {GROCERY_LIST_ENTRIES_DESCRIPTION},
+                    {GROCERY_LIST_ENTRIES_POSITION}, 
+                    {GROCERY_LIST_ENTRIES_QUANTITY}, 
+                    {GROCERY_LIST_ENTRIES_UNIT}, 
+                    {GROCERY_LIST_ENTRIES_NOTES}, 
+                    {GROCERY_LIST_ENTRIES_CATEGORY_ID},
+                    {GROCERY_LIST_ENTRIES_LIST_ID},
+                    {GROCERY_LIST_ENTRIES_CREATED_AT},
+                    {GROCERY_LIST_ENTRIES_PRIORITY},
+                    {GROCERY_LIST_ENTRIES_PRICE_CENTS},
+                    {GROCERY_LIST_ENTRIES_CURRENCY},
+                    {GROCERY_LIST_ENTRIES_VISIBLE_FROM}
+                )
+            VALUES (
+                ?, ?, ?, ?, NULLIF(?, ''), ?,
+                (SELECT {CATEGORIES_LIST_ID} FROM {TABLE_NAME_CATEGORIES} WHERE {CATEGORIES_ID} = ?),
+                CURRENT_TIMESTAMP,
+                ?, ?, NULLIF(?, ''), ?
+            )
+            RETURNING {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(&entry.description)
+        .bind(entry.position)
+        .bind(&quantity)
+        .bind(&unit)
+        .bind(&entry.notes)
+        .bind(entry.category_id)
+        .bind(entry.category_id)
+        .bind(priority)
+        .bind(entry.price_cents)
+        .bind(&entry.currency)
+        .bind(entry.visible_from)
+        .fetch_one(conn)
+        .await?;
+
+        Ok(entry)
+    }
+
+    /// update_entry updates the entry if it is still at entry.version,
+    /// bumping its version
+    pub async fn update_entry(
+        &self,
+        id: i64,
+        entry: UpdateGroceryListEntry,
+    ) -> Result<VersionedUpdate<GroceryListEntry>> {
+        let mut tx = self.pool.begin().await?;
+
+        let update = self.update_entry_in_transaction(id, entry, &mut tx).await?;
+        if matches!(update, VersionedUpdate::Updated(_)) {
+            tx.commit().await?;
+        }
+
+        Ok(update)
+    }
+
+    /// update_entries applies every update in one transaction, so if any of
+    /// them can't be applied then none of them are
+    pub async fn update_entries(
+        &self,
+        updates: Vec<(i64, UpdateGroceryListEntry)>,
+    ) -> Result<BatchUpdate> {
+        let mut tx = self.pool.begin().await?;
+        let mut updated = Vec::with_capacity(updates.len());
+
+        for (id, entry) in updates {
+            match self.update_entry_in_transaction(id, entry, &mut tx).await? {
+                VersionedUpdate::Updated(entry) => updated.push(entry),
+                VersionedUpdate::Conflict(current) => {
+                    return Ok(BatchUpdate::Conflict(id, Box::new(current)))
+                }
+                VersionedUpdate::NotFound => return Ok(BatchUpdate::NotFound(id)),
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(BatchUpdate::Updated(updated))
+    }
+
+    /// update_entry_in_transaction is update_entry within tx, which is left
+    /// for the caller to commit
+    async fn update_entry_in_transaction(
+        &self,
+        id: i64,
+        entry: UpdateGroceryListEntry,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<VersionedUpdate<GroceryListEntry>> {
+        let mut query_builder =
+            sqlx::QueryBuilder::new(&format!("UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES} SET "));
+        let mut separated = query_builder.separated(",");
+        let mut must_reorder = false;
+
+        if let Some(description) = &entry.description {
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_DESCRIPTION} = "))
+                .push_bind_unseparated(description);
+        }
+
+        if let Some(is_completed) = entry.completed {
+            separated.push(GROCERY_LIST_ENTRIES_COMPLETED_AT);
+            if is_completed {
+                separated.push_unseparated(" = CURRENT_TIMESTAMP");
+            } else {
+                separated.push_unseparated(" = NULL");
+            }
+        }
+        if let Some(quantity) = &entry.quantity {
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_QUANTITY} = "))
+                .push_bind_unseparated(quantity);
+        }
+        if let Some(unit) = &entry.unit {
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_UNIT} = "))
+                .push_bind_unseparated(unit);
+        }
+        if let Some(notes) = &entry.notes {
+            // an empty note clears it
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_NOTES} = NULLIF("))
+                .push_bind_unseparated(notes)
+                .push_unseparated(", '')");
+        }
+        if let Some(priority) = entry.priority {
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_PRIORITY} = "))
+                .push_bind_unseparated(priority);
+        }
+        if let Some(price_cents) = entry.price_cents {
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_PRICE_CENTS} = "))
+                .push_bind_unseparated(price_cents);
+        }
+        if let Some(currency) = &entry.currency {
+            // an empty currency clears it
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_CURRENCY} = NULLIF("))
+                .push_bind_unseparated(currency)
+                .push_unseparated(", '')");
+        }
+        if let Some(visible_from) = entry.visible_from {
+            separated
+                .push(format!("{GROCERY_LIST_ENTRIES_VISIBLE_FROM} = "))
+                .push_bind_unseparated(visible_from);
+        }
+        if entry.category_id.is_some() || entry.position.is_some() {
+            must_reorder = true;
+        }
+
+        separated.push(format!(
+            "{GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP"
+        ));
+        separated.push(format!("{GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1"));
+
+        query_builder
+            .push(format!(" WHERE {GROCERY_LIST_ENTRIES_ID} = "))
+            .push_bind(id)
+            .push(format!(" AND {GROCERY_LIST_ENTRIES_VERSION} = "))
+            .push_bind(entry.version)
+            .push(format!(" AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL"));
+        query_builder.push(format!(
+            " RETURNING {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
         ));
 
-        let mut tx = self.pool.begin().await?;
+        // update non-position fields of this entry, unless it has changed
+        // since the client last saw it
+        let Some(row) = query_builder.build().fetch_optional(&mut **tx).await? else {
+            let current = sqlx::query_as(&format!(
+                "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+                WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL",
+                all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+            ))
+            .bind(id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+            return Ok(match current {
+                Some(current) => VersionedUpdate::Conflict(current),
+                None => VersionedUpdate::NotFound,
+            });
+        };
+
+        // then update positional/category fields for this and affected entries
+        if must_reorder {
+            // if no position is provided, we hit this code path because we are
+            // changing categories, in which case we want to default to the
+            // front of the list
+            let new_position = entry
+                .position
+                .unwrap_or(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION);
+
+            // if no category is provided, we are just repositioning within the category
+            let new_category_id = entry
+                .category_id
+                .unwrap_or_else(|| row.get(GROCERY_LIST_ENTRIES_CATEGORY_ID));
+
+            self.update_category_and_position_for_entry(id, new_position, new_category_id, tx)
+                .await?;
+        }
+
+        let updated = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} WHERE {GROCERY_LIST_ENTRIES_ID} = ?",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(VersionedUpdate::Updated(updated))
+    }
+
+    /// toggle_entry_completed flips the completed state of the entry with the
+    /// given id without modifying any of its other fields. Checking an entry
+    /// off is recorded as a purchase of it.
+    pub async fn toggle_entry_completed(&self, id: i64) -> Result<Option<GroceryListEntry>> {
+        let mut tx = self.pool.begin().await?;
+
+        let entry: Option<GroceryListEntry> = sqlx::query_as(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_COMPLETED_AT} = CASE
+                    WHEN {GROCERY_LIST_ENTRIES_COMPLETED_AT} IS NULL THEN CURRENT_TIMESTAMP
+                    ELSE NULL
+                END,
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP,
+                {GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            RETURNING {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(entry) = entry.as_ref().filter(|entry| entry.completed_at.is_some()) {
+            sqlx::query(&format!(
+                "INSERT INTO {TABLE_NAME_PURCHASE_EVENTS}
+                ({PURCHASE_EVENTS_ENTRY_ID}, {PURCHASE_EVENTS_LIST_ID}, {PURCHASE_EVENTS_DESCRIPTION},
+                    {PURCHASE_EVENTS_QUANTITY}, {PURCHASE_EVENTS_UNIT}, {PURCHASE_EVENTS_PURCHASED_AT})
+                VALUES (?, ?, ?, ?, ?, ?)"
+            ))
+            .bind(entry.id)
+            .bind(entry.list_id)
+            .bind(&entry.description)
+            .bind(&entry.quantity)
+            .bind(&entry.unit)
+            .bind(entry.completed_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(entry)
+    }
+
+    /// toggle_entry_pinned pins the entry if it isn't pinned and unpins it if
+    /// it is. Its position is left alone, so unpinning puts it back where it
+    /// was in the manual order.
+    pub async fn toggle_entry_pinned(&self, id: i64) -> Result<Option<GroceryListEntry>> {
+        Ok(sqlx::query_as(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_PINNED} = NOT {GROCERY_LIST_ENTRIES_PINNED},
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP,
+                {GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            RETURNING {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    /// get_purchase_history gets every recorded purchase of the item with the
+    /// given description, most recent first, on one list or on every list.
+    /// Descriptions are matched ignoring case.
+    pub async fn get_purchase_history(
+        &self,
+        description: &str,
+        list_id: Option<i64>,
+    ) -> Result<Vec<PurchaseEvent>> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_PURCHASE_EVENTS}
+            WHERE {PURCHASE_EVENTS_DESCRIPTION} = ? COLLATE NOCASE
+                AND (? IS NULL OR {PURCHASE_EVENTS_LIST_ID} = ?)
+            ORDER BY {PURCHASE_EVENTS_PURCHASED_AT} DESC, {PURCHASE_EVENTS_ID} DESC",
+            all_fields(&PURCHASE_EVENTS_FIELDS)
+        ))
+        .bind(description.trim())
+        .bind(list_id)
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// get_active_entry gets the entry along with its tags, or None if it
+    /// doesn't exist or is in the trash
+    pub async fn get_active_entry(
+        &self,
+        id: i64,
+    ) -> Result<Option<(GroceryListEntry, Vec<String>)>> {
+        let mut tx = self.pool.begin().await?;
+
+        let entry = sqlx::query_as::<_, GroceryListEntry>(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+
+        let tags = self.entry_tags(id, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(Some((entry, tags)))
+    }
+
+    /// get_list_entry_tags gets the tags of every entry in the list, keyed by
+    /// entry id. Entries without tags are left out.
+    pub async fn get_list_entry_tags(&self, list_id: i64) -> Result<HashMap<i64, Vec<String>>> {
+        let rows: Vec<(i64, String)> = sqlx::query_as(&format!(
+            "SELECT et.{ENTRY_TAGS_ENTRY_ID}, t.{TAGS_NAME}
+            FROM {TABLE_NAME_ENTRY_TAGS} et
+            JOIN {TABLE_NAME_TAGS} t ON t.{TAGS_ID} = et.{ENTRY_TAGS_TAG_ID}
+            JOIN {TABLE_NAME_GROCERY_LIST_ENTRIES} e ON e.{GROCERY_LIST_ENTRIES_ID} = et.{ENTRY_TAGS_ENTRY_ID}
+            WHERE e.{GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            ORDER BY t.{TAGS_NAME}"
+        ))
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+        for (entry_id, name) in rows {
+            tags.entry(entry_id).or_default().push(name);
+        }
+        Ok(tags)
+    }
+
+    /// add_entry_tag puts tag on the entry, which is a no-op if it already has
+    /// it, and returns every tag the entry has. An entry that doesn't exist or
+    /// is in the trash can't be tagged.
+    pub async fn add_entry_tag(&self, entry_id: i64, tag: &str) -> Result<Option<Vec<String>>> {
+        let mut tx = self.pool.begin().await?;
+
+        if !self.touch_entry(entry_id, &mut tx).await? {
+            return Ok(None);
+        }
+
+        sqlx::query(&format!(
+            "INSERT INTO {TABLE_NAME_TAGS} ({TAGS_NAME}) VALUES (?)
+            ON CONFLICT({TAGS_NAME}) DO NOTHING"
+        ))
+        .bind(tag)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "INSERT INTO {TABLE_NAME_ENTRY_TAGS} ({ENTRY_TAGS_ENTRY_ID}, {ENTRY_TAGS_TAG_ID})
+            SELECT ?, {TAGS_ID} FROM {TABLE_NAME_TAGS} WHERE {TAGS_NAME} = ?
+            ON CONFLICT DO NOTHING"
+        ))
+        .bind(entry_id)
+        .bind(tag)
+        .execute(&mut *tx)
+        .await?;
+
+        let tags = self.entry_tags(entry_id, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(Some(tags))
+    }
+
+    /// remove_entry_tag takes tag off the entry, which is a no-op if it
+    /// doesn't have it, and returns the tags the entry has left. Tags that no
+    /// entry has any more are forgotten.
+    pub async fn remove_entry_tag(&self, entry_id: i64, tag: &str) -> Result<Option<Vec<String>>> {
+        let mut tx = self.pool.begin().await?;
+
+        if !self.touch_entry(entry_id, &mut tx).await? {
+            return Ok(None);
+        }
+
+        sqlx::query(&format!(
+            "DELETE FROM {TABLE_NAME_ENTRY_TAGS}
+            WHERE {ENTRY_TAGS_ENTRY_ID} = ?
+            AND {ENTRY_TAGS_TAG_ID} IN (SELECT {TAGS_ID} FROM {TABLE_NAME_TAGS} WHERE {TAGS_NAME} = ?)"
+        ))
+        .bind(entry_id)
+        .bind(tag)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "DELETE FROM {TABLE_NAME_TAGS}
+            WHERE {TAGS_ID} NOT IN (SELECT {ENTRY_TAGS_TAG_ID} FROM {TABLE_NAME_ENTRY_TAGS})"
+        ))
+        .execute(&mut *tx)
+        .await?;
+
+        let tags = self.entry_tags(entry_id, &mut tx).await?;
+        tx.commit().await?;
+
+        Ok(Some(tags))
+    }
+
+    /// touch_entry marks the entry as updated, so that clients syncing with
+    /// changed_since pick up a change to its tags. It is false if the entry
+    /// doesn't exist or is in the trash.
+    async fn touch_entry(
+        &self,
+        entry_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<bool> {
+        let result = sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ?
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL"
+        ))
+        .bind(entry_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// entry_tags gets the entry's tags in alphabetical order
+    async fn entry_tags(
+        &self,
+        entry_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<Vec<String>> {
+        Ok(sqlx::query_scalar(&format!(
+            "SELECT t.{TAGS_NAME} FROM {TABLE_NAME_TAGS} t
+            JOIN {TABLE_NAME_ENTRY_TAGS} et ON et.{ENTRY_TAGS_TAG_ID} = t.{TAGS_ID}
+            WHERE et.{ENTRY_TAGS_ENTRY_ID} = ?
+            ORDER BY t.{TAGS_NAME}"
+        ))
+        .bind(entry_id)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// delete_entry moves the entry to the trash, taking it out of its
+    /// category's ordering. It can be brought back with restore_entry until
+    /// it is purged.
+    pub async fn delete_entry(&self, id: i64) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted: Option<(Option<i64>, i64)> = sqlx::query_as(&format!(
+            "SELECT {GROCERY_LIST_ENTRIES_POSITION}, {GROCERY_LIST_ENTRIES_CATEGORY_ID}
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL"
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some((prior_position, prior_category_id)) = deleted else {
+            return Ok(false);
+        };
+
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_DELETED_AT} = CURRENT_TIMESTAMP,
+                {GROCERY_LIST_ENTRIES_POSITION} = NULL,
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP,
+                {GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+        ))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        // archived entries have no position, so there is no gap to close
+        if let Some(prior_position) = prior_position {
+            self.decrement_entry_positions_gt(prior_position, prior_category_id, &mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// delete_completed_entries moves every completed entry on the list to the
+    /// trash and returns their ids. Entries that aren't completed are never
+    /// touched.
+    pub async fn delete_completed_entries(&self, list_id: i64) -> Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+
+        // closing the gaps from the highest position down keeps the lower
+        // positions where they are until it is their turn
+        let completed: Vec<(i64, i64, i64)> = sqlx::query_as(&format!(
+            "SELECT {GROCERY_LIST_ENTRIES_ID}, {GROCERY_LIST_ENTRIES_POSITION}, {GROCERY_LIST_ENTRIES_CATEGORY_ID}
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+                AND {GROCERY_LIST_ENTRIES_COMPLETED_AT} IS NOT NULL
+                AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL
+            ORDER BY {GROCERY_LIST_ENTRIES_POSITION} DESC"
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for (id, position, category_id) in &completed {
+            sqlx::query(&format!(
+                "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+                SET
+                    {GROCERY_LIST_ENTRIES_DELETED_AT} = CURRENT_TIMESTAMP,
+                    {GROCERY_LIST_ENTRIES_POSITION} = NULL,
+                    {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP,
+                    {GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1
+                WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_COMPLETED_AT} IS NOT NULL"
+            ))
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+            self.decrement_entry_positions_gt(*position, *category_id, &mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(completed.into_iter().map(|(id, _, _)| id).collect())
+    }
+
+    /// restore_entry takes the entry back out of the trash. Unless it was
+    /// archived, it is appended to the end of its category.
+    pub async fn restore_entry(&self, id: i64) -> Result<Option<GroceryListEntry>> {
+        let mut tx = self.pool.begin().await?;
+
+        let deleted: Option<(Option<DateTime<Utc>>, i64)> = sqlx::query_as(&format!(
+            "SELECT {GROCERY_LIST_ENTRIES_ARCHIVED_AT}, {GROCERY_LIST_ENTRIES_CATEGORY_ID}
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NOT NULL"
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some((archived_at, category_id)) = deleted else {
+            return Ok(None);
+        };
+
+        let position = match archived_at {
+            Some(_) => None,
+            None => Some(
+                self.next_position_for_item_in_category(category_id, &mut tx)
+                    .await?,
+            ),
+        };
+
+        let entry = sqlx::query_as(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_DELETED_AT} = NULL,
+                {GROCERY_LIST_ENTRIES_POSITION} = ?,
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP,
+                {GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ?
+            RETURNING {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(position)
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if archived_at.is_none() {
+            self.ensure_category_room(category_id, 1, &mut tx).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(entry))
+    }
+
+    /// get_deleted_entries gets the entries in the list's trash, most
+    /// recently deleted first
+    pub async fn get_deleted_entries(&self, list_id: i64) -> Result<Vec<GroceryListEntry>> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_DELETED_AT} IS NOT NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            ORDER BY {GROCERY_LIST_ENTRIES_DELETED_AT} DESC",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+        ))
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// purge_deleted_entries permanently removes entries that have been in
+    /// the trash for longer than TRASH_RETENTION_DAYS
+    pub async fn purge_deleted_entries(&self) -> Result<u64> {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_DELETED_AT} < datetime('now', ?)"
+        ))
+        .bind(format!("-{} days", TRASH_RETENTION_DAYS))
+        .execute(&self.pool)
+        .await?;
+
+        tracing::debug!("purged {} deleted entries", result.rows_affected());
+        Ok(result.rows_affected())
+    }
+
+    /// get_prior_position_and_category is a helper for reorder_entries{_with_transaction}
+    async fn get_prior_position_and_category(
+        &self,
+        entry_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<(i64, i64)> {
+        let result: (i64, i64) = sqlx::query_as(&format!(
+            "SELECT {GROCERY_LIST_ENTRIES_POSITION}, {GROCERY_LIST_ENTRIES_CATEGORY_ID} 
+                FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} 
+                WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+        ))
+        .bind(entry_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok((result.0, result.1))
+    }
+
+    /// reorder_entries moves the entry with entry.id to entry.new_position
+    /// in its category, or in entry.new_category_id. The position is only
+    /// taken as where the entry goes among the others, so one that is out of
+    /// range puts it first or last, and every entry involved is renumbered
+    /// from the minimum position without gaps.
+    pub async fn reorder_entries(&self, reorder_request: ReorderEntry) -> Result<ReorderOutcome> {
+        let entry = sqlx::query_as::<_, GroceryListEntry>(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+        ))
+        .bind(reorder_request.id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(entry) = entry else {
+            return Ok(ReorderOutcome::InvalidIds(format!(
+                "unknown entry id [{}]",
+                reorder_request.id
+            )));
+        };
+
+        if let Some(new_category_id) = reorder_request.new_category_id {
+            let category = self.get_category(new_category_id).await?;
+            if category.is_none_or(|c| c.list_id != entry.list_id) {
+                return Ok(ReorderOutcome::InvalidIds(format!(
+                    "unknown category id [{}]",
+                    new_category_id
+                )));
+            }
+        }
+
+        // if no position is provided, we hit this code path because we are
+        // changing categories, in which case we want to default to the
+        // front of the list
+        let new_position = reorder_request
+            .new_position
+            .unwrap_or(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION);
+
+        // if no category is provided, we are just repositioning within the category
+        let new_category_id = reorder_request.new_category_id.unwrap_or(entry.category_id);
+
+        let mut tx = self.pool.begin().await?;
+
+        if new_category_id != entry.category_id {
+            let mut prior_ids = self
+                .positioned_entry_ids(entry.category_id, &mut tx)
+                .await?;
+            prior_ids.retain(|id| *id != entry.id);
+            self.renumber_entries(entry.category_id, &prior_ids, &mut tx)
+                .await?;
+        }
+
+        let ids = self.positioned_entry_ids(new_category_id, &mut tx).await?;
+        let ids = placed_at(ids, entry.id, new_position);
+        self.renumber_entries(new_category_id, &ids, &mut tx)
+            .await
+            .inspect_err(|e| tracing::error!("failed to renumber entries: {}", e))?;
+        if new_category_id != entry.category_id {
+            self.ensure_category_room(new_category_id, 1, &mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(ReorderOutcome::Reordered)
+    }
+
+    /// move_entry moves an active entry into the category at the given
+    /// position, closing the gap it leaves and making room for it. A position
+    /// past the end, or none at all, puts it last.
+    pub async fn move_entry(&self, id: i64, move_request: MoveEntry) -> Result<EntryMove> {
+        let entry = sqlx::query_as::<_, GroceryListEntry>(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(entry) = entry else {
+            return Ok(EntryMove::EntryNotFound);
+        };
+
+        let category = self.get_category(move_request.category_id).await?;
+        if category.is_none_or(|c| c.list_id != entry.list_id) {
+            return Ok(EntryMove::CategoryNotFound);
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        // within the same category the entry's own slot doesn't count
+        let mut last_position = self
+            .next_position_for_item_in_category(move_request.category_id, &mut tx)
+            .await?;
+        if move_request.category_id == entry.category_id {
+            last_position -= 1;
+        }
+        let new_position = move_request
+            .position
+            .unwrap_or(last_position)
+            .clamp(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION, last_position);
+
+        self.update_category_and_position_for_entry(
+            id,
+            new_position,
+            move_request.category_id,
+            &mut tx,
+        )
+        .await?;
+
+        let moved = sqlx::query_as(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET {GROCERY_LIST_ENTRIES_VERSION} = {GROCERY_LIST_ENTRIES_VERSION} + 1
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ?
+            RETURNING {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(EntryMove::Moved(Box::new(moved)))
+    }
+
+    /// order_entries puts every active entry in the category in the order of
+    /// order_request.ids, which must name each of them exactly once
+    pub async fn order_entries(&self, order_request: OrderEntries) -> Result<ReorderOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let current_ids = self
+            .positioned_entry_ids(order_request.category_id, &mut tx)
+            .await?;
+
+        if let Some(invalid) = check_reorder_ids(&order_request.ids, &current_ids) {
+            return Ok(ReorderOutcome::InvalidIds(invalid));
+        }
+
+        self.renumber_entries(order_request.category_id, &order_request.ids, &mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(ReorderOutcome::Reordered)
+    }
+
+    /// reset_entry_order puts the active entries in the category back in the
+    /// order they were added, undoing any manual ordering, and returns them in
+    /// their new order. A category that doesn't exist is None.
+    pub async fn reset_entry_order(
+        &self,
+        category_id: i64,
+    ) -> Result<Option<Vec<GroceryListEntry>>> {
+        if self.get_category(category_id).await?.is_none() {
+            return Ok(None);
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let ids: Vec<i64> = sqlx::query_scalar(&format!(
+            "SELECT {GROCERY_LIST_ENTRIES_ID} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ? AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL
+            ORDER BY {GROCERY_LIST_ENTRIES_CREATED_AT}, {GROCERY_LIST_ENTRIES_ID}"
+        ))
+        .bind(category_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        self.renumber_entries(category_id, &ids, &mut tx).await?;
+
+        let entries = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ? AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL
+            ORDER BY {GROCERY_LIST_ENTRIES_POSITION}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(category_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(entries))
+    }
+
+    /// nudge_entry swaps the entry's position with the next entry along in its
+    /// category in the direction given, among the entries that are pinned the
+    /// same as it so the swap is one the list shows. At either end nothing
+    /// moves. The entries the nudge affected are returned, which at an end is
+    /// just the entry itself, or None for an entry that isn't active.
+    pub async fn nudge_entry(
+        &self,
+        id: i64,
+        direction: Nudge,
+    ) -> Result<Option<Vec<GroceryListEntry>>> {
+        let mut tx = self.pool.begin().await?;
+
+        let Some(entry): Option<GroceryListEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ? AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let (comparison, order) = match direction {
+            Nudge::Up => ("<", "DESC"),
+            Nudge::Down => (">", "ASC"),
+        };
+        let neighbor: Option<GroceryListEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ? AND {GROCERY_LIST_ENTRIES_PINNED} = ?
+            AND {GROCERY_LIST_ENTRIES_POSITION} {comparison} ?
+            ORDER BY {GROCERY_LIST_ENTRIES_POSITION} {order}
+            LIMIT 1",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(entry.category_id)
+        .bind(entry.pinned)
+        .bind(entry.position)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(neighbor) = neighbor else {
+            return Ok(Some(vec![entry]));
+        };
+
+        // the entry steps out of the way first, since no two entries in a
+        // category can share a position
+        for (entry_id, position) in [
+            (
+                entry.id,
+                entry.position.map(|p| p + MAX_NUM_POSITIONED_GROCERY_ITEMS),
+            ),
+            (neighbor.id, entry.position),
+            (entry.id, neighbor.position),
+        ] {
+            sqlx::query(&format!(
+                "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+                SET {GROCERY_LIST_ENTRIES_POSITION} = ?, {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP
+                WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+            ))
+            .bind(position)
+            .bind(entry_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let entries = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} IN (?, ?)
+            ORDER BY {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_order_by(EntrySort::Position),
+        ))
+        .bind(entry.id)
+        .bind(neighbor.id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(entries))
+    }
+
+    /// reorder_categories moves the category with category.id to
+    /// category.new_position among the categories of reorder_request.list_id,
+    /// which it must be in. As with reorder_entries, the position only says
+    /// where it goes among the others and only that list's categories are
+    /// renumbered, without gaps.
+    pub async fn reorder_categories(
+        &self,
+        reorder_request: ReorderCategory,
+    ) -> Result<ReorderOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let ids = self
+            .ordered_category_ids(reorder_request.list_id, &mut tx)
+            .await?;
+        if !ids.contains(&reorder_request.id) {
+            return Ok(ReorderOutcome::InvalidIds(format!(
+                "unknown ids [{}] in list {}",
+                reorder_request.id, reorder_request.list_id
+            )));
+        }
+        let ids = placed_at(ids, reorder_request.id, reorder_request.new_position);
+        self.renumber_categories(&ids, &mut tx)
+            .await
+            .inspect_err(|e| tracing::error!("failed to renumber categories: {}", e))?;
+
+        tx.commit().await?;
+
+        Ok(ReorderOutcome::Reordered)
+    }
+
+    /// order_categories puts every category in the list in the order of
+    /// order_request.ids, which must name each of them exactly once
+    pub async fn order_categories(&self, order_request: OrderCategories) -> Result<ReorderOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        let current_ids = self
+            .ordered_category_ids(order_request.list_id, &mut tx)
+            .await?;
+
+        if let Some(invalid) = check_reorder_ids(&order_request.ids, &current_ids) {
+            return Ok(ReorderOutcome::InvalidIds(format!(
+                "{} in list {}",
+                invalid, order_request.list_id
+            )));
+        }
+
+        self.renumber_categories(&order_request.ids, &mut tx).await?;
+
+        tx.commit().await?;
+
+        Ok(ReorderOutcome::Reordered)
+    }
+
+    /// positioned_entry_ids gets the ids of the active entries in the category
+    /// in order
+    async fn positioned_entry_ids(
+        &self,
+        category_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar(&format!(
+            "SELECT {GROCERY_LIST_ENTRIES_ID} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ? AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL
+            ORDER BY {GROCERY_LIST_ENTRIES_POSITION}"
+        ))
+        .bind(category_id)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// renumber_entries puts the entries with ids in the category, in that
+    /// order, numbered from the minimum position. The entries already in the
+    /// category are moved out of the way first so that assigning the new
+    /// positions never collides with a position that is still taken.
+    async fn renumber_entries(
+        &self,
+        category_id: i64,
+        ids: &[i64],
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} + {MAX_NUM_POSITIONED_GROCERY_ITEMS}
+            WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ? AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL"
+        ))
+        .bind(category_id)
+        .execute(&mut **tx)
+        .await?;
+
+        let positions = ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION..;
+        for (position, id) in positions.zip(ids) {
+            self.insert_entry_in_ordering(*id, position, category_id, tx)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// ordered_category_ids gets the ids of the list's categories in order
+    async fn ordered_category_ids(
+        &self,
+        list_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar(&format!(
+            "SELECT {CATEGORIES_ID} FROM {TABLE_NAME_CATEGORIES}
+            WHERE {CATEGORIES_LIST_ID} = ?
+            ORDER BY {CATEGORIES_POSITION}, {CATEGORIES_ID}"
+        ))
+        .bind(list_id)
+        .fetch_all(&mut **tx)
+        .await?)
+    }
+
+    /// renumber_categories puts the categories with ids in that order,
+    /// numbered from the minimum position
+    async fn renumber_categories(
+        &self,
+        ids: &[i64],
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<()> {
+        let positions = ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION..;
+        for (position, id) in positions.zip(ids) {
+            sqlx::query(&format!(
+                "UPDATE {TABLE_NAME_CATEGORIES}
+                SET {CATEGORIES_POSITION} = ?, {CATEGORIES_UPDATED_AT} = CURRENT_TIMESTAMP
+                WHERE {CATEGORIES_ID} = ?"
+            ))
+            .bind(position)
+            .bind(id)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// sort_categories_alphabetically renumbers the list's categories in order
+    /// of their names, ignoring case, or from Z to A with reverse, and returns
+    /// them in their new order
+    pub async fn sort_categories_alphabetically(
+        &self,
+        list_id: i64,
+        reverse: bool,
+    ) -> Result<Vec<Category>> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut categories: Vec<(i64, String)> = sqlx::query_as(&format!(
+            "SELECT {CATEGORIES_ID}, {CATEGORIES_NAME} FROM {TABLE_NAME_CATEGORIES}
+            WHERE {CATEGORIES_LIST_ID} = ?"
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        categories.sort_by_cached_key(|(_, name)| name.to_lowercase());
+        if reverse {
+            categories.reverse();
+        }
+
+        let ids: Vec<i64> = categories.iter().map(|(id, _)| *id).collect();
+        self.renumber_categories(&ids, &mut tx).await?;
+
+        let sorted = sqlx::query_as::<_, Category>(&format!(
+            "SELECT {} FROM {TABLE_NAME_CATEGORIES}
+            WHERE {CATEGORIES_LIST_ID} = ?
+            ORDER BY {CATEGORIES_POSITION}",
+            all_fields(&CATEGORIES_FIELDS)
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(sorted)
+    }
+
+    async fn update_category_and_position_for_entry(
+        &self,
+        entry_id: i64,
+        new_position: i64,
+        new_category_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<()> {
+        let (prior_position, prior_category_id) =
+            self.get_prior_position_and_category(entry_id, tx).await?;
+
+        self.remove_entry_from_ordering(entry_id, tx).await?;
+
+        self.decrement_entry_positions_gt(prior_position, prior_category_id, tx)
+            .await?;
+
+        self.increment_entry_positions_ge(new_position, new_category_id, tx)
+            .await?;
+
+        self.insert_entry_in_ordering(entry_id, new_position, new_category_id, tx)
+            .await?;
+
+        if new_category_id != prior_category_id {
+            self.ensure_category_room(new_category_id, 1, tx).await?;
+        }
+
+        Ok(())
+    }
+
+    /// ensure_category_room fails with CategoryFull if the category now has
+    /// more active entries than its max_entries, which happens when the added
+    /// entries that were just put in it didn't fit. It is run inside the
+    /// transaction that adds them, after they were added, so concurrent writes
+    /// can't both get in under the cap.
+    async fn ensure_category_room(
+        &self,
+        category_id: i64,
+        added: i64,
+        conn: &mut SqliteConnection,
+    ) -> Result<()> {
+        let room: Option<(Option<i64>, i64)> = sqlx::query_as(&format!(
+            "SELECT c.{CATEGORIES_MAX_ENTRIES}, (
+                SELECT COUNT(*) FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} e
+                WHERE e.{GROCERY_LIST_ENTRIES_CATEGORY_ID} = c.{CATEGORIES_ID}
+                AND e.{GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+                AND e.{GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            )
+            FROM {TABLE_NAME_CATEGORIES} c
+            WHERE c.{CATEGORIES_ID} = ?"
+        ))
+        .bind(category_id)
+        .fetch_optional(conn)
+        .await?;
+
+        match room {
+            Some((Some(max_entries), count)) if count > max_entries => {
+                tracing::info!(
+                    "rejected {} more entries for category {}, it has {} of {}",
+                    added,
+                    category_id,
+                    count - added,
+                    max_entries
+                );
+                Err(CategoryFull {
+                    category_id,
+                    max_entries,
+                    count: count - added,
+                }
+                .into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn remove_entry_from_ordering(
+        &self,
+        entry_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET {GROCERY_LIST_ENTRIES_POSITION} = ? 
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+        ))
+        .bind(ORDERABLE_LIST_REORDER_TEMPORARY_POSITION)
+        .bind(entry_id)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    async fn insert_entry_in_ordering(
+        &self,
+        entry_id: i64,
+        new_position: i64,
+        new_category_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES} 
+            SET
+                {GROCERY_LIST_ENTRIES_POSITION} = ?,
+                {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?,
+                {GROCERY_LIST_ENTRIES_LIST_ID} = (
+                    SELECT {CATEGORIES_LIST_ID} FROM {TABLE_NAME_CATEGORIES} WHERE {CATEGORIES_ID} = ?
+                ),
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP
+            WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+        ))
+        .bind(new_position)
+        .bind(new_category_id)
+        .bind(new_category_id)
+        .bind(entry_id)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// decrement_positions_gt decrements the position field if the position is greater than the provided position and the category_id matches
+    async fn decrement_entry_positions_gt(
+        &self,
+        prior_position: i64,
+        prior_category_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} + {}
+            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
+                AND {GROCERY_LIST_ENTRIES_POSITION} > ? 
+                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
+            MAX_NUM_POSITIONED_GROCERY_ITEMS - 1
+        ))
+        .bind(prior_position)
+        .bind(prior_category_id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} - {MAX_NUM_POSITIONED_GROCERY_ITEMS},
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP
+            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
+                AND {GROCERY_LIST_ENTRIES_POSITION} > ? 
+                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
+        ))
+        .bind(prior_position)
+        .bind(prior_category_id)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// increment_positions_ge increments the position field if the position is greather than OR equal to the provided position and the category_id matches
+    async fn increment_entry_positions_ge(
+        &self,
+        new_position: i64,
+        new_category_id: i64,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} + {MAX_NUM_POSITIONED_GROCERY_ITEMS}
+            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
+                AND {GROCERY_LIST_ENTRIES_POSITION} >= ? 
+                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
+        ))
+        .bind(new_position)
+        .bind(new_category_id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} - {},
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP
+            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
+                AND {GROCERY_LIST_ENTRIES_POSITION} >= ? 
+                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
+            MAX_NUM_POSITIONED_GROCERY_ITEMS - 1
+        ))
+        .bind(new_position)
+        .bind(new_category_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_category(&self, id: i64) -> Result<Option<Category>> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_CATEGORIES} WHERE {CATEGORIES_ID} = ? LIMIT 1",
+            all_fields(&CATEGORIES_FIELDS),
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    pub async fn get_all_categories(&self, list_id: i64) -> Result<Vec<Category>> {
+        let categories = sqlx::query_as::<_, Category>(&format!(
+            "SELECT {} FROM {TABLE_NAME_CATEGORIES}
+            WHERE {CATEGORIES_LIST_ID} = ?
+            ORDER BY {CATEGORIES_POSITION}",
+            all_fields(&CATEGORIES_FIELDS)
+        ))
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(categories)
+    }
+
+    /// get_category_entry_counts counts the active entries in each category of
+    /// the list, and how many of those are completed, by category id. A
+    /// category without entries is left out.
+    pub async fn get_category_entry_counts(
+        &self,
+        list_id: i64,
+    ) -> Result<HashMap<i64, (i64, i64)>> {
+        let rows: Vec<(i64, i64, i64)> = sqlx::query_as(&format!(
+            "SELECT e.{GROCERY_LIST_ENTRIES_CATEGORY_ID},
+                COUNT(*),
+                COUNT(e.{GROCERY_LIST_ENTRIES_COMPLETED_AT})
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} e
+            JOIN {TABLE_NAME_CATEGORIES} c ON c.{CATEGORIES_ID} = e.{GROCERY_LIST_ENTRIES_CATEGORY_ID}
+            WHERE c.{CATEGORIES_LIST_ID} = ?
+            AND e.{GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND e.{GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            GROUP BY e.{GROCERY_LIST_ENTRIES_CATEGORY_ID}"
+        ))
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(category_id, total, completed)| (category_id, (total, completed)))
+            .collect())
+    }
+
+    /// get_next_position_for_category gets the next position available for a
+    /// category in the given list (i.e. what position to append it to the end
+    /// of the list)
+    pub async fn get_next_position_for_category(&self, list_id: i64) -> Result<i64> {
+        let mut conn = self.pool.acquire().await?;
+        self.next_position_for_category(list_id, &mut conn).await
+    }
+
+    async fn next_position_for_category(
+        &self,
+        list_id: i64,
+        conn: &mut SqliteConnection,
+    ) -> Result<i64> {
+        let next_position = "next_position";
+        Ok(sqlx::query(&format!(
+            "SELECT {CATEGORIES_POSITION} + 1 as {next_position} 
+            FROM {TABLE_NAME_CATEGORIES} 
+            WHERE {CATEGORIES_LIST_ID} = ?
+            ORDER BY {CATEGORIES_POSITION} DESC 
+            LIMIT 1",
+        ))
+        .bind(list_id)
+        .fetch_optional(conn)
+        .await?
+        .map(|r| r.get(next_position))
+        .unwrap_or(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION))
+    }
+
+    pub async fn create_category(&self, category: CreateCategory) -> Result<Category> {
+        let list_id = category.list_id.unwrap_or(DEFAULT_LIST_ID);
+
+        let category = sqlx::query_as(&format!(
+            "INSERT INTO {TABLE_NAME_CATEGORIES} 
+            ({CATEGORIES_NAME}, {CATEGORIES_POSITION}, {CATEGORIES_LIST_ID}, {CATEGORIES_COLOR}, {CATEGORIES_ICON}, {CATEGORIES_MAX_ENTRIES}, {CATEGORIES_CREATED_AT}) 
+            VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP) 
+            RETURNING {}",
+            all_fields(&CATEGORIES_FIELDS)
+        ))
+        .bind(&category.name)
+        .bind(self.get_next_position_for_category(list_id).await?)
+        .bind(list_id)
+        .bind(&category.color)
+        .bind(&category.icon)
+        .bind(category.max_entries)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(category)
+    }
+
+    /// duplicate_category copies the category into a new one called name at
+    /// the end of its list, along with the entries in it that aren't completed.
+    /// The copies keep their order, pins and tags. A category that doesn't
+    /// exist is None.
+    pub async fn duplicate_category(
+        &self,
+        id: i64,
+        name: &str,
+    ) -> Result<Option<(Category, Vec<GroceryListEntry>)>> {
+        let Some(original) = self.get_category(id).await? else {
+            return Ok(None);
+        };
+
+        let mut tx = self.pool.begin().await?;
+
+        let position = self
+            .next_position_for_category(original.list_id, &mut tx)
+            .await?;
+        let category: Category = sqlx::query_as(&format!(
+            "INSERT INTO {TABLE_NAME_CATEGORIES}
+            ({CATEGORIES_NAME}, {CATEGORIES_POSITION}, {CATEGORIES_LIST_ID}, {CATEGORIES_COLOR}, {CATEGORIES_ICON}, {CATEGORIES_MAX_ENTRIES}, {CATEGORIES_CREATED_AT})
+            VALUES (?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
+            RETURNING {}",
+            all_fields(&CATEGORIES_FIELDS)
+        ))
+        .bind(name)
+        .bind(position)
+        .bind(original.list_id)
+        .bind(&original.color)
+        .bind(&original.icon)
+        .bind(original.max_entries)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let entries: Vec<GroceryListEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?
+            AND {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL
+            AND {GROCERY_LIST_ENTRIES_COMPLETED_AT} IS NULL
+            ORDER BY {GROCERY_LIST_ENTRIES_POSITION}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+        ))
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let positions = ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION..;
+        for (position, entry) in positions.zip(entries) {
+            let copy = self
+                .insert_entry(
+                    CreateGroceryListEntry {
+                        description: entry.description,
+                        position: Some(position),
+                        priority: Some(entry.priority),
+                        quantity: Some(entry.quantity),
+                        unit: Some(entry.unit),
+                        notes: entry.notes,
+                        price_cents: entry.price_cents,
+                        currency: entry.currency,
+                        visible_from: entry.visible_from,
+                        category_id: Some(category.id),
+                        list_id: None,
+                    },
+                    &mut tx,
+                )
+                .await?;
+
+            sqlx::query(&format!(
+                "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES} SET {GROCERY_LIST_ENTRIES_PINNED} = ?
+                WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+            ))
+            .bind(entry.pinned)
+            .bind(copy.id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(&format!(
+                "INSERT INTO {TABLE_NAME_ENTRY_TAGS} ({ENTRY_TAGS_ENTRY_ID}, {ENTRY_TAGS_TAG_ID})
+                SELECT ?, {ENTRY_TAGS_TAG_ID} FROM {TABLE_NAME_ENTRY_TAGS} WHERE {ENTRY_TAGS_ENTRY_ID} = ?"
+            ))
+            .bind(copy.id)
+            .bind(entry.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let copies = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?
+            ORDER BY {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_order_by(EntrySort::Position),
+        ))
+        .bind(category.id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some((category, copies)))
+    }
+
+    /// update_category updates the category if it is still at
+    /// category.version, bumping its version
+    pub async fn update_category(
+        &self,
+        id: i64,
+        category: UpdateCategory,
+    ) -> Result<VersionedUpdate<Category>> {
+        let mut query_builder =
+            sqlx::QueryBuilder::new(&format!("UPDATE {TABLE_NAME_CATEGORIES} SET "));
+        let mut separated = query_builder.separated(", ");
+
+        if let Some(name) = &category.name {
+            separated
+                .push(format!("{CATEGORIES_NAME} = "))
+                .push_bind_unseparated(name);
+        }
+        if let Some(color) = &category.color {
+            separated
+                .push(format!("{CATEGORIES_COLOR} = "))
+                .push_bind_unseparated((!color.is_empty()).then_some(color));
+        }
+        if let Some(icon) = &category.icon {
+            separated
+                .push(format!("{CATEGORIES_ICON} = "))
+                .push_bind_unseparated((!icon.is_empty()).then_some(icon));
+        }
+        if let Some(max_entries) = category.max_entries {
+            separated
+                .push(format!("{CATEGORIES_MAX_ENTRIES} = "))
+                .push_bind_unseparated(max_entries);
+        }
 
-        // update non-position fields of this entry
-        let row = query_builder.build().fetch_one(&mut *tx).await?;
+        separated
+            .push(CATEGORIES_UPDATED_AT)
+            .push_unseparated(" = CURRENT_TIMESTAMP");
+        separated.push(format!("{CATEGORIES_VERSION} = {CATEGORIES_VERSION} + 1"));
 
-        // then update positional/category fields for this and affected entries
-        if must_reorder {
-            // if no position is provided, we hit this code path because we are
-            // changing categories, in which case we want to default to the
-            // front of the list
-            let new_position = entry
-                .position
-                .unwrap_or(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION);
+        query_builder
+            .push(format!(" WHERE {CATEGORIES_ID} = "))
+            .push_bind(id)
+            .push(format!(" AND {CATEGORIES_VERSION} = "))
+            .push_bind(category.version);
+        query_builder.push(" RETURNING ");
+        query_builder.push(all_fields(&CATEGORIES_FIELDS));
 
-            // if no category is provided, we are just repositioning within the category
-            let new_category_id = entry
-                .category_id
-                .unwrap_or_else(|| row.get(GROCERY_LIST_ENTRIES_CATEGORY_ID));
+        let row = query_builder.build().fetch_optional(&self.pool).await?;
 
-            self.update_category_and_position_for_entry(id, new_position, new_category_id, &mut tx)
-                .await?;
+        if let Some(row) = row {
+            Ok(VersionedUpdate::Updated(Category::from_row(&row)?))
+        } else {
+            Ok(match self.get_category(id).await? {
+                Some(current) => VersionedUpdate::Conflict(current),
+                None => VersionedUpdate::NotFound,
+            })
         }
+    }
 
-        tx.commit().await?;
+    /// delete_category deletes the category along with its entries. Unless
+    /// force is set a category that still has active entries is left alone,
+    /// so that they aren't deleted by accident. A list's default category is
+    /// never deleted since entries fall back to it.
+    pub async fn delete_category(&self, id: i64, force: bool) -> Result<CategoryDeletion> {
+        let mut tx = self.pool.begin().await?;
 
-        Ok(Some(self.get_entry(id).await?))
-    }
+        let is_default: Option<bool> = sqlx::query_scalar(&format!(
+            "SELECT {CATEGORIES_IS_DEFAULT_CATEGORY} FROM {TABLE_NAME_CATEGORIES} WHERE {CATEGORIES_ID} = ?"
+        ))
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?;
 
-    pub async fn delete_entry(&self, id: i64) -> Result<bool> {
+        match is_default {
+            None => return Ok(CategoryDeletion::NotFound),
+            Some(true) => return Ok(CategoryDeletion::IsDefault),
+            Some(false) => {}
+        }
+
+        if !force {
+            let entries: i64 = sqlx::query_scalar(&format!(
+                "SELECT COUNT(*) FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+                WHERE {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?
+                AND {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+                AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL"
+            ))
+            .bind(id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if entries > 0 {
+                return Ok(CategoryDeletion::NotEmpty(entries));
+            }
+        }
+
+        // entries go with their category through the foreign key
         let result = sqlx::query(&format!(
-            "DELETE FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} WHERE id = ?"
+            "DELETE FROM {TABLE_NAME_CATEGORIES} WHERE {CATEGORIES_ID} = ?"
         ))
         .bind(id)
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(result.rows_affected() > 0)
+        tx.commit().await?;
+
+        if result.rows_affected() > 0 {
+            Ok(CategoryDeletion::Deleted)
+        } else {
+            Ok(CategoryDeletion::NotFound)
+        }
     }
 
-    /// get_prior_position_and_category is a helper for reorder_entries{_with_transaction}
-    async fn get_prior_position_and_category(&self, entry_id: i64) -> Result<(i64, i64)> {
-        let result: (i64, i64) = sqlx::query_as(&format!(
-            "SELECT {GROCERY_LIST_ENTRIES_POSITION}, {GROCERY_LIST_ENTRIES_CATEGORY_ID} 
-                FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} 
-                WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+    pub async fn get_all_lists(&self) -> Result<Vec<List>> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_LISTS} ORDER BY {LISTS_ID}",
+            all_fields(&LISTS_FIELDS)
         ))
-        .bind(entry_id)
-        .fetch_one(&self.pool)
-        .await?;
+        .fetch_all(&self.pool)
+        .await?)
+    }
 
-        Ok((result.0, result.1))
+    pub async fn get_list(&self, id: i64) -> Result<Option<List>> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_LISTS} WHERE {LISTS_ID} = ? LIMIT 1",
+            all_fields(&LISTS_FIELDS)
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?)
     }
 
-    async fn get_prior_position_for_category(&self, category_id: i64) -> Result<i64> {
-        let result: i64 = sqlx::query_scalar(&format!(
-            "SELECT {CATEGORIES_POSITION}
-                FROM {TABLE_NAME_CATEGORIES} 
-                WHERE {CATEGORIES_ID} = ?"
+    /// create_list creates a new list along with the default category that
+    /// entries in the list fall back to
+    pub async fn create_list(&self, list: CreateList) -> Result<List> {
+        let mut tx = self.pool.begin().await?;
+
+        let list: List = sqlx::query_as(&format!(
+            "INSERT INTO {TABLE_NAME_LISTS} ({LISTS_NAME}) VALUES (?) RETURNING {}",
+            all_fields(&LISTS_FIELDS)
         ))
-        .bind(category_id)
-        .fetch_one(&self.pool)
+        .bind(&list.name)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            "INSERT INTO {TABLE_NAME_CATEGORIES}
+            ({CATEGORIES_NAME}, {CATEGORIES_IS_DEFAULT_CATEGORY}, {CATEGORIES_POSITION}, {CATEGORIES_LIST_ID}, {CATEGORIES_CREATED_AT})
+            VALUES (?, TRUE, ?, ?, CURRENT_TIMESTAMP)"
+        ))
+        .bind(DEFAULT_CATEGORY_NAME)
+        .bind(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION)
+        .bind(list.id)
+        .execute(&mut *tx)
         .await?;
 
-        Ok(result)
+        tx.commit().await?;
+
+        Ok(list)
     }
 
-    /// reorder_entries will assign the specific entry.new_position to the entry
-    /// with entry.id, adjusting all other entries in the same category up or
-    /// down as needed to make room for the updated entry
-    pub async fn reorder_entries(&self, reorder_request: ReorderEntry) -> Result<()> {
-        let entry = self.get_entry(reorder_request.id).await?;
+    pub async fn update_list(&self, id: i64, list: UpdateList) -> Result<Option<List>> {
+        let mut query_builder =
+            sqlx::QueryBuilder::new(format!("UPDATE {TABLE_NAME_LISTS} SET "));
+        let mut separated = query_builder.separated(", ");
 
-        // if no position is provided, we hit this code path because we are
-        // changing categories, in which case we want to default to the
-        // front of the list
-        let new_position = reorder_request
-            .new_position
-            .unwrap_or(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION);
+        if let Some(name) = &list.name {
+            separated
+                .push(format!("{LISTS_NAME} = "))
+                .push_bind_unseparated(name);
+        }
 
-        // if no category is provided, we are just repositioning within the category
-        let new_category_id = reorder_request.new_category_id.unwrap_or(entry.category_id);
+        separated
+            .push(LISTS_UPDATED_AT)
+            .push_unseparated(" = CURRENT_TIMESTAMP");
 
-        let mut tx = self.pool.begin().await?;
+        query_builder
+            .push(format!(" WHERE {LISTS_ID} = "))
+            .push_bind(id);
+        query_builder.push(" RETURNING ");
+        query_builder.push(all_fields(&LISTS_FIELDS));
 
-        self.update_category_and_position_for_entry(
-            reorder_request.id,
-            new_position,
-            new_category_id,
-            &mut tx,
-        )
-        .await
-        .inspect_err(|e| tracing::error!("failed to update_category_and_position: {}", e))?;
+        let row = query_builder.build().fetch_optional(&self.pool).await?;
 
-        tx.commit().await?;
+        if let Some(row) = row {
+            Ok(Some(List::from_row(&row)?))
+        } else {
+            Ok(None)
+        }
+    }
 
-        Ok(())
+    /// delete_list deletes the list, and with it all of its categories and
+    /// entries
+    pub async fn delete_list(&self, id: i64) -> Result<bool> {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {TABLE_NAME_LISTS} WHERE {LISTS_ID} = ?"
+        ))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
     }
 
-    /// reorder_categories will assign the specific category.new_position to the category
-    /// with category.id, adjusting all other categories up or
-    /// down as needed to make room for the updated category
-    pub async fn reorder_categories(&self, reorder_request: ReorderCategory) -> Result<()> {
+    /// share_list gets the list's read-only share, minting a token for it if
+    /// it hasn't been shared yet. A list that doesn't exist can't be shared.
+    pub async fn share_list(&self, list_id: i64) -> Result<Option<ListShare>> {
         let mut tx = self.pool.begin().await?;
 
-        self.update_position_for_category(
-            reorder_request.id,
-            reorder_request.new_position,
-            &mut tx,
-        )
-        .await
-        .inspect_err(|e| tracing::error!("failed to update_category_and_position: {}", e))?;
+        // the token is 128 random bits, which can't be guessed
+        sqlx::query(&format!(
+            "INSERT INTO {TABLE_NAME_LIST_SHARES} ({LIST_SHARES_LIST_ID}, {LIST_SHARES_TOKEN})
+            SELECT {LISTS_ID}, LOWER(HEX(RANDOMBLOB(16))) FROM {TABLE_NAME_LISTS}
+            WHERE {LISTS_ID} = ?
+            ON CONFLICT({LIST_SHARES_LIST_ID}) DO NOTHING"
+        ))
+        .bind(list_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let share = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_LIST_SHARES} WHERE {LIST_SHARES_LIST_ID} = ?",
+            all_fields(&LIST_SHARES_FIELDS)
+        ))
+        .bind(list_id)
+        .fetch_optional(&mut *tx)
+        .await?;
 
         tx.commit().await?;
 
-        Ok(())
+        Ok(share)
     }
 
-    async fn update_category_and_position_for_entry(
-        &self,
-        entry_id: i64,
-        new_position: i64,
-        new_category_id: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        let (prior_position, prior_category_id) =
-            self.get_prior_position_and_category(entry_id).await?;
+    /// unshare_list revokes the list's read-only share, so that its token no
+    /// longer works. Sharing the list again mints a new token.
+    pub async fn unshare_list(&self, list_id: i64) -> Result<bool> {
+        let result = sqlx::query(&format!(
+            "DELETE FROM {TABLE_NAME_LIST_SHARES} WHERE {LIST_SHARES_LIST_ID} = ?"
+        ))
+        .bind(list_id)
+        .execute(&self.pool)
+        .await?;
 
-        self.remove_entry_from_ordering(entry_id, tx).await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-        self.decrement_entry_positions_gt(prior_position, prior_category_id, tx)
-            .await?;
+    /// get_shared_list_id gets the id of the list shared with token, if the
+    /// share hasn't been revoked
+    pub async fn get_shared_list_id(&self, token: &str) -> Result<Option<i64>> {
+        Ok(sqlx::query_scalar(&format!(
+            "SELECT {LIST_SHARES_LIST_ID} FROM {TABLE_NAME_LIST_SHARES} WHERE {LIST_SHARES_TOKEN} = ?"
+        ))
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
 
-        self.increment_entry_positions_ge(new_position, new_category_id, tx)
-            .await?;
+    /// archive_list snapshots the active entries in the list into a new
+    /// archived trip and then archives the entries, clearing the list. Both
+    /// happen in one transaction so that entries can't be lost in between.
+    /// A list with no active entries has nothing to archive.
+    pub async fn archive_list(&self, list_id: i64) -> Result<Option<ArchivedTrip>> {
+        let mut tx = self.pool.begin().await?;
 
-        self.insert_entry_in_ordering(entry_id, new_position, new_category_id, tx)
-            .await?;
+        let entries: Vec<GroceryListEntry> = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?
+            ORDER BY {}",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS),
+            entry_order_by(EntrySort::Position),
+        ))
+        .bind(list_id)
+        .fetch_all(&mut *tx)
+        .await?;
 
-        Ok(())
-    }
+        if entries.is_empty() {
+            return Ok(None);
+        }
 
-    async fn update_position_for_category(
-        &self,
-        category_id: i64,
-        new_position: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        let prior_position = self.get_prior_position_for_category(category_id).await?;
+        let entries: Vec<ApiGroceryListEntry> = entries.iter().map(Into::into).collect();
+        let row = sqlx::query(&format!(
+            "INSERT INTO {TABLE_NAME_ARCHIVED_TRIPS} ({ARCHIVED_TRIPS_LIST_ID}, {ARCHIVED_TRIPS_ENTRIES})
+            VALUES (?, ?) RETURNING {}",
+            all_fields(&ARCHIVED_TRIPS_FIELDS)
+        ))
+        .bind(list_id)
+        .bind(serde_json::to_string(&entries)?)
+        .fetch_one(&mut *tx)
+        .await?;
+        let trip = archived_trip_from_row(&row)?;
 
-        self.remove_category_from_ordering(category_id, tx).await?;
+        sqlx::query(&format!(
+            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            SET
+                {GROCERY_LIST_ENTRIES_ARCHIVED_AT} = CURRENT_TIMESTAMP,
+                {GROCERY_LIST_ENTRIES_POSITION} = NULL,
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP
+            WHERE {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            AND {GROCERY_LIST_ENTRIES_LIST_ID} = ?"
+        ))
+        .bind(list_id)
+        .execute(&mut *tx)
+        .await?;
 
-        self.decrement_category_positions_gt(prior_position, tx)
-            .await?;
+        tx.commit().await?;
 
-        self.increment_category_positions_ge(new_position, tx)
-            .await?;
+        Ok(Some(trip))
+    }
 
-        self.insert_category_in_ordering(category_id, new_position, tx)
-            .await
-            .inspect_err(|e| tracing::error!("failed to insert category in ordering: {}", e))?;
+    /// get_archived_trips gets the archived trips, most recent first, for one
+    /// list or for every list
+    pub async fn get_archived_trips(&self, list_id: Option<i64>) -> Result<Vec<ArchivedTrip>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM {TABLE_NAME_ARCHIVED_TRIPS}
+            WHERE ? IS NULL OR {ARCHIVED_TRIPS_LIST_ID} = ?
+            ORDER BY {ARCHIVED_TRIPS_ARCHIVED_AT} DESC, {ARCHIVED_TRIPS_ID} DESC",
+            all_fields(&ARCHIVED_TRIPS_FIELDS)
+        ))
+        .bind(list_id)
+        .bind(list_id)
+        .fetch_all(&self.pool)
+        .await?;
 
-        Ok(())
+        rows.iter().map(archived_trip_from_row).collect()
     }
 
-    async fn remove_entry_from_ordering(
-        &self,
-        entry_id: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
-            SET {GROCERY_LIST_ENTRIES_POSITION} = ? 
-            WHERE {GROCERY_LIST_ENTRIES_ID} = ?"
+    pub async fn get_archived_trip(&self, id: i64) -> Result<Option<ArchivedTrip>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM {TABLE_NAME_ARCHIVED_TRIPS} WHERE {ARCHIVED_TRIPS_ID} = ? LIMIT 1",
+            all_fields(&ARCHIVED_TRIPS_FIELDS)
         ))
-        .bind(ORDERABLE_LIST_REORDER_TEMPORARY_POSITION)
-        .bind(entry_id)
-        .execute(&mut **tx)
+        .bind(id)
+        .fetch_optional(&self.pool)
         .await?;
-        Ok(())
+
+        row.as_ref().map(archived_trip_from_row).transpose()
     }
 
-    async fn remove_category_from_ordering(
+    /// restore_archived_trip adds the entries from an archived trip back to its
+    /// list as new, uncompleted entries at the end of their categories. Entries
+    /// whose category has since been deleted go where a new entry with that
+    /// description would. With categories_only just the descriptions and
+    /// categories are restored, not quantities, units, notes or priorities.
+    pub async fn restore_archived_trip(
         &self,
-        category_id: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_CATEGORIES}
-            SET {CATEGORIES_POSITION} = ? 
-            WHERE {CATEGORIES_ID} = ?"
+        id: i64,
+        categories_only: bool,
+    ) -> Result<Option<Vec<GroceryListEntry>>> {
+        let Some(trip) = self.get_archived_trip(id).await? else {
+            return Ok(None);
+        };
+
+        let category_ids: Vec<i64> = self
+            .get_all_categories(trip.list_id)
+            .await?
+            .iter()
+            .map(|category| category.id)
+            .collect();
+
+        let entries = trip
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let category_id = Some(entry.category_id).filter(|id| category_ids.contains(id));
+                if categories_only {
+                    CreateGroceryListEntry {
+                        description: entry.description,
+                        position: None,
+                        priority: None,
+                        quantity: None,
+                        unit: None,
+                        notes: None,
+                        price_cents: None,
+                        currency: None,
+                        visible_from: None,
+                        category_id,
+                        list_id: Some(trip.list_id),
+                    }
+                } else {
+                    CreateGroceryListEntry {
+                        description: entry.description,
+                        position: None,
+                        priority: Some(entry.priority),
+                        quantity: Some(entry.quantity),
+                        unit: Some(entry.unit),
+                        notes: entry.notes,
+                        price_cents: entry.price_cents,
+                        currency: entry.currency,
+                        visible_from: None,
+                        category_id,
+                        list_id: Some(trip.list_id),
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Some(self.create_entries(entries).await?))
+    }
+
+    pub async fn create_template(&self, template: CreateTemplate) -> Result<Template> {
+        let row = sqlx::query(&format!(
+            "INSERT INTO {TABLE_NAME_TEMPLATES} ({TEMPLATES_NAME}, {TEMPLATES_ITEMS})
+            VALUES (?, ?) RETURNING {}",
+            all_fields(&TEMPLATES_FIELDS)
         ))
-        .bind(ORDERABLE_LIST_REORDER_TEMPORARY_POSITION)
-        .bind(category_id)
-        .execute(&mut **tx)
+        .bind(&template.name)
+        .bind(serde_json::to_string(&template.items)?)
+        .fetch_one(&self.pool)
         .await?;
-        Ok(())
+
+        template_from_row(&row)
     }
 
-    async fn insert_entry_in_ordering(
-        &self,
-        entry_id: i64,
-        new_position: i64,
-        new_category_id: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES} 
-            SET {GROCERY_LIST_ENTRIES_POSITION} = ?, {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ? WHERE
-            {GROCERY_LIST_ENTRIES_ID} = ?"
+    pub async fn get_templates(&self) -> Result<Vec<Template>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM {TABLE_NAME_TEMPLATES} ORDER BY {TEMPLATES_NAME}",
+            all_fields(&TEMPLATES_FIELDS)
         ))
-        .bind(new_position)
-        .bind(new_category_id)
-        .bind(entry_id)
-        .execute(&mut **tx)
+        .fetch_all(&self.pool)
         .await?;
-        Ok(())
+
+        rows.iter().map(template_from_row).collect()
     }
 
-    async fn insert_category_in_ordering(
-        &self,
-        category_id: i64,
-        new_position: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_CATEGORIES} 
-            SET {CATEGORIES_POSITION} = ? WHERE
-            {CATEGORIES_ID} = ?"
+    pub async fn get_template(&self, id: i64) -> Result<Option<Template>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM {TABLE_NAME_TEMPLATES} WHERE {TEMPLATES_ID} = ? LIMIT 1",
+            all_fields(&TEMPLATES_FIELDS)
         ))
-        .bind(new_position)
-        .bind(category_id)
-        .execute(&mut **tx)
+        .bind(id)
+        .fetch_optional(&self.pool)
         .await?;
-        Ok(())
+
+        row.as_ref().map(template_from_row).transpose()
+    }
+
+    /// apply_template adds the template's items to the list, skipping any that
+    /// are already on it, compared ignoring case and surrounding whitespace.
+    /// Items whose category isn't in the list go where a new entry with that
+    /// description would.
+    pub async fn apply_template(
+        &self,
+        id: i64,
+        list_id: i64,
+    ) -> Result<Option<Vec<GroceryListEntry>>> {
+        let Some(template) = self.get_template(id).await? else {
+            return Ok(None);
+        };
+
+        let category_ids: Vec<i64> = self
+            .get_all_categories(list_id)
+            .await?
+            .iter()
+            .map(|category| category.id)
+            .collect();
+
+        let mut present: std::collections::HashSet<String> = self
+            .get_active_entries(list_id, EntrySort::Position, EntryFilter::default())
+            .await?
+            .iter()
+            .map(|entry| entry.description.trim().to_lowercase())
+            .collect();
+
+        let entries = template
+            .items
+            .into_iter()
+            .filter(|item| present.insert(item.description.trim().to_lowercase()))
+            .map(|item| CreateGroceryListEntry {
+                description: item.description,
+                position: None,
+                priority: None,
+                quantity: None,
+                unit: None,
+                notes: None,
+                price_cents: None,
+                currency: None,
+                visible_from: None,
+                category_id: item.category_id.filter(|id| category_ids.contains(id)),
+                list_id: Some(list_id),
+            })
+            .collect();
+
+        Ok(Some(self.create_entries(entries).await?))
     }
 
-    /// decrement_positions_gt decrements the position field if the position is greater than the provided position and the category_id matches
-    async fn decrement_entry_positions_gt(
+    /// begin_export starts the transaction an export is read in, so that it is
+    /// consistent even if the list is being edited while it streams out, and
+    /// reads every list and category in display order
+    pub async fn begin_export(
         &self,
-        prior_position: i64,
-        prior_category_id: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
-            SET {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} + {}
-            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
-                AND {GROCERY_LIST_ENTRIES_POSITION} > ? 
-                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
-            MAX_NUM_POSITIONED_GROCERY_ITEMS - 1
+    ) -> Result<(sqlx::Transaction<'static, Sqlite>, Vec<List>, Vec<Category>)> {
+        let mut tx = self.pool.begin().await?;
+
+        let lists = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_LISTS} ORDER BY {LISTS_ID}",
+            all_fields(&LISTS_FIELDS)
         ))
-        .bind(prior_position)
-        .bind(prior_category_id)
-        .execute(&mut **tx)
+        .fetch_all(&mut *tx)
         .await?;
 
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
-            SET {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} - {MAX_NUM_POSITIONED_GROCERY_ITEMS}
-            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
-                AND {GROCERY_LIST_ENTRIES_POSITION} > ? 
-                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
+        let categories = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_CATEGORIES}
+            ORDER BY {CATEGORIES_LIST_ID}, {CATEGORIES_POSITION}, {CATEGORIES_ID}",
+            all_fields(&CATEGORIES_FIELDS)
         ))
-        .bind(prior_position)
-        .bind(prior_category_id)
-        .execute(&mut **tx)
+        .fetch_all(&mut *tx)
         .await?;
-        Ok(())
+
+        Ok((tx, lists, categories))
     }
 
-    /// decrement_positions_gt decrements the position field if the position is greater than the provided position and the category_id matches
-    async fn decrement_category_positions_gt(
+    /// export_entries streams every entry that isn't in the trash, one row at
+    /// a time, grouped by category in the same order begin_export reads the
+    /// categories in
+    pub fn export_entries<'t>(
         &self,
-        prior_position: i64,
+        tx: &'t mut sqlx::Transaction<'static, Sqlite>,
+    ) -> impl Stream<Item = Result<GroceryListEntry, sqlx::Error>> + Send + 't {
+        sqlx::query_as(export_entries_query()).fetch(&mut **tx)
+    }
+
+    /// import recreates the lists, categories and entries of an export in a
+    /// single transaction, so a failed import leaves the existing data as it
+    /// was. Imported rows get new ids. Lists and categories are matched to
+    /// existing ones by name, the exported default list is imported into the
+    /// default list and each list's default category into that list's default
+    /// category. Imported entries are appended to the end of their category.
+    ///
+    /// In ImportMode::Replace everything except the default list and its
+    /// default category is deleted first. A dry run does all of that and rolls
+    /// it back, so the summary is exactly what the import would do, and the
+    /// changes are what it would have made.
+    pub async fn import(
+        &self,
+        document: ExportDocument,
+        mode: ImportMode,
+        dry_run: bool,
+    ) -> Result<(ImportSummary, ImportChanges)> {
+        let mut tx = self.pool.begin().await?;
+        let mut summary = ImportSummary {
+            dry_run,
+            ..ImportSummary::default()
+        };
+        let mut changes = ImportChanges::default();
+
+        if mode == ImportMode::Replace {
+            changes.removed_entries = sqlx::query_as(&format!(
+                "DELETE FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} RETURNING {}",
+                all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+            ))
+            .fetch_all(&mut *tx)
+            .await?;
+            changes.removed_categories = sqlx::query_as(&format!(
+                "DELETE FROM {TABLE_NAME_CATEGORIES}
+                WHERE NOT ({CATEGORIES_LIST_ID} = ? AND {CATEGORIES_IS_DEFAULT_CATEGORY})
+                RETURNING {}",
+                all_fields(&CATEGORIES_FIELDS)
+            ))
+            .bind(DEFAULT_LIST_ID)
+            .fetch_all(&mut *tx)
+            .await?;
+            changes.removed_lists = sqlx::query_as(&format!(
+                "DELETE FROM {TABLE_NAME_LISTS} WHERE {LISTS_ID} <> ? RETURNING {}",
+                all_fields(&LISTS_FIELDS)
+            ))
+            .bind(DEFAULT_LIST_ID)
+            .fetch_all(&mut *tx)
+            .await?;
+        }
+
+        summary.id_collisions = self.import_id_collisions(&document, &mut tx).await?;
+
+        for ExportList { list, categories } in document.lists {
+            let list_id = self
+                .import_list(&list, mode, &mut summary, &mut changes, &mut tx)
+                .await?;
+
+            for ExportCategory { category, entries } in categories {
+                let category_id = self
+                    .import_category(&category, list_id, &mut summary, &mut changes, &mut tx)
+                    .await?;
+
+                let mut added = 0;
+                for entry in entries {
+                    // entries in the trash are not part of an export
+                    if entry.deleted_at.is_some() {
+                        continue;
+                    }
+
+                    let position = match entry.archived_at {
+                        Some(_) => None,
+                        None => Some(
+                            self.next_position_for_item_in_category(category_id, &mut tx)
+                                .await?,
+                        ),
+                    };
+
+                    let imported = sqlx::query_as(&format!(
+                        "INSERT INTO {TABLE_NAME_GROCERY_LIST_ENTRIES}
+                            (
+                                {GROCERY_LIST_ENTRIES_DESCRIPTION},
+                                {GROCERY_LIST_ENTRIES_COMPLETED_AT},
+                                {GROCERY_LIST_ENTRIES_ARCHIVED_AT},
+                                {GROCERY_LIST_ENTRIES_POSITION},
+                                {GROCERY_LIST_ENTRIES_QUANTITY},
+                                {GROCERY_LIST_ENTRIES_UNIT},
+                                {GROCERY_LIST_ENTRIES_NOTES},
+                                {GROCERY_LIST_ENTRIES_PRIORITY},
+                                {GROCERY_LIST_ENTRIES_CATEGORY_ID},
+                                {GROCERY_LIST_ENTRIES_LIST_ID},
+                                {GROCERY_LIST_ENTRIES_CREATED_AT},
+                                {GROCERY_LIST_ENTRIES_PRICE_CENTS},
+                                {GROCERY_LIST_ENTRIES_CURRENCY},
+                                {GROCERY_LIST_ENTRIES_PINNED},
+                                {GROCERY_LIST_ENTRIES_VISIBLE_FROM}
+                            )
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        RETURNING {}",
+                        all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
+                    ))
+                    .bind(&entry.description)
+                    .bind(entry.completed_at)
+                    .bind(entry.archived_at)
+                    .bind(position)
+                    .bind(&entry.quantity)
+                    .bind(&entry.unit)
+                    .bind(&entry.notes)
+                    .bind(entry.priority)
+                    .bind(category_id)
+                    .bind(list_id)
+                    .bind(entry.created_at)
+                    .bind(entry.price_cents)
+                    .bind(&entry.currency)
+                    .bind(entry.pinned)
+                    .bind(entry.visible_from)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                    changes.entries.push(imported);
+
+                    summary.entries += 1;
+                    if entry.archived_at.is_none() {
+                        added += 1;
+                    }
+                }
+                if added > 0 {
+                    self.ensure_category_room(category_id, added, &mut tx)
+                        .await?;
+                }
+            }
+
+            // every list needs a default category for entries to fall back to
+            let has_default_category: bool = sqlx::query_scalar(&format!(
+                "SELECT EXISTS (
+                    SELECT 1 FROM {TABLE_NAME_CATEGORIES}
+                    WHERE {CATEGORIES_LIST_ID} = ? AND {CATEGORIES_IS_DEFAULT_CATEGORY}
+                )"
+            ))
+            .bind(list_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if !has_default_category {
+                let position = self.next_position_for_category(list_id, &mut tx).await?;
+                sqlx::query(&format!(
+                    "INSERT INTO {TABLE_NAME_CATEGORIES}
+                    ({CATEGORIES_NAME}, {CATEGORIES_IS_DEFAULT_CATEGORY}, {CATEGORIES_POSITION}, {CATEGORIES_LIST_ID}, {CATEGORIES_CREATED_AT})
+                    VALUES (?, TRUE, ?, ?, CURRENT_TIMESTAMP)"
+                ))
+                .bind(DEFAULT_CATEGORY_NAME)
+                .bind(position)
+                .bind(list_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        if dry_run {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+
+        Ok((summary, changes))
+    }
+
+    /// import_id_collisions describes the rows in the document whose id is used
+    /// by more than one row of the same kind, and the entries whose id is
+    /// already taken by an existing entry, which a dry run of the import
+    /// reports
+    async fn import_id_collisions(
+        &self,
+        document: &ExportDocument,
         tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_CATEGORIES}
-            SET {CATEGORIES_POSITION} = {CATEGORIES_POSITION} + {}
-            WHERE {CATEGORIES_POSITION} > ?",
-            MAX_NUM_POSITIONED_GROCERY_ITEMS - 1
+    ) -> Result<Vec<String>> {
+        let mut collisions = Vec::new();
+        let (mut lists, mut categories, mut entries) =
+            (HashSet::new(), HashSet::new(), HashSet::new());
+
+        for ExportList {
+            list,
+            categories: exported,
+        } in &document.lists
+        {
+            if !lists.insert(list.id) {
+                collisions.push(format!("list {} appears more than once", list.id));
+            }
+            for ExportCategory {
+                category,
+                entries: exported,
+            } in exported
+            {
+                if !categories.insert(category.id) {
+                    collisions.push(format!("category {} appears more than once", category.id));
+                }
+                // entries in the trash are not imported, so can't collide
+                for entry in exported.iter().filter(|entry| entry.deleted_at.is_none()) {
+                    if !entries.insert(entry.id) {
+                        collisions.push(format!("entry {} appears more than once", entry.id));
+                        continue;
+                    }
+
+                    let taken: bool = sqlx::query_scalar(&format!(
+                        "SELECT EXISTS (
+                            SELECT 1 FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} WHERE {GROCERY_LIST_ENTRIES_ID} = ?
+                        )"
+                    ))
+                    .bind(entry.id)
+                    .fetch_one(&mut **tx)
+                    .await?;
+                    if taken {
+                        collisions.push(format!("entry {} already exists", entry.id));
+                    }
+                }
+            }
+        }
+
+        Ok(collisions)
+    }
+
+    /// import_list finds or creates the list that an exported list is
+    /// imported into, returning its id
+    async fn import_list(
+        &self,
+        list: &List,
+        mode: ImportMode,
+        summary: &mut ImportSummary,
+        changes: &mut ImportChanges,
+        tx: &mut sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<i64> {
+        if list.id == DEFAULT_LIST_ID {
+            // when replacing, the default list takes on the exported name
+            // too, unless another imported list already has it
+            if mode == ImportMode::Replace {
+                sqlx::query(&format!(
+                    "UPDATE OR IGNORE {TABLE_NAME_LISTS}
+                    SET {LISTS_NAME} = ?, {LISTS_UPDATED_AT} = CURRENT_TIMESTAMP
+                    WHERE {LISTS_ID} = ?"
+                ))
+                .bind(&list.name)
+                .bind(DEFAULT_LIST_ID)
+                .execute(&mut **tx)
+                .await?;
+            }
+            summary.merged_lists += 1;
+            return Ok(DEFAULT_LIST_ID);
+        }
+
+        let existing: Option<i64> = sqlx::query_scalar(&format!(
+            "SELECT {LISTS_ID} FROM {TABLE_NAME_LISTS} WHERE {LISTS_NAME} = ?"
         ))
-        .bind(prior_position)
-        .execute(&mut **tx)
+        .bind(&list.name)
+        .fetch_optional(&mut **tx)
         .await?;
 
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_CATEGORIES}
-            SET {CATEGORIES_POSITION} = {CATEGORIES_POSITION} - {MAX_NUM_POSITIONED_GROCERY_ITEMS}
-            WHERE {CATEGORIES_POSITION} > ?",
+        if let Some(id) = existing {
+            summary.merged_lists += 1;
+            return Ok(id);
+        }
+
+        summary.lists += 1;
+        let imported: List = sqlx::query_as(&format!(
+            "INSERT INTO {TABLE_NAME_LISTS} ({LISTS_NAME}) VALUES (?) RETURNING {}",
+            all_fields(&LISTS_FIELDS)
         ))
-        .bind(prior_position)
-        .execute(&mut **tx)
+        .bind(&list.name)
+        .fetch_one(&mut **tx)
         .await?;
-        Ok(())
+        let id = imported.id;
+        changes.lists.push(imported);
+        Ok(id)
     }
 
-    /// increment_positions_ge increments the position field if the position is greather than OR equal to the provided position and the category_id matches
-    async fn increment_entry_positions_ge(
+    /// import_category finds or creates the category in list_id that an
+    /// exported category is imported into, returning its id
+    async fn import_category(
         &self,
-        new_position: i64,
-        new_category_id: i64,
+        category: &Category,
+        list_id: i64,
+        summary: &mut ImportSummary,
+        changes: &mut ImportChanges,
         tx: &mut sqlx::Transaction<'static, Sqlite>,
-    ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
-            SET {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} + {MAX_NUM_POSITIONED_GROCERY_ITEMS}
-            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
-                AND {GROCERY_LIST_ENTRIES_POSITION} >= ? 
-                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
-        ))
-        .bind(new_position)
-        .bind(new_category_id)
-        .execute(&mut **tx)
-        .await?;
+    ) -> Result<i64> {
+        let existing: Option<i64> = if category.is_default_category {
+            sqlx::query_scalar(&format!(
+                "SELECT {CATEGORIES_ID} FROM {TABLE_NAME_CATEGORIES}
+                WHERE {CATEGORIES_LIST_ID} = ? AND {CATEGORIES_IS_DEFAULT_CATEGORY}
+                ORDER BY {CATEGORIES_ID}
+                LIMIT 1"
+            ))
+            .bind(list_id)
+            .fetch_optional(&mut **tx)
+            .await?
+        } else {
+            sqlx::query_scalar(&format!(
+                "SELECT {CATEGORIES_ID} FROM {TABLE_NAME_CATEGORIES}
+                WHERE {CATEGORIES_LIST_ID} = ? AND {CATEGORIES_NAME} = ?"
+            ))
+            .bind(list_id)
+            .bind(&category.name)
+            .fetch_optional(&mut **tx)
+            .await?
+        };
 
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES}
-            SET {GROCERY_LIST_ENTRIES_POSITION} = {GROCERY_LIST_ENTRIES_POSITION} - {}
-            WHERE {GROCERY_LIST_ENTRIES_POSITION} IS NOT NULL 
-                AND {GROCERY_LIST_ENTRIES_POSITION} >= ? 
-                AND {GROCERY_LIST_ENTRIES_CATEGORY_ID} = ?",
-            MAX_NUM_POSITIONED_GROCERY_ITEMS - 1
+        if let Some(id) = existing {
+            summary.merged_categories += 1;
+            return Ok(id);
+        }
+
+        let position = self.next_position_for_category(list_id, tx).await?;
+
+        summary.categories += 1;
+        let created: Category = sqlx::query_as(&format!(
+            "INSERT INTO {TABLE_NAME_CATEGORIES}
+            ({CATEGORIES_NAME}, {CATEGORIES_IS_DEFAULT_CATEGORY}, {CATEGORIES_POSITION}, {CATEGORIES_LIST_ID}, {CATEGORIES_COLOR}, {CATEGORIES_ICON}, {CATEGORIES_MAX_ENTRIES}, {CATEGORIES_CREATED_AT})
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            RETURNING {}",
+            all_fields(&CATEGORIES_FIELDS)
         ))
-        .bind(new_position)
-        .bind(new_category_id)
-        .execute(&mut **tx)
+        .bind(&category.name)
+        .bind(category.is_default_category)
+        .bind(position)
+        .bind(list_id)
+        .bind(&category.color)
+        .bind(&category.icon)
+        .bind(category.max_entries)
+        .bind(category.created_at)
+        .fetch_one(&mut **tx)
         .await?;
 
-        Ok(())
+        let id = created.id;
+        changes.categories.push(created);
+        Ok(id)
     }
 
-    /// increment_positions_ge increments the position field if the position is greather than OR equal to the provided position and the category_id matches
-    async fn increment_category_positions_ge(
+    /// record_audit adds a change to a row of table to the audit log
+    pub async fn record_audit(
         &self,
-        new_position: i64,
-        tx: &mut sqlx::Transaction<'static, Sqlite>,
+        table: AuditTable,
+        row_id: i64,
+        action: AuditAction,
+        diff: &serde_json::Value,
     ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_CATEGORIES}
-            SET {CATEGORIES_POSITION} = {CATEGORIES_POSITION} + {MAX_NUM_POSITIONED_GROCERY_ITEMS}
-            WHERE {CATEGORIES_POSITION} >= ?",
-        ))
-        .bind(new_position)
-        .execute(&mut **tx)
-        .await?;
+        let table_name = match table {
+            AuditTable::Entries => TABLE_NAME_GROCERY_LIST_ENTRIES,
+            AuditTable::Categories => TABLE_NAME_CATEGORIES,
+            AuditTable::Lists => TABLE_NAME_LISTS,
+        };
 
         sqlx::query(&format!(
-            "UPDATE {TABLE_NAME_CATEGORIES}
-            SET {CATEGORIES_POSITION} = {CATEGORIES_POSITION} - {}
-            WHERE {CATEGORIES_POSITION} >= ?",
-            MAX_NUM_POSITIONED_GROCERY_ITEMS - 1
+            "INSERT INTO {TABLE_NAME_AUDIT_LOG}
+            ({AUDIT_LOG_TABLE_NAME}, {AUDIT_LOG_ROW_ID}, {AUDIT_LOG_ACTION}, {AUDIT_LOG_DIFF})
+            VALUES (?, ?, ?, ?)"
         ))
-        .bind(new_position)
-        .execute(&mut **tx)
+        .bind(table_name)
+        .bind(row_id)
+        .bind(action.as_str())
+        .bind(diff.to_string())
+        .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    pub async fn get_category(&self, id: i64) -> Result<Category> {
-        Ok(sqlx::query_as(&format!(
-            "SELECT {} FROM {TABLE_NAME_CATEGORIES} WHERE {CATEGORIES_ID} = ? LIMIT 1",
-            all_fields(&CATEGORIES_FIELDS),
-        ))
-        .bind(id)
-        .fetch_one(&self.pool)
-        .await?)
-    }
-
-    pub async fn get_all_categories(&self) -> Result<Vec<Category>> {
-        let categories = sqlx::query_as::<_, Category>(&format!(
-            "SELECT {} FROM {TABLE_NAME_CATEGORIES} ORDER BY {CATEGORIES_POSITION}",
-            all_fields(&CATEGORIES_FIELDS)
-        ))
-        .fetch_all(&self.pool)
-        .await?;
+    /// get_audit_log gets up to limit of the most recent changes in the audit
+    /// log, newest first
+    pub async fn get_audit_log(&self, limit: i64) -> Result<Vec<AuditEntry>> {
+        let rows: Vec<(i64, DateTime<Utc>, String, i64, String, String)> =
+            sqlx::query_as(&format!(
+                "SELECT {} FROM {TABLE_NAME_AUDIT_LOG}
+                ORDER BY {AUDIT_LOG_ID} DESC
+                LIMIT ?",
+                all_fields(&AUDIT_LOG_FIELDS)
+            ))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
 
-        Ok(categories)
+        rows.into_iter()
+            .map(|(id, created_at, table_name, row_id, action, diff)| {
+                Ok(AuditEntry {
+                    id,
+                    created_at,
+                    table_name,
+                    row_id,
+                    action,
+                    diff: serde_json::from_str(&diff)?,
+                })
+            })
+            .collect()
     }
 
-    /// get_next_position_for_category gets the next position available for a
-    /// category (i.e. what position to append it to the end of the list)
-    pub async fn get_next_position_for_category(&self) -> Result<i64> {
-        let next_position = "next_position";
-        Ok(sqlx::query(&format!(
-            "SELECT {CATEGORIES_POSITION} + 1 as {next_position} 
-            FROM {TABLE_NAME_CATEGORIES} 
-            ORDER BY {CATEGORIES_POSITION} DESC 
-            LIMIT 1",
+    /// get_idempotent_entry gets the entry that was created with the
+    /// Idempotency-Key, as it is now, if the key was used within the last
+    /// IDEMPOTENCY_KEY_TTL_HOURS
+    pub async fn get_idempotent_entry(&self, key: &str) -> Result<Option<GroceryListEntry>> {
+        let entry = sqlx::query_as(&format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES}
+            WHERE {GROCERY_LIST_ENTRIES_ID} = (
+                SELECT {IDEMPOTENCY_KEYS_ENTRY_ID} FROM {TABLE_NAME_IDEMPOTENCY_KEYS}
+                WHERE {IDEMPOTENCY_KEYS_KEY} = ?
+                AND {IDEMPOTENCY_KEYS_CREATED_AT} >= datetime('now', ?)
+            )",
+            all_fields(&GROCERY_LIST_ENTRIES_FIELDS)
         ))
+        .bind(key)
+        .bind(format!("-{} hours", IDEMPOTENCY_KEY_TTL_HOURS))
         .fetch_optional(&self.pool)
-        .await?
-        .map(|r| r.get(next_position))
-        .unwrap_or(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION))
+        .await?;
+
+        Ok(entry)
     }
 
-    pub async fn create_category(&self, category: CreateCategory) -> Result<Category> {
-        let category = sqlx::query_as(&format!(
-            "INSERT INTO {TABLE_NAME_CATEGORIES} 
-            ({CATEGORIES_NAME}, {CATEGORIES_POSITION}) 
-            VALUES (?, ?) 
-            RETURNING {}",
-            all_fields(&CATEGORIES_FIELDS)
+    /// release_expired_idempotency_key forgets the Idempotency-Key if it was
+    /// used longer than IDEMPOTENCY_KEY_TTL_HOURS ago, so that it can be
+    /// claimed again. Being the first write of the transaction creating the
+    /// entry, it also waits out any other request creating one, so that a
+    /// request racing it with the same key sees the key it claimed.
+    async fn release_expired_idempotency_key(
+        &self,
+        key: &str,
+        conn: &mut SqliteConnection,
+    ) -> Result<()> {
+        sqlx::query(&format!(
+            "DELETE FROM {TABLE_NAME_IDEMPOTENCY_KEYS}
+            WHERE {IDEMPOTENCY_KEYS_KEY} = ?
+            AND {IDEMPOTENCY_KEYS_CREATED_AT} < datetime('now', ?)"
         ))
-        .bind(&category.name)
-        .bind(self.get_next_position_for_category().await?)
-        .fetch_one(&self.pool)
+        .bind(key)
+        .bind(format!("-{} hours", IDEMPOTENCY_KEY_TTL_HOURS))
+        .execute(conn)
         .await?;
 
-        Ok(category)
+        Ok(())
     }
 
-    pub async fn update_category(
+    /// claim_idempotency_key records that the Idempotency-Key resulted in the
+    /// entry, failing with IdempotencyKeyUsed if another request already
+    /// claimed it
+    async fn claim_idempotency_key(
         &self,
-        id: i64,
-        category: UpdateCategory,
-    ) -> Result<Option<Category>> {
-        let mut query_builder =
-            sqlx::QueryBuilder::new(&format!("UPDATE {TABLE_NAME_CATEGORIES} SET "));
-        let mut separated = query_builder.separated(", ");
-
-        if let Some(name) = &category.name {
-            separated
-                .push(&format!("{CATEGORIES_NAME} = "))
-                .push_bind_unseparated(name);
-        }
-
-        separated
-            .push(CATEGORIES_UPDATED_AT)
-            .push_unseparated(" = CURRENT_TIMESTAMP");
-
-        query_builder
-            .push(&format!(" WHERE {CATEGORIES_ID} = "))
-            .push_bind(id);
-        query_builder.push(" RETURNING ");
-        query_builder.push(all_fields(&CATEGORIES_FIELDS));
-
-        let row = query_builder.build().fetch_optional(&self.pool).await?;
+        key: &str,
+        entry_id: i64,
+        conn: &mut SqliteConnection,
+    ) -> Result<()> {
+        let claimed = sqlx::query(&format!(
+            "INSERT INTO {TABLE_NAME_IDEMPOTENCY_KEYS}
+            ({IDEMPOTENCY_KEYS_KEY}, {IDEMPOTENCY_KEYS_ENTRY_ID}, {IDEMPOTENCY_KEYS_CREATED_AT})
+            VALUES (?, ?, CURRENT_TIMESTAMP)"
+        ))
+        .bind(key)
+        .bind(entry_id)
+        .execute(conn)
+        .await;
 
-        if let Some(row) = row {
-            Ok(Some(Category::from_row(&row)?))
-        } else {
-            Ok(None)
+        match claimed {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                Err(IdempotencyKeyUsed.into())
+            }
+            Err(e) => Err(e.into()),
         }
     }
 
-    pub async fn delete_category(&self, id: i64) -> Result<bool> {
+    /// purge_expired_idempotency_keys forgets the Idempotency-Keys that are
+    /// older than IDEMPOTENCY_KEY_TTL_HOURS
+    pub async fn purge_expired_idempotency_keys(&self) -> Result<u64> {
         let result = sqlx::query(&format!(
-            "DELETE FROM {TABLE_NAME_CATEGORIES} WHERE {CATEGORIES_ID} = ?"
+            "DELETE FROM {TABLE_NAME_IDEMPOTENCY_KEYS}
+            WHERE {IDEMPOTENCY_KEYS_CREATED_AT} < datetime('now', ?)"
         ))
-        .bind(id)
+        .bind(format!("-{} hours", IDEMPOTENCY_KEY_TTL_HOURS))
         .execute(&self.pool)
         .await?;
 
-        Ok(result.rows_affected() > 0)
+        tracing::debug!("purged {} expired idempotency keys", result.rows_affected());
+        Ok(result.rows_affected())
     }
 
-    pub async fn get_suggestions(&self, query: &str) -> Result<Vec<String>> {
+    /// get_suggestions completes the description being typed from previously
+    /// added entries, most frequently added first
+    pub async fn get_suggestions(&self, query: &str, limit: i64) -> Result<Vec<String>> {
         let has_quantity = if let Some(first_word) = query.split_whitespace().next() {
             first_word.chars().next().unwrap_or('a').is_numeric()
         } else {
@@ -667,20 +3616,86 @@ impl Database {
             false => ("".to_string(), query.to_string()),
         };
 
-        let suggestions = sqlx::query_scalar::<_, String>(&format!(
-            "SELECT DISTINCT {GROCERY_LIST_ENTRIES_DESCRIPTION} 
-            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} 
-            WHERE {GROCERY_LIST_ENTRIES_DESCRIPTION} LIKE ? AND {GROCERY_LIST_ENTRIES_DESCRIPTION} != '' 
-            ORDER BY {GROCERY_LIST_ENTRIES_DESCRIPTION} 
-            LIMIT 10"
+        // alongside each description, look up the unit it is most commonly
+        // bought in so a bare quantity like `2` can be completed to `2lb`
+        let mut suggestions = sqlx::query_as::<_, (String, Option<String>)>(&format!(
+            "SELECT e.{GROCERY_LIST_ENTRIES_DESCRIPTION}, {}
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} e
+            WHERE e.{GROCERY_LIST_ENTRIES_DESCRIPTION} LIKE ? ESCAPE '\\' AND e.{GROCERY_LIST_ENTRIES_DESCRIPTION} != '' 
+            GROUP BY e.{GROCERY_LIST_ENTRIES_DESCRIPTION}
+            ORDER BY COUNT(*) DESC, e.{GROCERY_LIST_ENTRIES_DESCRIPTION} 
+            LIMIT ?",
+            most_common_unit_column()
         ))
-        .bind(format!("{}%", match_query))
+        .bind(format!("{}%", escape_like(&match_query)))
+        .bind(limit)
         .fetch_all(&self.pool)
         .await?;
 
+        // a typo stops the prefix from matching anything, so when there are
+        // few matches, fill up with descriptions that are spelled nearly the
+        // same. Those are ranked after every prefix match.
+        let remaining = limit - suggestions.len() as i64;
+        if remaining > 0 && match_query.chars().count() >= MIN_FUZZY_SUGGESTIONS_QUERY_LEN {
+            let fuzzy = self
+                .get_fuzzy_suggestions(&match_query, &suggestions, remaining)
+                .await?;
+            suggestions.extend(fuzzy);
+        }
+
+        let quantity_has_unit = quantity.chars().any(|c| c.is_alphabetic());
+
         Ok(suggestions
             .into_iter()
-            .map(|s| format!("{} {}", quantity, s))
+            .map(|(s, unit)| match unit {
+                Some(unit) if !quantity.is_empty() && !quantity_has_unit => {
+                    format!("{}{} {}", quantity, unit, s)
+                }
+                _ => format!("{} {}", quantity, s),
+            })
+            .collect())
+    }
+
+    /// get_fuzzy_suggestions finds up to limit previously added descriptions
+    /// within a typo or two of query, closest first and then most frequently
+    /// added, leaving out the ones already suggested. Only the distinct
+    /// descriptions are compared, not every entry.
+    async fn get_fuzzy_suggestions(
+        &self,
+        query: &str,
+        suggested: &[(String, Option<String>)],
+        limit: i64,
+    ) -> Result<Vec<(String, Option<String>)>> {
+        let descriptions = sqlx::query_as::<_, (String, Option<String>)>(&format!(
+            "SELECT e.{GROCERY_LIST_ENTRIES_DESCRIPTION}, {}
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} e
+            WHERE e.{GROCERY_LIST_ENTRIES_DESCRIPTION} != ''
+            GROUP BY e.{GROCERY_LIST_ENTRIES_DESCRIPTION}
+            ORDER BY COUNT(*) DESC, e.{GROCERY_LIST_ENTRIES_DESCRIPTION}",
+            most_common_unit_column()
+        ))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let query = query.to_lowercase();
+        let max_typos = if query.chars().count() > 5 { 2 } else { 1 };
+
+        let mut matches: Vec<_> = descriptions
+            .into_iter()
+            .filter(|(description, _)| !suggested.iter().any(|(s, _)| s == description))
+            .filter_map(|suggestion| {
+                let typos = typos_from(&query, &suggestion.0.to_lowercase());
+                (typos <= max_typos).then_some((typos, suggestion))
+            })
+            .collect();
+        // the sort is stable, so equally close descriptions stay in order of
+        // how often they were added
+        matches.sort_by_key(|(typos, _)| *typos);
+
+        Ok(matches
+            .into_iter()
+            .take(limit as usize)
+            .map(|(_, suggestion)| suggestion)
             .collect())
     }
 
@@ -689,8 +3704,10 @@ impl Database {
             "UPDATE {TABLE_NAME_GROCERY_LIST_ENTRIES} 
             SET 
                 {GROCERY_LIST_ENTRIES_ARCHIVED_AT} = CURRENT_TIMESTAMP,
-                {GROCERY_LIST_ENTRIES_POSITION} = NULL
-            WHERE {GROCERY_LIST_ENTRIES_COMPLETED_AT} < datetime('now','-1 day')"
+                {GROCERY_LIST_ENTRIES_POSITION} = NULL,
+                {GROCERY_LIST_ENTRIES_UPDATED_AT} = CURRENT_TIMESTAMP
+            WHERE {GROCERY_LIST_ENTRIES_COMPLETED_AT} < datetime('now','-1 day')
+            AND {GROCERY_LIST_ENTRIES_ARCHIVED_AT} IS NULL"
         ))
         .execute(&self.pool)
         .await?;
@@ -700,6 +3717,219 @@ impl Database {
     }
 }
 
+/// archived_trip_from_row reads an archived trip, whose entries are stored as
+/// JSON
+fn archived_trip_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ArchivedTrip> {
+    Ok(ArchivedTrip {
+        id: row.try_get(ARCHIVED_TRIPS_ID)?,
+        list_id: row.try_get(ARCHIVED_TRIPS_LIST_ID)?,
+        archived_at: row.try_get(ARCHIVED_TRIPS_ARCHIVED_AT)?,
+        entries: serde_json::from_str(row.try_get(ARCHIVED_TRIPS_ENTRIES)?)?,
+    })
+}
+
+/// template_from_row reads a template, whose items are stored as JSON
+fn template_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Template> {
+    Ok(Template {
+        id: row.try_get(TEMPLATES_ID)?,
+        name: row.try_get(TEMPLATES_NAME)?,
+        items: serde_json::from_str(row.try_get(TEMPLATES_ITEMS)?)?,
+        created_at: row.try_get(TEMPLATES_CREATED_AT)?,
+        updated_at: row.try_get(TEMPLATES_UPDATED_AT)?,
+    })
+}
+
 fn all_fields(field_list: &[&str]) -> String {
     field_list.join(", ")
 }
+
+/// entry_completed_filter is a WHERE condition on entries that takes whether
+/// they are completed to bind twice, keeping only the entries that are, or
+/// aren't, or every entry when it is null
+fn entry_completed_filter() -> String {
+    format!("(? IS NULL OR ({GROCERY_LIST_ENTRIES_COMPLETED_AT} IS NOT NULL) = ?)")
+}
+
+/// entry_visible_filter is a WHERE condition on entries that takes whether to
+/// hide scheduled entries to bind once, leaving out the entries whose
+/// visible_from is still to come when it is true
+fn entry_visible_filter() -> String {
+    format!(
+        "(NOT ? OR {GROCERY_LIST_ENTRIES_VISIBLE_FROM} IS NULL
+            OR datetime({GROCERY_LIST_ENTRIES_VISIBLE_FROM}) <= datetime('now'))"
+    )
+}
+
+/// entry_tag_filter is a WHERE condition on entries that takes a tag to bind
+/// twice, keeping only the entries with that tag, or every entry when it is
+/// null
+fn entry_tag_filter() -> String {
+    format!(
+        "(? IS NULL OR {GROCERY_LIST_ENTRIES_ID} IN (
+            SELECT et.{ENTRY_TAGS_ENTRY_ID} FROM {TABLE_NAME_ENTRY_TAGS} et
+            JOIN {TABLE_NAME_TAGS} t ON t.{TAGS_ID} = et.{ENTRY_TAGS_TAG_ID}
+            WHERE t.{TAGS_NAME} = ?
+        ))"
+    )
+}
+
+/// export_entries_query is the query export_entries streams. It is built once
+/// and kept, since the stream borrows it for as long as it runs.
+fn export_entries_query() -> &'static str {
+    static QUERY: OnceLock<String> = OnceLock::new();
+    // archived entries have no position, so they go after the active entries
+    // in the order they were archived
+    QUERY.get_or_init(|| {
+        format!(
+            "SELECT {} FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} e
+            JOIN {TABLE_NAME_CATEGORIES} c ON c.{CATEGORIES_ID} = e.{GROCERY_LIST_ENTRIES_CATEGORY_ID}
+            WHERE e.{GROCERY_LIST_ENTRIES_DELETED_AT} IS NULL
+            ORDER BY
+                c.{CATEGORIES_LIST_ID},
+                c.{CATEGORIES_POSITION},
+                c.{CATEGORIES_ID},
+                e.{GROCERY_LIST_ENTRIES_POSITION} IS NULL,
+                e.{GROCERY_LIST_ENTRIES_POSITION},
+                e.{GROCERY_LIST_ENTRIES_ARCHIVED_AT},
+                e.{GROCERY_LIST_ENTRIES_ID}",
+            GROCERY_LIST_ENTRIES_FIELDS
+                .iter()
+                .map(|field| format!("e.{}", field))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })
+}
+
+/// entry_order_by is the ORDER BY clause for listing entries by sort. Entries in
+/// different categories can share a position, so ties are broken by id to keep
+/// the order stable between requests.
+fn entry_order_by(sort: EntrySort) -> String {
+    // pinned entries come first either way, in the same order among
+    // themselves as the rest
+    match sort {
+        EntrySort::Position => format!(
+            "{GROCERY_LIST_ENTRIES_PINNED} DESC, {GROCERY_LIST_ENTRIES_POSITION}, {GROCERY_LIST_ENTRIES_ID}"
+        ),
+        EntrySort::Priority => format!(
+            "{GROCERY_LIST_ENTRIES_PINNED} DESC, {GROCERY_LIST_ENTRIES_PRIORITY} DESC, {GROCERY_LIST_ENTRIES_POSITION}, {GROCERY_LIST_ENTRIES_ID}"
+        ),
+    }
+}
+
+/// placed_at puts id among the other ids at position, counting from the
+/// minimum position. A position that is out of range puts it at the nearest
+/// end, so whatever position a client sends there is a sensible order.
+fn placed_at(mut ids: Vec<i64>, id: i64, position: i64) -> Vec<i64> {
+    ids.retain(|other| *other != id);
+    let index = position
+        .saturating_sub(ORDERABLE_LIST_MINIMUM_PERMANENT_POSITION)
+        .clamp(0, ids.len() as i64) as usize;
+    ids.insert(index, id);
+    ids
+}
+
+/// check_reorder_ids checks that the submitted ordering names every current id
+/// exactly once, describing the offending ids if it doesn't
+fn check_reorder_ids(submitted: &[i64], current: &[i64]) -> Option<String> {
+    let mut seen = std::collections::HashSet::new();
+    let mut duplicate: Vec<i64> = submitted
+        .iter()
+        .filter(|id| !seen.insert(**id))
+        .copied()
+        .collect();
+    duplicate.dedup();
+
+    let unknown: Vec<i64> = submitted
+        .iter()
+        .filter(|id| !current.contains(id))
+        .copied()
+        .collect();
+    let missing: Vec<i64> = current
+        .iter()
+        .filter(|id| !submitted.contains(id))
+        .copied()
+        .collect();
+
+    let problems: Vec<String> = [
+        ("duplicate", duplicate),
+        ("unknown", unknown),
+        ("missing", missing),
+    ]
+    .into_iter()
+    .filter(|(_, ids)| !ids.is_empty())
+    .map(|(problem, ids)| format!("{} ids {:?}", problem, ids))
+    .collect();
+
+    (!problems.is_empty()).then(|| problems.join(", "))
+}
+
+/// add_quantities adds two quantity amounts, treating a missing amount as 1,
+/// or is None when either of them isn't a number
+fn add_quantities(a: &str, b: &str) -> Option<String> {
+    let parse = |quantity: &str| -> Option<f64> {
+        if quantity.is_empty() {
+            Some(1.0)
+        } else {
+            quantity
+                .parse()
+                .ok()
+                .filter(|amount: &f64| amount.is_finite())
+        }
+    };
+
+    // round away float noise like 0.1 + 0.2 = 0.30000000000000004
+    let sum = ((parse(a)? + parse(b)?) * 1000.0).round() / 1000.0;
+    Some(sum.to_string())
+}
+
+/// most_common_unit_column is a column for selecting from entries `e` the
+/// unit that e's description is most commonly bought in
+fn most_common_unit_column() -> String {
+    format!(
+        "(
+            SELECT u.{GROCERY_LIST_ENTRIES_UNIT}
+            FROM {TABLE_NAME_GROCERY_LIST_ENTRIES} u
+            WHERE u.{GROCERY_LIST_ENTRIES_DESCRIPTION} = e.{GROCERY_LIST_ENTRIES_DESCRIPTION}
+                AND u.{GROCERY_LIST_ENTRIES_UNIT} != ''
+            GROUP BY u.{GROCERY_LIST_ENTRIES_UNIT}
+            ORDER BY COUNT(*) DESC
+            LIMIT 1
+        )"
+    )
+}
+
+/// typos_from is how many typos query is from description, as the edit
+/// distance to either the whole description or, for a description that is
+/// still being typed, the start of it that is as long as query
+fn typos_from(query: &str, description: &str) -> usize {
+    let start: String = description.chars().take(query.chars().count()).collect();
+    edit_distance(query, description).min(edit_distance(query, &start))
+}
+
+/// edit_distance is the fewest single character insertions, deletions and
+/// substitutions that turn a into b
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// escape_like escapes the LIKE wildcards in user input so they are matched
+/// literally, for use with `ESCAPE '\'`
+fn escape_like(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
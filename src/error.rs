@@ -0,0 +1,347 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::rejection::JsonRejection,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::database::CategoryFull;
+
+/// BUSY_RETRY_AFTER_SECS is how long clients are asked to wait before retrying
+/// a request that failed because the database was locked
+const BUSY_RETRY_AFTER_SECS: u64 = 1;
+
+/// SQLITE_BUSY and SQLITE_LOCKED are the primary result codes sqlite uses
+/// when another connection holds the lock it needs. Extended result codes
+/// keep the primary code in their low byte.
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+
+/// ApiError is a failed request. It is sent to the client as
+/// `{"error": {"code": "...", "message": "..."}}` so that every failure can
+/// be handled the same way.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// code is a stable, machine readable name for the kind of failure
+    pub code: &'static str,
+    pub message: String,
+    /// current is the row as it is now on the server, sent along with a
+    /// conflict so the client can replace its stale copy
+    pub current: Option<serde_json::Value>,
+    /// retry_after is how many seconds the client should wait before trying
+    /// again, sent as the Retry-After header
+    pub retry_after: Option<u64>,
+    /// request_id is the id of the failed request, to quote when reporting it
+    pub request_id: Option<String>,
+    /// fields is what is wrong with each invalid field of the request body,
+    /// keyed by the field's path like `items[0].description`
+    pub fields: Option<BTreeMap<String, String>>,
+}
+
+/// ErrorBody is how an ApiError is sent
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody<'a> {
+    error: ErrorDetail<'a>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ErrorDetail<'a> {
+    code: &'a str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<&'a serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fields: Option<&'a BTreeMap<String, String>>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            current: None,
+            retry_after: None,
+            request_id: crate::telemetry::current_request_id(),
+            fields: None,
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    /// unauthorized is a request that needs the api token but didn't carry it
+    pub fn unauthorized() -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "a valid api token is required",
+        )
+    }
+
+    pub fn method_not_allowed(message: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "method_not_allowed",
+            message,
+        )
+    }
+
+    pub fn unprocessable(message: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "unprocessable_entity",
+            message,
+        )
+    }
+
+    /// invalid is a request body with fields that failed validation, which
+    /// are each reported with what is wrong with them
+    pub fn invalid(fields: BTreeMap<String, String>) -> Self {
+        let message = match fields.len() {
+            1 => "1 field is invalid".to_string(),
+            n => format!("{} fields are invalid", n),
+        };
+        Self {
+            fields: Some(fields),
+            ..Self::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid", message)
+        }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal", message)
+    }
+
+    /// busy is a request that failed because the database was locked by
+    /// another writer, which is worth retrying shortly
+    pub fn busy() -> Self {
+        Self {
+            retry_after: Some(BUSY_RETRY_AFTER_SECS),
+            ..Self::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "database_busy",
+                "the database is busy, try again shortly",
+            )
+        }
+    }
+
+    /// too_many_requests is a request from a client that is over its rate
+    /// limit, which can try again after retry_after seconds
+    pub fn too_many_requests(retry_after: u64) -> Self {
+        Self {
+            retry_after: Some(retry_after),
+            ..Self::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limited",
+                "too many requests, slow down",
+            )
+        }
+    }
+
+    /// timeout is a request that took too long to handle
+    pub fn timeout() -> Self {
+        Self::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "timeout",
+            "the request took too long, try again later",
+        )
+    }
+
+    /// database is a failed database call. A category that was full is
+    /// reported as category_full and a locked database as busy, anything else
+    /// as internal with the given message.
+    pub fn database(e: &anyhow::Error, message: impl Into<String>) -> Self {
+        if let Some(full) = e.downcast_ref::<CategoryFull>() {
+            return Self::category_full(full.category_id, full.max_entries, full.count);
+        }
+
+        let busy = e
+            .chain()
+            .filter_map(|cause| cause.downcast_ref::<sqlx::Error>())
+            .any(is_busy);
+
+        if busy {
+            Self::busy()
+        } else {
+            Self::internal(message)
+        }
+    }
+
+    /// not_empty is a refusal to delete something that other rows still
+    /// depend on
+    pub fn not_empty(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "not_empty", message)
+    }
+
+    /// category_full is a refusal to add entries to a category that would
+    /// take it past its max_entries, sending along how many it has now
+    pub fn category_full(category_id: i64, max_entries: i64, count: i64) -> Self {
+        Self {
+            current: Some(serde_json::json!({
+                "category_id": category_id,
+                "max_entries": max_entries,
+                "count": count,
+            })),
+            ..Self::new(
+                StatusCode::CONFLICT,
+                "category_full",
+                format!(
+                    "category {} already has {} of its at most {} entries",
+                    category_id, count, max_entries
+                ),
+            )
+        }
+    }
+
+    /// already_exists is a refusal to create something that would duplicate
+    /// existing, which is sent along so the client can use it instead
+    pub fn already_exists<T: Serialize>(message: impl Into<String>, existing: &T) -> Self {
+        Self {
+            current: serde_json::to_value(existing).ok(),
+            ..Self::new(StatusCode::CONFLICT, "already_exists", message)
+        }
+    }
+
+    /// conflict is a rejected update to a row that changed since the client
+    /// last saw it
+    pub fn conflict<T: Serialize>(message: impl Into<String>, current: &T) -> Self {
+        Self {
+            current: serde_json::to_value(current).ok(),
+            ..Self::new(StatusCode::CONFLICT, "conflict", message)
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code: self.code,
+                message: &self.message,
+                current: self.current.as_ref(),
+                request_id: self.request_id.as_deref(),
+                fields: self.fields.as_ref(),
+            },
+        };
+
+        let mut response = (self.status, Json(body)).into_response();
+        if let Some(retry_after) = self.retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, retry_after.into());
+        }
+        response
+    }
+}
+
+/// is_busy is whether e means the database was locked by another connection
+fn is_busy(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::Database(db_error) => db_error
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            .is_some_and(|code| matches!(code & 0xff, SQLITE_BUSY | SQLITE_LOCKED)),
+        // every connection was tied up waiting on the lock
+        sqlx::Error::PoolTimedOut => true,
+        _ => false,
+    }
+}
+
+/// database errors are logged here rather than sent to the client, which only
+/// learns that something went wrong
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        tracing::error!("internal error: {:#}", e);
+        Self::database(&e, "internal server error")
+    }
+}
+
+/// a body that isn't JSON at all is rejected before any of its fields can be
+/// looked at
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        let code = match rejection.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+            _ => "bad_request",
+        };
+        Self::new(rejection.status(), code, rejection.body_text())
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        tracing::error!("database error: {}", e);
+        if is_busy(&e) {
+            Self::busy()
+        } else {
+            Self::internal("internal server error")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempPath;
+
+    #[tokio::test]
+    async fn test_error_body() {
+        let response = ApiError::not_found("entry 3 not found").into_response();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            serde_json::json!({"error": {"code": "not_found", "message": "entry 3 not found"}}),
+            body
+        );
+    }
+
+    #[tokio::test]
+    async fn test_locked_database_is_busy() {
+        use sqlx::sqlite::{SqliteConnectOptions, SqlitePool};
+        use std::time::Duration;
+
+        let path = TempPath::new("busy");
+        let options = SqliteConnectOptions::new()
+            .filename(&*path)
+            .create_if_missing(true)
+            .busy_timeout(Duration::ZERO);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        sqlx::query("CREATE TABLE t (x INTEGER)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let mut holder = pool.begin().await.unwrap();
+        sqlx::query("INSERT INTO t VALUES (1)")
+            .execute(&mut *holder)
+            .await
+            .unwrap();
+        let e = sqlx::query("INSERT INTO t VALUES (2)")
+            .execute(&pool)
+            .await
+            .unwrap_err();
+
+        holder.rollback().await.unwrap();
+        pool.close().await;
+
+        let response = ApiError::database(&e.into(), "failed").into_response();
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, response.status());
+        assert_eq!("1", response.headers()[header::RETRY_AFTER]);
+    }
+}
@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::database;
+use crate::error::{ApiError, ErrorBody};
+use crate::models::audit::AuditEntry;
+use crate::state::AppState;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    /// limit is how many changes to return, at most 500
+    limit: Option<i64>,
+}
+
+/// get_audit_log lists the most recent changes to entries, categories and
+/// lists, newest first
+#[utoipa::path(
+    get,
+    path = "/api/audit",
+    tag = "audit",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "the most recent changes", body = [AuditEntry]),
+        (status = 400, description = "the limit is less than 1", body = ErrorBody),
+    ),
+)]
+pub async fn get_audit_log(
+    State(state): State<AppState>,
+    Query(params): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, ApiError> {
+    let limit = match params.limit {
+        None => database::DEFAULT_AUDIT_LIMIT,
+        Some(limit) if limit < 1 => {
+            return Err(ApiError::bad_request(format!(
+                "limit must be at least 1, got {}",
+                limit
+            )))
+        }
+        Some(limit) => limit.min(database::MAX_AUDIT_LIMIT),
+    };
+
+    match state.get_audit_log(limit).await {
+        Ok(entries) => Ok(Json(entries)),
+        Err(e) => {
+            tracing::error!("Failed to get audit log: {}", e);
+            Err(ApiError::database(&e, "failed to get audit log"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, memory_state};
+    use crate::{
+        handlers::{create_entries, delete_entry, patch_entry},
+        validation::ValidJson,
+    };
+    use axum::extract::Path;
+
+    #[tokio::test]
+    async fn test_get_audit_log() {
+        let state = memory_state().await;
+
+        let Json(created) = create_entries(State(state.clone()), ValidJson(vec![entry("milk")]))
+            .await
+            .unwrap();
+        let id = created[0].id;
+
+        let Json(patched) = patch_entry(
+            State(state.clone()),
+            Path(id),
+            ValidJson(
+                serde_json::from_value(serde_json::json!({"description": "oat milk"})).unwrap(),
+            ),
+        )
+        .await
+        .unwrap();
+        delete_entry(State(state.clone()), Path(id)).await.unwrap();
+
+        let Json(log) = get_audit_log(State(state.clone()), Query(AuditQuery { limit: None }))
+            .await
+            .unwrap();
+        let Json(latest) =
+            get_audit_log(State(state.clone()), Query(AuditQuery { limit: Some(1) }))
+                .await
+                .unwrap();
+        let invalid =
+            get_audit_log(State(state.clone()), Query(AuditQuery { limit: Some(0) })).await;
+
+        state.pool.close().await;
+
+        let actions: Vec<&str> = log.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(vec!["delete", "update", "create"], actions);
+        assert!(log
+            .iter()
+            .all(|entry| entry.table_name == "grocery_list_entries" && entry.row_id == id));
+
+        assert_eq!("oat milk", patched.description);
+        assert_eq!("milk", log[2].diff["description"]["after"]);
+        assert!(log[2].diff["description"].get("before").is_none());
+        assert_eq!("milk", log[1].diff["description"]["before"]);
+        assert_eq!("oat milk", log[1].diff["description"]["after"]);
+        assert!(log[1].diff.get("unit").is_none());
+        assert!(log[0].diff["description"].get("after").is_none());
+        assert_eq!("oat milk", log[0].diff["description"]["before"]);
+
+        assert_eq!(1, latest.len());
+        assert_eq!("delete", latest[0].action);
+        assert!(invalid.is_err());
+    }
+}
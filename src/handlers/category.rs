@@ -4,101 +4,1071 @@ use axum::{
     response::Json,
 };
 use serde::Deserialize;
-use std::sync::Arc;
+use utoipa::IntoParams;
 
-use crate::database::Database;
-use crate::models::category::{CreateCategory, Category, ReorderCategory, UpdateCategory};
+use super::grocery::{suggestions_limit, with_tags};
+use crate::database::{self, CategoryDeletion, ReorderOutcome, VersionedUpdate};
+use crate::error::{ApiError, ErrorBody};
+use crate::models::audit::AuditTable;
+use crate::models::category::{
+    Category, CategoryWithCounts, CategoryWithEntries, CreateCategory, DuplicatedCategory,
+    ReorderCategories, UpdateCategory,
+};
+use crate::models::event::ChangeEvent;
+use crate::models::grocery_entry::ApiGroceryListEntry;
+use crate::state::AppState;
+use crate::validation::{FieldErrors, ValidJson};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SuggestionsQuery {
     query: String,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategoriesQuery {
+    list_id: Option<i64>,
+    /// include is what to return along with each category, which can only be
+    /// counts
+    include: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CategoryQuery {
+    /// include is what to return along with the category, which can only be
+    /// entries
+    include: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SortCategoriesQuery {
+    list_id: Option<i64>,
+    #[serde(default)]
+    reverse: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DeleteCategoryQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+/// MAX_ICON_LEN is the longest icon identifier a category can have
+const MAX_ICON_LEN: usize = 32;
+
+/// validate_color checks that a color is a hex color like `#aabbcc`. An empty
+/// color is allowed and means no color.
+fn validate_color(color: &str) -> Result<(), String> {
+    let is_hex_color = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+    if color.is_empty() || is_hex_color {
+        Ok(())
+    } else {
+        Err(format!(
+            "color must be a hex color like #aabbcc, got '{}'",
+            color
+        ))
+    }
+}
+
+/// validate_icon checks that an icon is a short identifier like `shopping-cart`.
+/// An empty icon is allowed and means no icon.
+fn validate_icon(icon: &str) -> Result<(), String> {
+    let is_identifier = icon
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if icon.len() <= MAX_ICON_LEN && is_identifier {
+        Ok(())
+    } else {
+        Err(format!(
+            "icon must be at most {} letters, digits, '-' or '_', got '{}'",
+            MAX_ICON_LEN, icon
+        ))
+    }
 }
 
+/// validate_max_entries checks that a category's limit leaves room for at
+/// least one entry
+fn validate_max_entries(max_entries: i64) -> Result<(), String> {
+    if max_entries >= 1 {
+        Ok(())
+    } else {
+        Err(format!(
+            "max_entries must be at least 1, got {}",
+            max_entries
+        ))
+    }
+}
 
+/// appearance_errors validates the color and icon, if present
+fn appearance_errors(color: Option<&str>, icon: Option<&str>) -> FieldErrors {
+    let mut errors = FieldErrors::new();
+    if let Some(color) = color {
+        errors.check("color", validate_color(color));
+    }
+    if let Some(icon) = icon {
+        errors.check("icon", validate_icon(icon));
+    }
+    errors
+}
+
+/// normalize_category_name is what a category name is compared by to tell
+/// whether two categories are the same, so that `Dairy` and `dairy ` are
+fn normalize_category_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// get_categories returns the list's categories in order, and with
+/// ?include=counts how many active entries each has and how many of those
+/// are done
+#[utoipa::path(
+    get,
+    path = "/api/categories",
+    tag = "categories",
+    params(CategoriesQuery),
+    responses(
+        (status = 200, description = "the list's categories in order", body = [CategoryWithCounts]),
+        (status = 400, description = "include names something other than counts", body = ErrorBody),
+    ),
+)]
 pub async fn get_categories(
-    State(db): State<Arc<Database>>,
-) -> Result<Json<Vec<Category>>, StatusCode> {
+    State(state): State<AppState>,
+    Query(params): Query<CategoriesQuery>,
+) -> Result<Json<Vec<CategoryWithCounts>>, ApiError> {
     tracing::info!("GET /api/categories called");
-    match db.get_all_categories().await {
+    let include_counts = match params.include.as_deref().map(str::trim) {
+        None | Some("") => false,
+        Some("counts") => true,
+        Some(other) => {
+            return Err(ApiError::bad_request(format!(
+                "can't include {}, only counts",
+                other
+            )))
+        }
+    };
+
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+    let categories = match state.get_all_categories(list_id).await {
         Ok(categories) => {
             tracing::info!("Successfully retrieved {} categories", categories.len());
-            Ok(Json(categories))
+            categories
         },
         Err(e) => {
             tracing::error!("Failed to get categories: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            return Err(ApiError::database(&e, "failed to get categories"));
         },
+    };
+
+    let counts = if include_counts {
+        match state.get_category_entry_counts(list_id).await {
+            Ok(counts) => Some(counts),
+            Err(e) => {
+                tracing::error!("Failed to count entries of categories: {}", e);
+                return Err(ApiError::database(&e, "failed to get categories"));
+            }
+        }
+    } else {
+        None
+    };
+
+    Ok(Json(
+        categories
+            .into_iter()
+            .map(|category| {
+                let count = counts
+                    .as_ref()
+                    .map(|counts| counts.get(&category.id).copied().unwrap_or((0, 0)));
+                CategoryWithCounts {
+                    total_entries: count.map(|(total, _)| total),
+                    completed_entries: count.map(|(_, completed)| completed),
+                    category,
+                }
+            })
+            .collect(),
+    ))
+}
+
+/// get_category returns a category, and with ?include=entries its active
+/// entries in order along with their tags
+#[utoipa::path(
+    get,
+    path = "/api/categories/{id}",
+    tag = "categories",
+    params(("id" = i64, Path, description = "the category to get"), CategoryQuery),
+    responses(
+        (status = 200, description = "the category", body = CategoryWithEntries),
+        (status = 400, description = "include names something other than entries", body = ErrorBody),
+        (status = 404, description = "the category doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn get_category(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<CategoryQuery>,
+) -> Result<Json<CategoryWithEntries>, ApiError> {
+    let include_entries = match params.include.as_deref().map(str::trim) {
+        None | Some("") => false,
+        Some("entries") => true,
+        Some(other) => {
+            return Err(ApiError::bad_request(format!(
+                "can't include {}, only entries",
+                other
+            )))
+        }
+    };
+
+    let category = match state.get_category(id).await {
+        Ok(Some(category)) => category,
+        Ok(None) => return Err(ApiError::not_found(format!("category {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to get category {}: {}", id, e);
+            return Err(ApiError::database(&e, "failed to get category"));
+        }
+    };
+    if !include_entries {
+        return Ok(Json(CategoryWithEntries {
+            category,
+            entries: None,
+        }));
+    }
+
+    let entries = state.get_active_category_entries(id).await;
+    let tags = state.get_list_entry_tags(category.list_id).await;
+    match entries.and_then(|entries| Ok((entries, tags?))) {
+        Ok((entries, tags)) => Ok(Json(CategoryWithEntries {
+            entries: Some(with_tags(&entries, &tags)),
+            category,
+        })),
+        Err(e) => {
+            tracing::error!("Failed to get entries of category {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to get category"))
+        }
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/categories",
+    tag = "categories",
+    request_body = CreateCategory,
+    responses(
+        (status = 200, description = "the new category", body = Category),
+        (status = 409, description = "a category with the same name already exists", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
 pub async fn create_category(
-    State(db): State<Arc<Database>>,
-    Json(payload): Json<CreateCategory>,
-) -> Result<Json<Category>, StatusCode> {
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreateCategory>,
+) -> Result<Json<Category>, ApiError> {
     tracing::info!("POST /api/categories called with name: '{}'", payload.name);
-    match db.create_category(payload).await {
+    let mut errors = appearance_errors(payload.color.as_deref(), payload.icon.as_deref());
+    if let Some(max_entries) = payload.max_entries {
+        errors.check("max_entries", validate_max_entries(max_entries));
+    }
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        errors.add("name", "category name must not be empty");
+    }
+    errors.into_result()?;
+
+    // a category that only differs by case or surrounding whitespace is the
+    // same one, which the client is pointed to instead
+    let list_id = payload.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+    let categories = state.get_all_categories(list_id).await.map_err(|e| {
+        tracing::error!("Failed to get categories: {}", e);
+        ApiError::database(&e, "failed to get categories")
+    })?;
+    let normalized = normalize_category_name(&name);
+    if let Some(existing) = categories
+        .iter()
+        .find(|category| normalize_category_name(&category.name) == normalized)
+    {
+        return Err(ApiError::already_exists(
+            format!("category '{}' already exists", existing.name),
+            existing,
+        ));
+    }
+
+    // an empty color or icon is the same as not having one
+    let payload = CreateCategory {
+        name,
+        color: payload.color.filter(|color| !color.is_empty()),
+        icon: payload.icon.filter(|icon| !icon.is_empty()),
+        ..payload
+    };
+
+    match state.create_category(payload).await {
         Ok(category) => {
             tracing::info!("Successfully created category with id: {}", category.id);
+            state
+                .audit(AuditTable::Categories, category.id, None, Some(&category))
+                .await;
+            state.publish(ChangeEvent::CategoryCreated { id: category.id });
             Ok(Json(category))
         },
         Err(e) => {
             tracing::error!("Failed to create category: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(ApiError::database(&e, "failed to create category"))
         },
     }
 }
 
+/// update_category applies the update only if the category is still at the
+/// version the client sent. Otherwise it responds with 409 and the current
+/// category so the client can merge.
+#[utoipa::path(
+    put,
+    path = "/api/categories/{id}",
+    tag = "categories",
+    params(("id" = i64, Path, description = "the category to update")),
+    request_body = UpdateCategory,
+    responses(
+        (status = 200, description = "the updated category", body = Category),
+        (status = 404, description = "the category doesn't exist", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
 pub async fn update_category(
-    State(db): State<Arc<Database>>,
+    State(state): State<AppState>,
     Path(id): Path<i64>,
-    Json(payload): Json<UpdateCategory>,
-) -> Result<Json<Category>, StatusCode> {
-    // don't allow rename default category
-    if id == 1 {
-        return Err(StatusCode::METHOD_NOT_ALLOWED)
+    ValidJson(payload): ValidJson<UpdateCategory>,
+) -> Result<Json<Category>, ApiError> {
+    let before = state.get_category(id).await.ok().flatten();
+    // don't allow rename default category, of any list
+    if before.as_ref().is_some_and(|c| c.is_default_category) {
+        return Err(ApiError::method_not_allowed(
+            "the default category can't be changed",
+        ));
+    }
+
+    let mut errors = appearance_errors(payload.color.as_deref(), payload.icon.as_deref());
+    if let Some(Some(max_entries)) = payload.max_entries {
+        errors.check("max_entries", validate_max_entries(max_entries));
     }
-    
-    match db.update_category(id, payload).await {
-        Ok(Some(category)) => Ok(Json(category)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    errors.into_result()?;
+
+    match state.update_category(id, payload).await {
+        Ok(VersionedUpdate::Updated(category)) => {
+            state
+                .audit(AuditTable::Categories, id, before.as_ref(), Some(&category))
+                .await;
+            state.publish(ChangeEvent::CategoryUpdated { id });
+            Ok(Json(category))
+        }
+        Ok(VersionedUpdate::Conflict(current)) => Err(ApiError::conflict(
+            format!(
+                "category {} was changed, it is now at version {}",
+                id, current.version
+            ),
+            &current,
+        )),
+        Ok(VersionedUpdate::NotFound) => {
+            Err(ApiError::not_found(format!("category {} not found", id)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to update category {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to update category"))
+        }
     }
 }
 
+/// delete_category deletes a category. One that still has entries is only
+/// deleted, along with its entries, when force is set, and a list's default
+/// category can't be deleted at all.
+#[utoipa::path(
+    delete,
+    path = "/api/categories/{id}",
+    tag = "categories",
+    params(("id" = i64, Path, description = "the category to delete"), DeleteCategoryQuery),
+    responses(
+        (status = 204, description = "the category was deleted"),
+        (status = 404, description = "the category doesn't exist", body = ErrorBody),
+        (status = 409, description = "the category still has entries", body = ErrorBody),
+    ),
+)]
 pub async fn delete_category(
-    State(db): State<Arc<Database>>,
+    State(state): State<AppState>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    match db.delete_category(id).await {
-        Ok(true) => Ok(StatusCode::NO_CONTENT),
-        Ok(false) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    Query(params): Query<DeleteCategoryQuery>,
+) -> Result<StatusCode, ApiError> {
+    let before = state.get_category(id).await.ok().flatten();
+    match state.delete_category(id, params.force).await {
+        Ok(CategoryDeletion::Deleted) => {
+            state
+                .audit(AuditTable::Categories, id, before.as_ref(), None)
+                .await;
+            state.publish(ChangeEvent::CategoryDeleted { id });
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(CategoryDeletion::NotFound) => {
+            Err(ApiError::not_found(format!("category {} not found", id)))
+        }
+        Ok(CategoryDeletion::IsDefault) => Err(ApiError::method_not_allowed(
+            "the default category can't be deleted",
+        )),
+        Ok(CategoryDeletion::NotEmpty(entries)) => Err(ApiError::not_empty(format!(
+            "category {} still has {} {}, delete with ?force=true to delete them too",
+            id,
+            entries,
+            if entries == 1 { "entry" } else { "entries" }
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to delete category {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to delete category"))
+        }
     }
 }
 
+/// reorder_categories either moves a single category or, given the full list
+/// of ids in a list, puts all of its categories in that order. Either way only
+/// the categories of the list_id in the payload are renumbered, and ids from
+/// any other list are rejected.
+#[utoipa::path(
+    put,
+    path = "/api/categories/reorder",
+    tag = "categories",
+    request_body = ReorderCategories,
+    responses(
+        (status = 204, description = "the categories were reordered"),
+        (status = 400, description = "the ids are wrong or aren't all in the list", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
 pub async fn reorder_categories(
-    State(db): State<Arc<Database>>,
-    Json(payload): Json<ReorderCategory>,
-) -> Result<StatusCode, StatusCode> {
-    match db.reorder_categories(payload).await {
-        Ok(()) => Ok(StatusCode::NO_CONTENT),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<ReorderCategories>,
+) -> Result<StatusCode, ApiError> {
+    let list_id = match &payload {
+        ReorderCategories::Move(reorder) => reorder.list_id,
+        ReorderCategories::Order(order) => order.list_id,
+    };
+
+    let result = match payload {
+        ReorderCategories::Move(reorder) => state.reorder_categories(reorder).await,
+        ReorderCategories::Order(order) => state.order_categories(order).await,
+    };
+
+    match result {
+        Ok(ReorderOutcome::Reordered) => {
+            state.publish(ChangeEvent::CategoriesReordered { list_id });
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(ReorderOutcome::InvalidIds(invalid)) => Err(ApiError::bad_request(invalid)),
+        Err(e) => {
+            tracing::error!("Failed to reorder categories: {}", e);
+            Err(ApiError::database(&e, "failed to reorder categories"))
+        }
+    }
+}
+
+/// sort_categories puts a list's categories in alphabetical order, or from Z
+/// to A with ?reverse=true, and returns them in their new order. The entries
+/// in each category keep their order.
+#[utoipa::path(
+    put,
+    path = "/api/categories/sort-alpha",
+    tag = "categories",
+    params(SortCategoriesQuery),
+    responses(
+        (status = 200, description = "the categories in their new order", body = [Category]),
+    ),
+)]
+pub async fn sort_categories(
+    State(state): State<AppState>,
+    Query(params): Query<SortCategoriesQuery>,
+) -> Result<Json<Vec<Category>>, ApiError> {
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+    tracing::info!(
+        "PUT /api/categories/sort-alpha called for list {}, reverse: {}",
+        list_id,
+        params.reverse
+    );
+
+    match state
+        .sort_categories_alphabetically(list_id, params.reverse)
+        .await
+    {
+        Ok(categories) => {
+            state.publish(ChangeEvent::CategoriesReordered { list_id });
+            Ok(Json(categories))
+        }
+        Err(e) => {
+            tracing::error!("Failed to sort categories: {}", e);
+            Err(ApiError::database(&e, "failed to sort categories"))
+        }
+    }
+}
+
+/// reset_entry_order puts the category's entries back in the order they were
+/// added, undoing manual ordering in just that category, and returns them in
+/// their new order
+#[utoipa::path(
+    put,
+    path = "/api/categories/{id}/reset-order",
+    tag = "categories",
+    params(("id" = i64, Path, description = "the category whose entries to reorder")),
+    responses(
+        (status = 200, description = "the category's entries in their new order", body = [ApiGroceryListEntry]),
+        (status = 404, description = "the category doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn reset_entry_order(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    tracing::info!("PUT /api/categories/{}/reset-order called", id);
+
+    match state.reset_entry_order(id).await {
+        Ok(Some(entries)) => {
+            state.publish(ChangeEvent::EntriesReordered { category_id: id });
+            Ok(Json(entries.iter().map(Into::into).collect()))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("category {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to reset order of category {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to reset entry order"))
+        }
+    }
+}
+
+/// copy_name is the first name for a copy of the category called name that
+/// none of the taken names already use, ignoring case
+fn copy_name(name: &str, taken: &[String]) -> String {
+    let taken: Vec<String> = taken
+        .iter()
+        .map(|name| normalize_category_name(name))
+        .collect();
+    let mut copy = format!("{} (copy)", name);
+    let mut n = 2;
+    while taken.contains(&normalize_category_name(&copy)) {
+        copy = format!("{} (copy {})", name, n);
+        n += 1;
+    }
+    copy
+}
+
+/// duplicate_category copies the category into a new one at the end of its
+/// list, named with a " (copy)" suffix, along with the entries in it that
+/// aren't completed
+#[utoipa::path(
+    post,
+    path = "/api/categories/{id}/duplicate",
+    tag = "categories",
+    params(("id" = i64, Path, description = "the category to copy")),
+    responses(
+        (status = 200, description = "the new category and its entries", body = DuplicatedCategory),
+        (status = 404, description = "the category doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn duplicate_category(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<DuplicatedCategory>, ApiError> {
+    tracing::info!("POST /api/categories/{}/duplicate called", id);
+
+    let original = match state.get_category(id).await {
+        Ok(Some(category)) => category,
+        Ok(None) => return Err(ApiError::not_found(format!("category {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to get category {}: {}", id, e);
+            return Err(ApiError::database(&e, "failed to get category"));
+        }
+    };
+    let taken: Vec<String> = state
+        .get_all_categories(original.list_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get categories: {}", e);
+            ApiError::database(&e, "failed to get categories")
+        })?
+        .into_iter()
+        .map(|category| category.name)
+        .collect();
+    let name = copy_name(&original.name, &taken);
+
+    match state.duplicate_category(id, &name).await {
+        Ok(Some((category, entries))) => {
+            tracing::info!("Duplicated category {} as {}", id, category.id);
+            state
+                .audit(AuditTable::Categories, category.id, None, Some(&category))
+                .await;
+            for entry in &entries {
+                state
+                    .audit(AuditTable::Entries, entry.id, None, Some(entry))
+                    .await;
+            }
+            state.publish(ChangeEvent::CategoryCreated { id: category.id });
+            Ok(Json(DuplicatedCategory {
+                category,
+                entries: entries.iter().map(Into::into).collect(),
+            }))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("category {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to duplicate category {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to duplicate category"))
+        }
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/categories/suggestions",
+    tag = "categories",
+    params(SuggestionsQuery),
+    responses(
+        (status = 200, description = "descriptions of past entries that match the query", body = [String]),
+    ),
+)]
 pub async fn get_suggestions(
-    State(db): State<Arc<Database>>,
+    State(state): State<AppState>,
     Query(params): Query<SuggestionsQuery>,
-) -> Result<Json<Vec<String>>, StatusCode> {
+) -> Result<Json<Vec<String>>, ApiError> {
     tracing::info!("GET /api/categories/suggestions called with query: '{}'", params.query);
-    match db.get_suggestions(&params.query).await {
+    let limit = suggestions_limit(params.limit).map_err(ApiError::bad_request)?;
+    match state.get_suggestions(&params.query, limit).await {
         Ok(suggestions) => {
             tracing::info!("Successfully retrieved {} suggestions", suggestions.len());
             Ok(Json(suggestions))
         },
         Err(e) => {
             tracing::error!("Failed to get suggestions: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(ApiError::database(&e, "failed to get suggestions"))
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{category, entry, memory_state};
+
+    #[test]
+    fn test_validate_color() {
+        assert!(validate_color("#aabbcc").is_ok());
+        assert!(validate_color("#A0B1C2").is_ok());
+        assert!(validate_color("").is_ok());
+        assert!(validate_color("red123").is_err());
+        assert!(validate_color("#abc").is_err());
+        assert!(validate_color("#aabbcg").is_err());
+    }
+
+    #[test]
+    fn test_validate_icon() {
+        assert!(validate_icon("shopping-cart").is_ok());
+        assert!(validate_icon("ice_cream2").is_ok());
+        assert!(validate_icon("").is_ok());
+        assert!(validate_icon("two words").is_err());
+        assert!(validate_icon(&"a".repeat(MAX_ICON_LEN + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_duplicate_category() {
+        let state = memory_state().await;
+
+        let create = |name: &str| create_category(State(state.clone()), ValidJson(category(name)));
+        let Json(created) = create(" Dairy").await.unwrap();
+        let duplicate = create("dairy ").await.unwrap_err();
+        let empty = create("  ").await.unwrap_err();
+
+        state.pool.close().await;
+
+        assert_eq!("Dairy", created.name);
+        assert_eq!(StatusCode::CONFLICT, duplicate.status);
+        assert_eq!("already_exists", duplicate.code);
+        assert_eq!(Some(created.id), duplicate.current.unwrap()["id"].as_i64());
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, empty.status);
+        assert!(empty.fields.unwrap().contains_key("name"));
+    }
+
+    #[tokio::test]
+    async fn test_sort_categories() {
+        let state = memory_state().await;
+
+        for name in ["produce", "Bakery", "dairy"] {
+            state.create_category(category(name)).await.unwrap();
+        }
+
+        let sort = |reverse| {
+            sort_categories(
+                State(state.clone()),
+                Query(SortCategoriesQuery {
+                    list_id: None,
+                    reverse,
+                }),
+            )
+        };
+        let Json(sorted) = sort(false).await.unwrap();
+        let Json(reversed) = sort(true).await.unwrap();
+
+        state.pool.close().await;
+
+        let names = |categories: &[Category]| {
+            categories
+                .iter()
+                .map(|category| category.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec!["Bakery", "dairy", "produce", "Uncategorized"],
+            names(&sorted)
+        );
+        assert_eq!(
+            vec!["Uncategorized", "produce", "dairy", "Bakery"],
+            names(&reversed)
+        );
+        let positions: Vec<_> = sorted.iter().map(|category| category.position).collect();
+        assert_eq!(vec![1, 2, 3, 4], positions);
+    }
+
+    #[tokio::test]
+    async fn test_reset_entry_order() {
+        use crate::database::EntryFilter;
+        use crate::models::grocery_entry::{CreateGroceryListEntry, EntrySort, OrderEntries};
+
+        let state = memory_state().await;
+
+        let produce = state.create_category(category("produce")).await.unwrap();
+        let mut ids = Vec::new();
+        for (position, description, category_id) in [
+            (1, "milk", database::DEFAULT_CATEGORY_ID),
+            (2, "eggs", database::DEFAULT_CATEGORY_ID),
+            (3, "bread", database::DEFAULT_CATEGORY_ID),
+            (1, "apples", produce.id),
+            (2, "pears", produce.id),
+        ] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(category_id),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        for (category_id, order) in [
+            (database::DEFAULT_CATEGORY_ID, vec![ids[2], ids[0], ids[1]]),
+            (produce.id, vec![ids[4], ids[3]]),
+        ] {
+            state
+                .order_entries(OrderEntries {
+                    category_id,
+                    ids: order,
+                })
+                .await
+                .unwrap();
+        }
+
+        let Json(entries) =
+            reset_entry_order(State(state.clone()), Path(database::DEFAULT_CATEGORY_ID))
+                .await
+                .unwrap();
+        let missing = reset_entry_order(State(state.clone()), Path(9999))
+            .await
+            .unwrap_err();
+        let untouched = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(
+            vec![(Some(1), ids[0]), (Some(2), ids[1]), (Some(3), ids[2])],
+            entries
+                .iter()
+                .map(|entry| (entry.position, entry.id))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+        let pears = untouched.iter().find(|entry| entry.id == ids[4]).unwrap();
+        assert_eq!(Some(1), pears.position);
+    }
+
+    #[tokio::test]
+    async fn test_reorder_category_out_of_range() {
+        use crate::models::category::ReorderCategory;
+
+        let state = memory_state().await;
+
+        let mut ids = vec![database::DEFAULT_CATEGORY_ID];
+        for name in ["produce", "bakery"] {
+            let category = state.create_category(category(name)).await.unwrap();
+            ids.push(category.id);
+        }
+
+        reorder_categories(
+            State(state.clone()),
+            ValidJson(ReorderCategories::Move(ReorderCategory {
+                list_id: database::DEFAULT_LIST_ID,
+                id: ids[0],
+                new_position: 500,
+            })),
+        )
+        .await
+        .unwrap();
+        let categories = state
+            .get_all_categories(database::DEFAULT_LIST_ID)
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        let order: Vec<_> = categories
+            .iter()
+            .map(|category| (category.position, category.id))
+            .collect();
+        assert_eq!(vec![(1, ids[1]), (2, ids[2]), (3, ids[0])], order);
+    }
+
+    #[tokio::test]
+    async fn test_delete_category_with_entries() {
+        use crate::{
+            database::EntryFilter,
+            models::grocery_entry::{CreateGroceryListEntry, EntrySort},
+        };
+
+        let state = memory_state().await;
+
+        let category = state.create_category(category("dairy")).await.unwrap();
+        state
+            .create_entry(CreateGroceryListEntry {
+                position: Some(1),
+                category_id: Some(category.id),
+                ..entry("milk")
+            })
+            .await
+            .unwrap();
+
+        let delete = |id, force| {
+            delete_category(
+                State(state.clone()),
+                Path(id),
+                Query(DeleteCategoryQuery { force }),
+            )
+        };
+        let refused = delete(category.id, false).await.unwrap_err();
+        let forced = delete(category.id, true).await.unwrap();
+        let missing = delete(category.id, true).await.unwrap_err();
+        let default = delete(database::DEFAULT_CATEGORY_ID, true)
+            .await
+            .unwrap_err();
+        let remaining = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(StatusCode::CONFLICT, refused.status);
+        assert_eq!("not_empty", refused.code);
+        assert!(refused.message.contains("still has 1 entry"));
+        assert_eq!(StatusCode::NO_CONTENT, forced);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, default.status);
+        assert!(remaining.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_default_category() {
+        use crate::models::list::CreateList;
+
+        let state = memory_state().await;
+
+        let hardware = state
+            .create_list(CreateList {
+                name: "hardware".to_string(),
+            })
+            .await
+            .unwrap();
+        let tools = state
+            .create_category(CreateCategory {
+                list_id: Some(hardware.id),
+                ..category("tools")
+            })
+            .await
+            .unwrap();
+        let default = state
+            .get_all_categories(hardware.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|c| c.is_default_category)
+            .unwrap();
+
+        let rename = |id, version| {
+            update_category(
+                State(state.clone()),
+                Path(id),
+                ValidJson(UpdateCategory {
+                    version,
+                    name: Some("renamed".to_string()),
+                    color: None,
+                    icon: None,
+                    max_entries: None,
+                }),
+            )
+        };
+        let groceries = rename(database::DEFAULT_CATEGORY_ID, 1).await.unwrap_err();
+        let refused = rename(default.id, default.version).await.unwrap_err();
+        let Json(renamed) = rename(tools.id, tools.version).await.unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, groceries.status);
+        assert_eq!(StatusCode::METHOD_NOT_ALLOWED, refused.status);
+        assert_eq!("renamed", renamed.name);
+    }
+
+    #[test]
+    fn test_copy_name() {
+        assert_eq!("dairy (copy)", copy_name("dairy", &["dairy".to_string()]));
+        assert_eq!(
+            "dairy (copy 3)",
+            copy_name(
+                "dairy",
+                &["Dairy (Copy)".to_string(), "dairy (copy 2)".to_string()]
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_category() {
+        use crate::models::grocery_entry::CreateGroceryListEntry;
+
+        let state = memory_state().await;
+
+        let produce = state
+            .create_category(CreateCategory {
+                color: Some("#00aa00".to_string()),
+                ..category("produce")
+            })
+            .await
+            .unwrap();
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "apples"), (2, "pears"), (3, "kale")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    quantity: Some("2".to_string()),
+                    category_id: Some(produce.id),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        state.toggle_entry_completed(ids[1]).await.unwrap();
+        state.add_entry_tag(ids[2], "organic").await.unwrap();
+
+        let Json(copy) = duplicate_category(State(state.clone()), Path(produce.id))
+            .await
+            .unwrap();
+        let Json(second) = duplicate_category(State(state.clone()), Path(produce.id))
+            .await
+            .unwrap();
+        let missing = duplicate_category(State(state.clone()), Path(9999))
+            .await
+            .unwrap_err();
+        let tags = state
+            .get_list_entry_tags(database::DEFAULT_LIST_ID)
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!("produce (copy)", copy.category.name);
+        assert_eq!(produce.color, copy.category.color);
+        assert!(copy.category.position > produce.position);
+        assert_eq!(
+            vec![("apples", Some(1)), ("kale", Some(2))],
+            copy.entries
+                .iter()
+                .map(|e| (e.description.as_str(), e.position))
+                .collect::<Vec<_>>()
+        );
+        assert!(copy
+            .entries
+            .iter()
+            .all(|e| !ids.contains(&e.id) && e.quantity == "2"));
+        assert_eq!(
+            Some(&vec!["organic".to_string()]),
+            tags.get(&copy.entries[1].id)
+        );
+
+        assert_eq!("produce (copy 2)", second.category.name);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+    }
+
+    #[tokio::test]
+    async fn test_get_category() {
+        use crate::models::grocery_entry::CreateGroceryListEntry;
+
+        let state = memory_state().await;
+
+        let produce = state.create_category(category("produce")).await.unwrap();
+        let mut ids = Vec::new();
+        for (position, description) in [(2, "pears"), (1, "apples"), (3, "kale")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(produce.id),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        state.delete_entry(ids[2]).await.unwrap();
+        state.add_entry_tag(ids[0], "organic").await.unwrap();
+
+        let get = |id: i64, include: Option<&str>| {
+            get_category(
+                State(state.clone()),
+                Path(id),
+                Query(CategoryQuery {
+                    include: include.map(str::to_string),
+                }),
+            )
+        };
+        let Json(bare) = get(produce.id, None).await.unwrap();
+        let Json(full) = get(produce.id, Some("entries")).await.unwrap();
+        let unknown = get(produce.id, Some("lists")).await.unwrap_err();
+        let missing = get(produce.id + 1, None).await.unwrap_err();
+
+        state.pool.close().await;
+
+        assert_eq!("produce", bare.category.name);
+        assert!(bare.entries.is_none());
+        assert!(serde_json::to_value(&bare)
+            .unwrap()
+            .get("entries")
+            .is_none());
+
+        let entries = full.entries.unwrap();
+        let descriptions: Vec<&str> = entries.iter().map(|e| e.description.as_str()).collect();
+        assert_eq!(vec!["apples", "pears"], descriptions);
+        assert_eq!(Some(vec!["organic".to_string()]), entries[1].tags);
+
+        assert_eq!(StatusCode::BAD_REQUEST, unknown.status);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+    }
+}
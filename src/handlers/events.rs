@@ -0,0 +1,116 @@
+use std::{convert::Infallible, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Response,
+    },
+};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
+
+use crate::models::event::ChangeEvent;
+use crate::state::AppState;
+
+/// KEEP_ALIVE_INTERVAL_SECS is how often an idle event stream is sent a
+/// comment, so that proxies don't close it for being quiet
+const KEEP_ALIVE_INTERVAL_SECS: u64 = 15;
+
+/// subscribe_events upgrades to a WebSocket that is sent every change event
+/// as JSON, e.g. `{"type": "entry_updated", "id": 3}`
+#[utoipa::path(
+    get,
+    path = "/api/ws",
+    tag = "events",
+    responses((status = 101, description = "a websocket that is sent every ChangeEvent as JSON")),
+)]
+pub async fn subscribe_events(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    tracing::info!("GET /api/ws called");
+    let events = state.subscribe();
+    ws.on_upgrade(move |socket| push_events(socket, events))
+}
+
+/// push_events forwards events to the client until it disconnects. Each
+/// client has its own task, so a slow one only ever delays itself.
+async fn push_events(mut socket: WebSocket, mut events: Receiver<ChangeEvent>) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let text = match serde_json::to_string(&event) {
+                        Ok(text) => text,
+                        Err(e) => {
+                            tracing::error!("Failed to serialize {:?}: {}", event, e);
+                            continue;
+                        }
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client fell behind and skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                // clients have nothing to say, so anything but a close is ignored
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    tracing::debug!("WebSocket client disconnected");
+}
+
+/// stream_events is the same feed as subscribe_events as server-sent events,
+/// for clients that can't use WebSockets. Each event is named by its type and
+/// carries the change as JSON.
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    tag = "events",
+    responses(
+        (status = 200, description = "a stream of server-sent events, one ChangeEvent each", body = ChangeEvent, content_type = "text/event-stream"),
+    ),
+)]
+pub async fn stream_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    tracing::info!("GET /api/events called");
+    let events = BroadcastStream::new(state.subscribe()).filter_map(|event| match event {
+        Ok(event) => sse_event(&event).map(Ok),
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+            tracing::warn!(
+                "Event stream client fell behind and skipped {} events",
+                skipped
+            );
+            None
+        }
+    });
+
+    Sse::new(events)
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(KEEP_ALIVE_INTERVAL_SECS)))
+}
+
+/// sse_event is a change event as a server-sent event
+fn sse_event(event: &ChangeEvent) -> Option<Event> {
+    let data = match serde_json::to_value(event) {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Failed to serialize {:?}: {}", event, e);
+            return None;
+        }
+    };
+    let kind = data["type"].as_str().unwrap_or_default().to_string();
+
+    Event::default().event(kind).json_data(data).ok()
+}
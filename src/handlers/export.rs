@@ -0,0 +1,586 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use utoipa::IntoParams;
+
+use super::grocery::validate_description;
+use crate::database::{self, EntryFilter, ImportChanges};
+use crate::error::{ApiError, ErrorBody};
+use crate::models::export::{ExportDocument, ImportMode, ImportSummary, EXPORT_SCHEMA_VERSION};
+use crate::models::audit::AuditTable;
+use crate::models::category::Category;
+use crate::models::event::ChangeEvent;
+use crate::models::grocery_entry::{EntrySort, GroceryListEntry};
+use crate::models::list::List;
+use crate::state::AppState;
+use crate::validation::FieldErrors;
+
+/// EXPORT_CHUNK_BYTES is about how much of an export is written before it is
+/// sent on to the client
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+
+/// EXPORT_CHANNEL_CAPACITY is how many chunks of an export can be waiting on a
+/// slow client before reading from the database pauses
+const EXPORT_CHANNEL_CAPACITY: usize = 4;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    #[serde(default)]
+    mode: ImportMode,
+    /// dry_run reports what the import would do without writing anything
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CsvExportQuery {
+    list_id: Option<i64>,
+}
+
+/// export returns every list, category and entry as a single JSON document
+/// that browsers download as a file. The entries are streamed out as they are
+/// read rather than collected first, so a large export doesn't have to fit in
+/// memory, and the document around them is written by hand to match
+/// ExportDocument.
+#[utoipa::path(
+    get,
+    path = "/api/export",
+    tag = "export",
+    responses(
+        (status = 200, description = "every list with its categories and entries", body = ExportDocument),
+    ),
+)]
+pub async fn export(State(state): State<AppState>) -> Result<Response, ApiError> {
+    tracing::info!("GET /api/export called");
+    let (tx, lists, categories) = state.begin_export().await.map_err(|e| {
+        tracing::error!("Failed to export: {}", e);
+        ApiError::database(&e, "failed to export")
+    })?;
+
+    let exported_at = Utc::now();
+    let (chunks, body) = mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+    tokio::spawn(async move {
+        let export = ExportWriter {
+            chunks: chunks.clone(),
+            buffer: Vec::new(),
+        };
+        if let Err(e) = export
+            .write(&state, tx, exported_at, lists, categories)
+            .await
+        {
+            // the response has already started, so all that can be done is
+            // to cut it short rather than let it look complete
+            tracing::error!("Failed to export: {}", e);
+            let _ = chunks.send(Err(e)).await;
+        }
+    });
+
+    let filename = format!("grocery-list-{}.json", exported_at.format("%Y-%m-%d"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(body)),
+    )
+        .into_response())
+}
+
+/// ExportWriter writes an export as JSON, sending it on in chunks of about
+/// EXPORT_CHUNK_BYTES
+struct ExportWriter {
+    chunks: mpsc::Sender<anyhow::Result<Vec<u8>>>,
+    buffer: Vec<u8>,
+}
+
+impl ExportWriter {
+    /// write writes the whole document, with each category's entries taken
+    /// from the stream as it comes to them. The lists, categories and entries
+    /// all come in display order, so the entries can be nested as they arrive.
+    async fn write(
+        mut self,
+        state: &AppState,
+        mut tx: sqlx::Transaction<'static, sqlx::Sqlite>,
+        exported_at: DateTime<Utc>,
+        lists: Vec<List>,
+        categories: Vec<Category>,
+    ) -> anyhow::Result<()> {
+        let mut entries = state.export_entries(&mut tx);
+        let mut next_entry = entries.next().await.transpose()?;
+
+        self.push(b"{\"version\":");
+        self.push_json(&EXPORT_SCHEMA_VERSION)?;
+        self.push(b",\"exported_at\":");
+        self.push_json(&exported_at)?;
+        self.push(b",\"lists\":[");
+        for (i, list) in lists.iter().enumerate() {
+            if i > 0 {
+                self.push(b",");
+            }
+            self.open_object(list, "categories")?;
+
+            let list_categories = categories.iter().filter(|c| c.list_id == list.id);
+            for (j, category) in list_categories.enumerate() {
+                if j > 0 {
+                    self.push(b",");
+                }
+                self.open_object(category, "entries")?;
+
+                let mut first = true;
+                while let Some(entry) = next_entry.take_if(|e| e.category_id == category.id) {
+                    if !first {
+                        self.push(b",");
+                    }
+                    first = false;
+                    self.push_json(&entry)?;
+                    self.flush_if_full().await?;
+                    next_entry = entries.next().await.transpose()?;
+                }
+
+                self.push(b"]}");
+            }
+
+            self.push(b"]}");
+        }
+        self.push(b"]}");
+
+        drop(entries);
+        tx.commit().await?;
+        self.flush().await
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    fn push_json<T: Serialize>(&mut self, value: &T) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.buffer, value)?;
+        Ok(())
+    }
+
+    /// open_object writes value, a struct, but leaves it open with field
+    /// started as an array, as `#[serde(flatten)]` would lay it out
+    fn open_object<T: Serialize>(&mut self, value: &T, field: &str) -> anyhow::Result<()> {
+        let object = serde_json::to_vec(value)?;
+        let Some(fields) = object.strip_suffix(b"}") else {
+            anyhow::bail!(
+                "{} is not written as a JSON object",
+                std::any::type_name::<T>()
+            );
+        };
+        self.push(fields);
+        if fields.len() > 1 {
+            self.push(b",");
+        }
+        self.push_json(&field)?;
+        self.push(b":[");
+        Ok(())
+    }
+
+    async fn flush_if_full(&mut self) -> anyhow::Result<()> {
+        if self.buffer.len() >= EXPORT_CHUNK_BYTES {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::take(&mut self.buffer);
+        self.chunks
+            .send(Ok(chunk))
+            .await
+            .map_err(|_| anyhow::anyhow!("the client went away"))
+    }
+}
+
+/// import restores a document produced by export. The schema version is
+/// checked before anything else so that a document from a newer server is
+/// rejected rather than half understood. With ?dry_run=true the document is
+/// checked and the summary of what would be imported is returned, without
+/// anything being written.
+#[utoipa::path(
+    post,
+    path = "/api/import",
+    tag = "export",
+    params(ImportQuery),
+    request_body = ExportDocument,
+    responses(
+        (status = 200, description = "how much was, or with dry_run would be, imported", body = ImportSummary),
+        (status = 422, description = "the document is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn import(
+    State(state): State<AppState>,
+    Query(params): Query<ImportQuery>,
+    Json(document): Json<serde_json::Value>,
+) -> Result<Json<ImportSummary>, ApiError> {
+    tracing::info!(
+        "POST /api/import called with mode {:?}, dry run: {}",
+        params.mode,
+        params.dry_run
+    );
+
+    let version = document
+        .get("version")
+        .and_then(serde_json::Value::as_i64)
+        .ok_or_else(|| ApiError::unprocessable("export is missing its schema version"))?;
+
+    if version > EXPORT_SCHEMA_VERSION {
+        return Err(ApiError::unprocessable(format!(
+            "export has schema version {}, but this server only understands up to version {}",
+            version, EXPORT_SCHEMA_VERSION
+        )));
+    }
+
+    let document: ExportDocument = serde_json::from_value(document)
+        .map_err(|e| ApiError::unprocessable(format!("invalid export: {}", e)))?;
+    let document = validate_import(document)?;
+
+    match state.import(document, params.mode, params.dry_run).await {
+        Ok((summary, changes)) => {
+            tracing::info!("Imported {:?}", summary);
+            if !summary.dry_run {
+                audit_import(&state, &changes).await;
+                publish_import(&state, &changes);
+            }
+            Ok(Json(summary))
+        }
+        Err(e) => {
+            tracing::error!("Failed to import: {}", e);
+            Err(ApiError::database(&e, "failed to import"))
+        }
+    }
+}
+
+/// validate_import checks the description of every entry the document would
+/// add the way a new entry's is checked, trimming them
+fn validate_import(mut document: ExportDocument) -> Result<ExportDocument, FieldErrors> {
+    let mut errors = FieldErrors::new();
+    for (l, list) in document.lists.iter_mut().enumerate() {
+        for (c, category) in list.categories.iter_mut().enumerate() {
+            for (e, entry) in category.entries.iter_mut().enumerate() {
+                // entries in the trash are not imported
+                if entry.deleted_at.is_some() {
+                    continue;
+                }
+                let field = format!("lists[{}].categories[{}].entries[{}].description", l, c, e);
+                if let Some(description) =
+                    errors.check(&field, validate_description(&entry.description))
+                {
+                    entry.description = description;
+                }
+            }
+        }
+    }
+
+    errors.into_result()?;
+    Ok(document)
+}
+
+/// audit_import records the rows an import removed and added in the audit log
+async fn audit_import(state: &AppState, changes: &ImportChanges) {
+    for entry in &changes.removed_entries {
+        state
+            .audit(AuditTable::Entries, entry.id, Some(entry), None)
+            .await;
+    }
+    for category in &changes.removed_categories {
+        state
+            .audit(AuditTable::Categories, category.id, Some(category), None)
+            .await;
+    }
+    for list in &changes.removed_lists {
+        state
+            .audit(AuditTable::Lists, list.id, Some(list), None)
+            .await;
+    }
+    for list in &changes.lists {
+        state
+            .audit(AuditTable::Lists, list.id, None, Some(list))
+            .await;
+    }
+    for category in &changes.categories {
+        state
+            .audit(AuditTable::Categories, category.id, None, Some(category))
+            .await;
+    }
+    for entry in &changes.entries {
+        state
+            .audit(AuditTable::Entries, entry.id, None, Some(entry))
+            .await;
+    }
+}
+
+/// publish_import tells connected clients about the rows an import removed
+/// and added
+fn publish_import(state: &AppState, changes: &ImportChanges) {
+    for entry in &changes.removed_entries {
+        state.publish(ChangeEvent::EntryDeleted { id: entry.id });
+    }
+    for category in &changes.removed_categories {
+        state.publish(ChangeEvent::CategoryDeleted { id: category.id });
+    }
+    for list in &changes.removed_lists {
+        state.publish(ChangeEvent::ListDeleted { id: list.id });
+    }
+    for list in &changes.lists {
+        state.publish(ChangeEvent::ListCreated { id: list.id });
+    }
+    for category in &changes.categories {
+        state.publish(ChangeEvent::CategoryCreated { id: category.id });
+    }
+    for entry in &changes.entries {
+        state.publish(ChangeEvent::EntryCreated { id: entry.id });
+    }
+}
+
+/// write_csv writes one row per entry, grouped by category in the order the
+/// categories are given
+fn write_csv(categories: &[Category], entries: &[GroceryListEntry]) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["category", "item", "quantity", "unit", "completed"])?;
+
+    for category in categories {
+        for entry in entries.iter().filter(|e| e.category_id == category.id) {
+            let completed = entry.completed_at.is_some().to_string();
+            writer.write_record([
+                &category.name,
+                &entry.description,
+                &entry.quantity,
+                &entry.unit,
+                &completed,
+            ])?;
+        }
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+/// export_csv returns the active entries of a list as a CSV file, in the order
+/// the list is displayed
+#[utoipa::path(
+    get,
+    path = "/api/export.csv",
+    tag = "export",
+    params(CsvExportQuery),
+    responses(
+        (status = 200, description = "the entries as CSV", body = String, content_type = "text/csv"),
+    ),
+)]
+pub async fn export_csv(
+    State(state): State<AppState>,
+    Query(params): Query<CsvExportQuery>,
+) -> Result<Response, ApiError> {
+    tracing::info!("GET /api/export.csv called");
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+
+    let (categories, entries) = match tokio::try_join!(
+        state.get_all_categories(list_id),
+        state.get_active_entries(list_id, EntrySort::Position, EntryFilter::default())
+    ) {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::error!("Failed to get entries for csv export: {}", e);
+            return Err(ApiError::database(&e, "failed to export"));
+        }
+    };
+
+    let body = match write_csv(&categories, &entries) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("Failed to write csv export: {}", e);
+            return Err(ApiError::internal("failed to export"));
+        }
+    };
+
+    let filename = format!("grocery-list-{}.csv", chrono::Utc::now().format("%Y-%m-%d"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{grocery_entry::CreateGroceryListEntry, list::CreateList};
+    use crate::test_support::{category, entry, memory_state};
+
+    #[tokio::test]
+    async fn test_export_streams_a_valid_document() {
+        let state = memory_state().await;
+
+        let produce = state.create_category(category("produce")).await.unwrap();
+        let hardware = state
+            .create_list(CreateList {
+                name: "hardware".to_string(),
+            })
+            .await
+            .unwrap();
+        for (position, description) in [(1, "milk"), (2, "eggs")] {
+            state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+        }
+
+        let response = export(State(state.clone())).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        let document: ExportDocument = serde_json::from_slice(&body).unwrap();
+        assert_eq!(EXPORT_SCHEMA_VERSION, document.version);
+        assert_eq!(
+            vec![database::DEFAULT_LIST_ID, hardware.id],
+            document
+                .lists
+                .iter()
+                .map(|list| list.list.id)
+                .collect::<Vec<_>>()
+        );
+
+        let categories = &document.lists[0].categories;
+        let default_category = categories
+            .iter()
+            .find(|c| c.category.id == database::DEFAULT_CATEGORY_ID)
+            .unwrap();
+        assert_eq!(
+            vec!["milk", "eggs"],
+            default_category
+                .entries
+                .iter()
+                .map(|entry| entry.description.as_str())
+                .collect::<Vec<_>>()
+        );
+        let produce = categories
+            .iter()
+            .find(|c| c.category.id == produce.id)
+            .unwrap();
+        assert!(produce.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_import_dry_run() {
+        use crate::models::grocery_entry::EntrySort;
+
+        let state = memory_state().await;
+
+        for (position, description) in [(1, "milk"), (2, "eggs")] {
+            state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+        }
+        let response = export(State(state.clone())).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let document: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let import = |dry_run, document| {
+            import(
+                State(state.clone()),
+                Query(ImportQuery {
+                    mode: ImportMode::Merge,
+                    dry_run,
+                }),
+                Json(document),
+            )
+        };
+        let mut events = state.subscribe();
+        let Json(dry_run) = import(true, document.clone()).await.unwrap();
+        let unchanged = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap();
+        let mut newer = document.clone();
+        newer["version"] = serde_json::json!(EXPORT_SCHEMA_VERSION + 1);
+        let mismatch = import(true, newer).await.unwrap_err();
+        let mut blank = document.clone();
+        blank["lists"][0]["categories"][0]["entries"][1]["description"] = serde_json::json!(" ");
+        let invalid = import(false, blank).await.unwrap_err();
+        let Json(imported) = import(false, document).await.unwrap();
+        // only the import that went through is sent to clients
+        let published: Vec<_> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        let merged = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        assert!(dry_run.dry_run);
+        assert_eq!(2, dry_run.entries);
+        assert_eq!(0, dry_run.lists);
+        assert_eq!(1, dry_run.merged_lists);
+        assert_eq!(1, dry_run.merged_categories);
+        assert_eq!(
+            unchanged
+                .iter()
+                .map(|entry| format!("entry {} already exists", entry.id))
+                .collect::<Vec<_>>(),
+            dry_run.id_collisions
+        );
+        assert_eq!(2, unchanged.len());
+        assert_eq!(
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+            mismatch.status
+        );
+        assert!(invalid
+            .fields
+            .unwrap()
+            .contains_key("lists[0].categories[0].entries[1].description"));
+
+        assert!(!imported.dry_run);
+        assert_eq!(dry_run.entries, imported.entries);
+        assert_eq!(4, merged.len());
+        assert_eq!(
+            merged[2..]
+                .iter()
+                .map(|entry| ChangeEvent::EntryCreated { id: entry.id })
+                .collect::<Vec<_>>(),
+            published
+        );
+    }
+}
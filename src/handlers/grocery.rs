@@ -1,19 +1,96 @@
+use std::collections::HashMap;
+
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
-use std::sync::Arc;
+use utoipa::IntoParams;
 
-use crate::{database::{self, Database}, models::grocery_entry::ApiGroceryListEntry};
+use crate::database::{
+    self, BatchUpdate, EntryFilter, EntryMove, Nudge, ReorderOutcome, VersionedUpdate,
+};
+use crate::error::{ApiError, ErrorBody};
+use crate::models::audit::AuditTable;
+use crate::models::event::ChangeEvent;
 use crate::models::grocery_entry::{
-    CreateGroceryListEntry, GroceryListEntry, ReorderEntry, UpdateGroceryListEntry,
+    ApiGroceryListEntry, BatchUpdateEntry, ClearedEntries, CreateGroceryListEntry, EntriesDelta,
+    EntriesPage, EntryPlacement, EntrySort, GroceryListEntry, MoveEntry, PatchGroceryListEntry,
+    ReorderEntries, UpdateGroceryListEntry,
 };
+use crate::models::purchase::PurchaseEvent;
+use crate::models::tag::{AddTag, EntryTags};
+use crate::state::AppState;
+use crate::timestamp;
+use crate::validation::{FieldErrors, ValidJson};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SuggestionsQuery {
+    #[serde(alias = "q")]
     query: String,
+    limit: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EntriesQuery {
+    list_id: Option<i64>,
+    changed_since: Option<String>,
+    #[serde(default)]
+    sort: EntrySort,
+    /// tag keeps only the entries with this tag
+    tag: Option<String>,
+    /// completed keeps only the entries that are done when true, or only the
+    /// ones still to get when false
+    completed: Option<String>,
+    /// include_scheduled keeps the entries that aren't visible_from yet, which
+    /// are otherwise left out, when true
+    include_scheduled: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClearCompletedQuery {
+    list_id: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HistoryQuery {
+    list_id: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ScheduledQuery {
+    list_id: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TrashQuery {
+    list_id: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CreateEntryQuery {
+    #[serde(default)]
+    merge: bool,
+    #[serde(default)]
+    position: EntryPlacement,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    q: String,
+    list_id: Option<i64>,
 }
 
 fn parse_entry_input(input: &str) -> (String, String, String) {
@@ -59,39 +136,143 @@ fn parse_entry_input(input: &str) -> (String, String, String) {
     }
 }
 
-pub async fn get_entries(
-    State(db): State<Arc<Database>>,
-) -> Result<Json<Vec<ApiGroceryListEntry>>, StatusCode> {
-    tracing::info!("GET /api/entries called");
+/// split_quantity separates a quantity such as `2lb` into its amount (`2`) and
+/// its unit (`lb`)
+fn split_quantity(quantity: &str) -> (String, String) {
+    let amount_len = quantity
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .unwrap_or(quantity.len());
+    let (amount, unit) = quantity.split_at(amount_len);
 
-    tracing::info!("archiving entries");
-    if let Err(e) = db.archive_entries().await {
-        tracing::error!("Failed to archive entries: {}", e);
+    (amount.to_string(), unit.to_string())
+}
+
+/// validate_quantity checks that a quantity amount, if present, is a
+/// non-negative decimal number
+fn validate_quantity(quantity: &str) -> Result<(), String> {
+    if quantity.is_empty() {
+        return Ok(());
     }
 
-    match db.get_active_entries().await {
-        Ok(entries) => {
-            tracing::info!("Successfully retrieved {} entries", entries.len());
-            Ok(Json(entries.iter().map(Into::into).collect()))
-        }
-        Err(e) => {
-            tracing::error!("Failed to get entries: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    match quantity.parse::<f64>() {
+        Ok(amount) if amount < 0.0 => Err(format!(
+            "quantity must not be negative, got '{}'",
+            quantity
+        )),
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!(
+            "quantity must be a decimal number, got '{}'",
+            quantity
+        )),
     }
 }
 
-pub async fn create_entry(
-    State(db): State<Arc<Database>>,
-    Json(payload): Json<CreateGroceryListEntry>,
-) -> Result<Json<GroceryListEntry>, StatusCode> {
-    tracing::info!(
-        "POST /api/entries '{:?}', '{:?}', '{:?}'",
-        payload.quantity,
-        payload.description,
-        payload.notes,
-    );
+/// validate_description trims a description, checking that something is left
+/// and that it isn't too long. Templates and imports check the descriptions of
+/// the entries they add with it too.
+pub(crate) fn validate_description(description: &str) -> Result<String, String> {
+    let description = description.trim();
+    if description.is_empty() {
+        return Err("description must not be empty".to_string());
+    }
+
+    let len = description.chars().count();
+    if len > database::MAX_ENTRY_DESCRIPTION_LEN {
+        return Err(format!(
+            "description must be at most {} characters, got {}",
+            database::MAX_ENTRY_DESCRIPTION_LEN,
+            len
+        ));
+    }
+
+    Ok(description.to_string())
+}
+
+/// validate_notes trims notes and checks that they aren't too long. Notes
+/// that are left empty mean the entry has no note.
+fn validate_notes(notes: &str) -> Result<String, String> {
+    let notes = notes.trim();
+    let len = notes.chars().count();
+    if len > database::MAX_ENTRY_NOTES_LEN {
+        return Err(format!(
+            "notes must be at most {} characters, got {}",
+            database::MAX_ENTRY_NOTES_LEN,
+            len
+        ));
+    }
+
+    Ok(notes.to_string())
+}
+
+/// normalize_tag trims and lower cases a tag, so that "Organic " and "organic"
+/// are the same tag, and checks that it isn't empty or too long
+fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err("tag must not be empty".to_string());
+    }
+
+    let len = tag.chars().count();
+    if len > database::MAX_TAG_LEN {
+        return Err(format!(
+            "tag must be at most {} characters, got {}",
+            database::MAX_TAG_LEN,
+            len
+        ));
+    }
+
+    Ok(tag)
+}
+
+/// validate_price_cents checks that a price is within the supported range
+fn validate_price_cents(price_cents: i64) -> Result<(), String> {
+    if (0..=database::MAX_ENTRY_PRICE_CENTS).contains(&price_cents) {
+        Ok(())
+    } else {
+        Err(format!(
+            "price_cents must be between 0 and {}, got {}",
+            database::MAX_ENTRY_PRICE_CENTS,
+            price_cents
+        ))
+    }
+}
+
+/// validate_currency checks that a currency is a three letter ISO 4217 code
+/// like USD, and returns it upper cased. An empty currency is kept as is.
+fn validate_currency(currency: &str) -> Result<String, String> {
+    let currency = currency.trim();
+    if currency.is_empty()
+        || (currency.len() == 3 && currency.chars().all(|c| c.is_ascii_alphabetic()))
+    {
+        Ok(currency.to_ascii_uppercase())
+    } else {
+        Err(format!(
+            "currency must be a three letter code like USD, got '{}'",
+            currency
+        ))
+    }
+}
+
+/// validate_priority checks that a priority is within the supported range
+fn validate_priority(priority: i64) -> Result<(), String> {
+    if (0..=database::MAX_ENTRY_PRIORITY).contains(&priority) {
+        Ok(())
+    } else {
+        Err(format!(
+            "priority must be between 0 and {}, got {}",
+            database::MAX_ENTRY_PRIORITY,
+            priority
+        ))
+    }
+}
 
+/// parse_create_payload splits the raw description into quantity, unit,
+/// description and notes when they were not provided separately, and
+/// validates the result. The category and position are left unset for the
+/// caller to decide.
+fn parse_create_payload(
+    payload: CreateGroceryListEntry,
+) -> Result<CreateGroceryListEntry, FieldErrors> {
     // Parse the raw input if no quantity/notes are provided
     let (quantity, description, notes) = if payload.quantity.is_none() && payload.notes.is_none() {
         let (parsed_quantity, parsed_description, parsed_notes) =
@@ -113,68 +294,91 @@ pub async fn create_entry(
         (payload.quantity, payload.description, payload.notes)
     };
 
-    let category_id = db
-        .get_last_category_for_description(&description)
-        .await
-        .map_err(|e| {
-            tracing::error!("failed to get category_id: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?
-        .unwrap_or(database::DEFAULT_CATEGORY_ID);
+    // a quantity with its unit attached (e.g. `2lb`) is split up unless the
+    // unit was provided separately
+    let (quantity, unit) = match (quantity, payload.unit) {
+        (Some(quantity), None) => {
+            let (amount, unit) = split_quantity(&quantity);
+            (Some(amount), Some(unit))
+        }
+        (quantity, unit) => (quantity, unit),
+    };
 
-    let position = db
-        .get_next_position_for_item_in_category(category_id)
-        .await
-        .map_err(|e| {
-            tracing::error!("failed to find next position: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    let mut errors = FieldErrors::new();
+    if let Some(quantity) = &quantity {
+        errors.check("quantity", validate_quantity(quantity));
+    }
+
+    if let Some(priority) = payload.priority {
+        errors.check("priority", validate_priority(priority));
+    }
 
-    let parsed_payload = CreateGroceryListEntry {
+    if let Some(price_cents) = payload.price_cents {
+        errors.check("price_cents", validate_price_cents(price_cents));
+    }
+
+    // the defaults are never used, since an invalid field fails the request
+    let description = errors
+        .check("description", validate_description(&description))
+        .unwrap_or_default();
+    let notes = notes
+        .and_then(|notes| errors.check("notes", validate_notes(&notes)))
+        .filter(|notes| !notes.is_empty());
+    let currency = payload
+        .currency
+        .and_then(|currency| errors.check("currency", validate_currency(&currency)))
+        .filter(|currency| !currency.is_empty());
+    errors.into_result()?;
+
+    Ok(CreateGroceryListEntry {
         description,
         quantity,
+        unit,
         notes,
-        category_id: Some(category_id),
-        position: Some(position),
-    };
-
-    match db.create_entry(parsed_payload).await {
-        Ok(entry) => {
-            tracing::info!("created entry with id: {}", entry.id);
-            Ok(Json(entry))
-        }
-        Err(e) => {
-            tracing::error!("failed to create entry: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        price_cents: payload.price_cents,
+        currency,
+        visible_from: payload.visible_from,
+        category_id: None,
+        position: None,
+        priority: payload.priority,
+        list_id: payload.list_id,
+    })
 }
 
-pub async fn update_entry(
-    State(db): State<Arc<Database>>,
-    Path(id): Path<i64>,
-    Json(payload): Json<UpdateGroceryListEntry>,
-) -> Result<Json<GroceryListEntry>, StatusCode> {
+/// parse_update_payload splits a new description into quantity, unit,
+/// description and notes when they were not provided separately, and
+/// validates the result
+fn parse_update_payload(
+    payload: UpdateGroceryListEntry,
+) -> Result<UpdateGroceryListEntry, FieldErrors> {
     // Parse the description if it's provided but quantity/notes are not
     let parsed_payload = if let Some(ref description) = payload.description {
         if payload.quantity.is_none() && payload.notes.is_none() {
             let (parsed_quantity, parsed_description, parsed_notes) =
                 parse_entry_input(description);
+            let (quantity, unit) = if parsed_quantity.is_empty() {
+                (None, payload.unit)
+            } else {
+                let (amount, unit) = split_quantity(&parsed_quantity);
+                (Some(amount), Some(unit))
+            };
             UpdateGroceryListEntry {
+                version: payload.version,
                 description: Some(parsed_description),
                 completed: payload.completed,
-                quantity: if parsed_quantity.is_empty() {
-                    None
-                } else {
-                    Some(parsed_quantity)
-                },
+                quantity,
+                unit,
                 notes: if parsed_notes.is_empty() {
                     None
                 } else {
                     Some(parsed_notes)
                 },
+                price_cents: payload.price_cents,
+                currency: payload.currency,
+                visible_from: payload.visible_from,
                 category_id: payload.category_id,
                 position: payload.position,
+                priority: payload.priority,
             }
         } else {
             payload
@@ -183,80 +387,2366 @@ pub async fn update_entry(
         payload
     };
 
-    match db.update_entry(id, parsed_payload).await {
-        Ok(Some(entry)) => Ok(Json(entry)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+    // a quantity with its unit attached (e.g. `2lb`) is split up unless the
+    // unit was provided separately. Clearing the quantity leaves the unit be.
+    let parsed_payload = match (&parsed_payload.quantity, &parsed_payload.unit) {
+        (Some(quantity), None) if !quantity.is_empty() => {
+            let (amount, unit) = split_quantity(quantity);
+            UpdateGroceryListEntry {
+                quantity: Some(amount),
+                unit: Some(unit),
+                ..parsed_payload
+            }
+        }
+        _ => parsed_payload,
+    };
+
+    let mut errors = FieldErrors::new();
+    if let Some(quantity) = &parsed_payload.quantity {
+        errors.check("quantity", validate_quantity(quantity));
+    }
+
+    if let Some(priority) = parsed_payload.priority {
+        errors.check("priority", validate_priority(priority));
+    }
+
+    if let Some(Some(price_cents)) = parsed_payload.price_cents {
+        errors.check("price_cents", validate_price_cents(price_cents));
+    }
+
+    let description = parsed_payload
+        .description
+        .as_deref()
+        .and_then(|description| errors.check("description", validate_description(description)));
+    // empty notes are kept so that they clear the entry's note
+    let notes = parsed_payload
+        .notes
+        .as_deref()
+        .and_then(|notes| errors.check("notes", validate_notes(notes)));
+    // an empty currency is kept so that it clears the entry's currency
+    let currency = parsed_payload
+        .currency
+        .as_deref()
+        .and_then(|currency| errors.check("currency", validate_currency(currency)));
+    errors.into_result()?;
+
+    Ok(UpdateGroceryListEntry {
+        description,
+        notes,
+        currency,
+        ..parsed_payload
+    })
+}
+
+/// parse_flag parses the true or false query parameter called name
+fn parse_flag(name: &str, value: &str) -> Result<bool, String> {
+    match value.trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!(
+            "invalid {} '{}', expected true or false",
+            name, value
+        )),
+    }
+}
+
+/// parse_changed_since parses the changed_since query parameter as an RFC 3339
+/// timestamp
+fn parse_changed_since(changed_since: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(changed_since)
+        .map(|since| since.with_timezone(&Utc))
+        .map_err(|e| {
+            format!(
+                "invalid changed_since '{}': {}. expected an RFC 3339 timestamp like 2026-01-02T15:04:05Z",
+                changed_since, e
+            )
+        })
+}
+
+/// validate_page checks the limit and offset query parameters for paging through
+/// entries
+fn validate_page(limit: Option<i64>, offset: Option<i64>) -> Result<(), String> {
+    if let Some(limit) = limit.filter(|limit| *limit < 1) {
+        return Err(format!("limit must be at least 1, got {}", limit));
+    }
+    if let Some(offset) = offset.filter(|offset| *offset < 0) {
+        return Err(format!("offset must not be negative, got {}", offset));
+    }
+
+    Ok(())
+}
+
+/// get_entries returns the active entries in a list along with their tags.
+/// When changed_since is given only the entries updated since then are
+/// returned, along with the ids of the entries that were removed from the list
+/// since then and the as_of to send as changed_since next time. When limit or offset is given a single page of entries is
+/// returned along with the total. When tag is given only the entries with it
+/// are returned, and when completed is only the ones that are, or aren't, done.
+/// Entries that are scheduled for later are left out unless include_scheduled
+/// is true.
+#[utoipa::path(
+    get,
+    path = "/api/entries",
+    tag = "entries",
+    params(EntriesQuery),
+    responses(
+        (status = 200, description = "the entries in order, or an EntriesPage when paging with limit or offset, or an EntriesDelta with changed_since", body = [ApiGroceryListEntry]),
+        (status = 400, description = "a query parameter is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn get_entries(
+    State(state): State<AppState>,
+    Query(params): Query<EntriesQuery>,
+) -> Result<Response, ApiError> {
+    tracing::info!("GET /api/entries called");
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+
+    let changed_since = params
+        .changed_since
+        .as_deref()
+        .map(parse_changed_since)
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+
+    validate_page(params.limit, params.offset).map_err(ApiError::bad_request)?;
+    let paginated = params.limit.is_some() || params.offset.is_some();
+    if paginated && changed_since.is_some() {
+        return Err(ApiError::bad_request(
+            "limit and offset can't be combined with changed_since",
+        ));
+    }
+
+    let tag = params
+        .tag
+        .as_deref()
+        .map(normalize_tag)
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+    if tag.is_some() && changed_since.is_some() {
+        return Err(ApiError::bad_request(
+            "tag can't be combined with changed_since",
+        ));
+    }
+
+    let completed = params
+        .completed
+        .as_deref()
+        .map(|completed| parse_flag("completed", completed))
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+    // an entry that was checked off has to show up in a delta whichever way
+    // it went, so a delta can't be filtered on it
+    if completed.is_some() && changed_since.is_some() {
+        return Err(ApiError::bad_request(
+            "completed can't be combined with changed_since",
+        ));
+    }
+
+    let include_scheduled = params
+        .include_scheduled
+        .as_deref()
+        .map(|include_scheduled| parse_flag("include_scheduled", include_scheduled))
+        .transpose()
+        .map_err(ApiError::bad_request)?
+        .unwrap_or(false);
+    let filter = EntryFilter {
+        tag: tag.as_deref(),
+        completed,
+        hide_scheduled: !include_scheduled,
+    };
+
+    tracing::info!("archiving entries");
+    if let Err(e) = state.archive_entries().await {
+        tracing::error!("Failed to archive entries: {}", e);
+    }
+
+    let tags = match state.get_list_entry_tags(list_id).await {
+        Ok(tags) => tags,
         Err(e) => {
-            tracing::error!("failed to update entry {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to get entry tags: {}", e);
+            return Err(ApiError::database(&e, "failed to get entries"));
+        }
+    };
+
+    if paginated {
+        let offset = params.offset.unwrap_or(0);
+        return match state
+            .get_active_entries_page(list_id, params.sort, filter, params.limit, offset)
+            .await
+        {
+            Ok((entries, total)) => {
+                tracing::info!(
+                    "Successfully retrieved {} of {} entries",
+                    entries.len(),
+                    total
+                );
+                let next_offset = offset + entries.len() as i64;
+                Ok(Json(EntriesPage {
+                    items: with_tags(&entries, &tags),
+                    total,
+                    next_offset: (next_offset < total).then_some(next_offset),
+                })
+                .into_response())
+            }
+            Err(e) => {
+                tracing::error!("Failed to get page of entries: {}", e);
+                Err(ApiError::database(&e, "failed to get entries"))
+            }
+        };
+    }
+
+    let Some(since) = changed_since else {
+        return match state.get_active_entries(list_id, params.sort, filter).await {
+            Ok(entries) => {
+                tracing::info!("Successfully retrieved {} entries", entries.len());
+                Ok(Json(with_tags(&entries, &tags)).into_response())
+            }
+            Err(e) => {
+                tracing::error!("Failed to get entries: {}", e);
+                Err(ApiError::database(&e, "failed to get entries"))
+            }
+        };
+    };
+
+    match state
+        .get_entries_changed_since(list_id, since, filter.hide_scheduled)
+        .await
+    {
+        Ok((entries, deleted_ids, as_of)) => {
+            tracing::info!(
+                "Retrieved {} changed and {} deleted entries since {}",
+                entries.len(),
+                deleted_ids.len(),
+                timestamp::format(since)
+            );
+            Ok(Json(EntriesDelta {
+                entries: with_tags(&entries, &tags),
+                deleted_ids,
+                as_of,
+            })
+            .into_response())
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to get entries changed since {}: {}",
+                timestamp::format(since),
+                e
+            );
+            Err(ApiError::database(&e, "failed to get entries"))
         }
     }
 }
 
-pub async fn delete_entry(
-    State(db): State<Arc<Database>>,
+/// with_tags converts entries for the api, filling in the tags each has
+pub(crate) fn with_tags(
+    entries: &[GroceryListEntry],
+    tags: &HashMap<i64, Vec<String>>,
+) -> Vec<ApiGroceryListEntry> {
+    entries
+        .iter()
+        .map(|entry| ApiGroceryListEntry {
+            tags: Some(tags.get(&entry.id).cloned().unwrap_or_default()),
+            ..entry.into()
+        })
+        .collect()
+}
+
+/// get_scheduled_entries returns the entries in a list that are scheduled for
+/// later, and so left out of get_entries, along with their tags, soonest
+/// first
+#[utoipa::path(
+    get,
+    path = "/api/entries/scheduled",
+    tag = "entries",
+    params(ScheduledQuery),
+    responses(
+        (status = 200, description = "the scheduled entries, soonest first", body = [ApiGroceryListEntry]),
+    ),
+)]
+pub async fn get_scheduled_entries(
+    State(state): State<AppState>,
+    Query(query): Query<ScheduledQuery>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    let list_id = query.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+
+    let tags = match state.get_list_entry_tags(list_id).await {
+        Ok(tags) => tags,
+        Err(e) => {
+            tracing::error!("Failed to get entry tags: {}", e);
+            return Err(ApiError::database(&e, "failed to get scheduled entries"));
+        }
+    };
+
+    match state.get_scheduled_entries(list_id).await {
+        Ok(entries) => {
+            tracing::info!("Retrieved {} scheduled entries", entries.len());
+            Ok(Json(with_tags(&entries, &tags)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to get scheduled entries: {}", e);
+            Err(ApiError::database(&e, "failed to get scheduled entries"))
+        }
+    }
+}
+
+/// get_entry returns a single active entry with its tags, in the same shape as
+/// the entries from get_entries
+#[utoipa::path(
+    get,
+    path = "/api/entries/{id}",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to get")),
+    responses(
+        (status = 200, description = "the entry", body = ApiGroceryListEntry),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn get_entry(
+    State(state): State<AppState>,
     Path(id): Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    match db.delete_entry(id).await {
-        Ok(true) => Ok(StatusCode::NO_CONTENT),
-        Ok(false) => Err(StatusCode::NOT_FOUND),
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+) -> Result<Json<ApiGroceryListEntry>, ApiError> {
+    match state.get_active_entry(id).await {
+        Ok(Some((entry, tags))) => Ok(Json(ApiGroceryListEntry {
+            tags: Some(tags),
+            ..(&entry).into()
+        })),
+        Ok(None) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to get entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to get entry"))
+        }
     }
 }
 
-pub async fn reorder_entries(
-    State(db): State<Arc<Database>>,
-    Json(payload): Json<ReorderEntry>,
-) -> Result<StatusCode, StatusCode> {
-    match db.reorder_entries(payload).await {
-        Ok(()) => Ok(StatusCode::NO_CONTENT),
+/// IDEMPOTENCY_KEY is the header a client can send with create_entry so that
+/// retrying the request returns the entry it already created instead of
+/// adding it again
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// idempotency_key reads the Idempotency-Key header, if it was sent
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<String>, String> {
+    let Some(key) = headers.get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+    match key.to_str().map(str::trim) {
+        Ok(key) if !key.is_empty() && key.chars().count() <= database::MAX_IDEMPOTENCY_KEY_LEN => {
+            Ok(Some(key.to_string()))
+        }
+        _ => Err(format!(
+            "Idempotency-Key must be 1 to {} visible characters",
+            database::MAX_IDEMPOTENCY_KEY_LEN
+        )),
+    }
+}
+
+/// repeated_entry is the response to a request whose Idempotency-Key was
+/// already used: the entry the first request resulted in, as it is now. None
+/// means the key is free.
+async fn repeated_entry(state: &AppState, key: &str) -> Result<Option<Response>, ApiError> {
+    match state.get_idempotent_entry(key).await {
+        Ok(Some(entry)) => {
+            tracing::info!(
+                "returning entry {} for a repeated idempotency key",
+                entry.id
+            );
+            Ok(Some(
+                Json(ApiGroceryListEntry::from(&entry)).into_response(),
+            ))
+        }
+        Ok(None) => Ok(None),
         Err(e) => {
-            tracing::error!("Failed to reorder: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("failed to look up idempotency key: {}", e);
+            Err(ApiError::database(&e, "failed to create entry"))
         }
     }
 }
 
-pub async fn get_suggestions(
-    State(db): State<Arc<Database>>,
-    Query(params): Query<SuggestionsQuery>,
-) -> Result<Json<Vec<String>>, StatusCode> {
+/// lost_idempotency_race answers a request that was beaten to its
+/// Idempotency-Key by one sent at the same time, with the entry the winner
+/// created
+async fn lost_idempotency_race(state: &AppState, key: Option<&str>) -> Result<Response, ApiError> {
+    let key = key.unwrap_or_default();
+    repeated_entry(state, key)
+        .await?
+        .ok_or_else(|| ApiError::internal("failed to create entry"))
+}
+
+/// create_entry adds an entry to the end of its category. With ?merge=true an
+/// outstanding entry with the same description and unit in that category has the
+/// quantity added to it instead, and is returned in place of a new entry. With
+/// ?position=top the new entry goes first in its category rather than last. A
+/// new entry is returned whole with 201 and its url in the Location header.
+/// A request with an Idempotency-Key that was already used in the last day
+/// gets back the entry the first one resulted in, with 200, and adds nothing.
+#[utoipa::path(
+    post,
+    path = "/api/entries",
+    tag = "entries",
+    params(
+        CreateEntryQuery,
+        ("Idempotency-Key" = Option<String>, Header, description = "makes retrying the request safe, by returning the entry it already created"),
+    ),
+    request_body = CreateGroceryListEntry,
+    responses(
+        (status = 201, description = "the new entry", body = ApiGroceryListEntry),
+        (status = 200, description = "the existing entry the new one was merged into, or the one an earlier request with the same Idempotency-Key resulted in", body = ApiGroceryListEntry),
+        (status = 400, description = "the Idempotency-Key is empty or too long", body = ErrorBody),
+        (status = 409, description = "the entry's category is full", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn create_entry(
+    State(state): State<AppState>,
+    Query(params): Query<CreateEntryQuery>,
+    headers: HeaderMap,
+    ValidJson(payload): ValidJson<CreateGroceryListEntry>,
+) -> Result<Response, ApiError> {
     tracing::info!(
-        "GET /api/entries/suggestions called with query: '{}'",
-        params.query
+        "POST /api/entries '{:?}', '{:?}', '{:?}'",
+        payload.quantity,
+        payload.description,
+        payload.notes,
     );
-    match db.get_suggestions(&params.query).await {
-        Ok(suggestions) => {
-            tracing::info!("Successfully retrieved {} suggestions", suggestions.len());
-            Ok(Json(suggestions))
+
+    let payload = parse_create_payload(payload)?;
+
+    let idempotency_key = idempotency_key(&headers).map_err(ApiError::bad_request)?;
+    if let Some(key) = &idempotency_key {
+        if let Some(response) = repeated_entry(&state, key).await? {
+            return Ok(response);
+        }
+    }
+
+    let list_id = payload.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+    let category_id = state
+        .get_category_for_new_entry(&payload.description, list_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("failed to get category_id: {}", e);
+            ApiError::database(&e, "failed to get category")
+        })?
+        .ok_or_else(|| ApiError::not_found(format!("list {} not found", list_id)))?;
+
+    if params.merge {
+        match state
+            .merge_entry(category_id, &payload, idempotency_key.as_deref())
+            .await
+        {
+            Ok(Some((before, entry))) => {
+                tracing::info!("merged into entry with id: {}", entry.id);
+                state
+                    .audit(AuditTable::Entries, entry.id, Some(&before), Some(&entry))
+                    .await;
+                state.publish(ChangeEvent::EntryUpdated { id: entry.id });
+                return Ok(Json(ApiGroceryListEntry::from(&entry)).into_response());
+            }
+            Ok(None) => {}
+            Err(e) if e.is::<database::IdempotencyKeyUsed>() => {
+                return lost_idempotency_race(&state, idempotency_key.as_deref()).await;
+            }
+            Err(e) => {
+                tracing::error!("failed to merge entry: {}", e);
+                return Err(ApiError::database(&e, "failed to merge entry"));
+            }
+        }
+    }
+
+    let entry = CreateGroceryListEntry {
+        category_id: Some(category_id),
+        list_id: Some(list_id),
+        ..payload
+    };
+    let created = match params.position {
+        EntryPlacement::Top => {
+            state
+                .create_entry_at_top(entry, idempotency_key.as_deref())
+                .await
+        }
+        EntryPlacement::Bottom => {
+            state
+                .create_entry_at_bottom(entry, idempotency_key.as_deref())
+                .await
+        }
+    };
+
+    match created {
+        Ok(entry) => {
+            tracing::info!("created entry with id: {}", entry.id);
+            state
+                .audit(AuditTable::Entries, entry.id, None, Some(&entry))
+                .await;
+            state.publish(ChangeEvent::EntryCreated { id: entry.id });
+            // the rest of the category moved down to make room
+            if params.position == EntryPlacement::Top {
+                state.publish(ChangeEvent::EntriesReordered { category_id });
+            }
+            let location = format!("/api/entries/{}", entry.id);
+            Ok((
+                StatusCode::CREATED,
+                [(header::LOCATION, location)],
+                Json(ApiGroceryListEntry::from(&entry)),
+            )
+                .into_response())
+        }
+        Err(e) if e.is::<database::IdempotencyKeyUsed>() => {
+            lost_idempotency_race(&state, idempotency_key.as_deref()).await
         }
         Err(e) => {
-            tracing::error!("Failed to get suggestions: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("failed to create entry: {}", e);
+            Err(ApiError::database(&e, "failed to create entry"))
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[utoipa::path(
+    post,
+    path = "/api/entries/bulk",
+    tag = "entries",
+    request_body = [CreateGroceryListEntry],
+    responses(
+        (status = 200, description = "the new entries", body = [ApiGroceryListEntry]),
+        (status = 409, description = "a category doesn't have room for its new entries", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn create_entries(
+    State(state): State<AppState>,
+    ValidJson(payloads): ValidJson<Vec<CreateGroceryListEntry>>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    tracing::info!("POST /api/entries/bulk with {} entries", payloads.len());
 
-    fn test_parse_entry_input(input: &str, expected: (&str, &str, &str)) {
-        let actual = parse_entry_input(input);
-        assert_eq!((expected.0.to_owned(), expected.1.to_owned(), expected.2.to_owned()), actual);
+    // every invalid entry is reported, by its index in the batch
+    let mut errors = FieldErrors::new();
+    let mut parsed_payloads = Vec::with_capacity(payloads.len());
+    for (i, payload) in payloads.into_iter().enumerate() {
+        match parse_create_payload(payload) {
+            Ok(payload) => parsed_payloads.push(payload),
+            Err(e) => errors.extend(&format!("[{}]", i), e),
+        }
     }
+    errors.into_result()?;
 
-    #[test]
-    fn test_inputs() {
-        test_parse_entry_input("2lb chicken", ("2lb", "chicken", ""));
-        test_parse_entry_input("2 chicken", ("2", "chicken", ""));
-        test_parse_entry_input("2lb chicken thighs", ("2lb", "chicken thighs", ""));
-        test_parse_entry_input("2lb chicken thighs (boneless)", ("2lb", "chicken thighs", "boneless"));
-        test_parse_entry_input("2lb chicken - skinless", ("2lb", "chicken", "skinless"));
-        test_parse_entry_input("2.5lb chicken", ("2.5lb", "chicken", ""));
-        test_parse_entry_input(".5lb chicken", (".5lb", "chicken", ""));
-        test_parse_entry_input("2.25lb carrot", ("2.25lb", "carrot", ""));
-        test_parse_entry_input(".carrot", ("", ".carrot", ""));
-        test_parse_entry_input("2.carrot", ("", "2.carrot", ""));
+    match state.create_entries(parsed_payloads).await {
+        Ok(entries) => {
+            tracing::info!("created {} entries", entries.len());
+            for entry in &entries {
+                state
+                    .audit(AuditTable::Entries, entry.id, None, Some(entry))
+                    .await;
+                state.publish(ChangeEvent::EntryCreated { id: entry.id });
+            }
+            Ok(Json(entries.iter().map(Into::into).collect()))
+        }
+        Err(e) => {
+            tracing::error!("failed to create entries: {}", e);
+            Err(ApiError::database(&e, "failed to create entries"))
+        }
+    }
+}
+
+/// update_entry applies the update only if the entry is still at the version
+/// the client sent. Otherwise it responds with 409 and the current entry so the
+/// client can merge.
+#[utoipa::path(
+    put,
+    path = "/api/entries/{id}",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to update")),
+    request_body = UpdateGroceryListEntry,
+    responses(
+        (status = 200, description = "the updated entry", body = ApiGroceryListEntry),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+        (status = 409, description = "the entry changed since the client last saw it, or the category it moves to is full", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn update_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<UpdateGroceryListEntry>,
+) -> Result<Json<ApiGroceryListEntry>, ApiError> {
+    let parsed_payload = parse_update_payload(payload)?;
+
+    let before = state.get_entry(id).await.ok();
+    match state.update_entry(id, parsed_payload).await {
+        Ok(VersionedUpdate::Updated(entry)) => {
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), Some(&entry))
+                .await;
+            state.publish(ChangeEvent::EntryUpdated { id });
+            Ok(Json(ApiGroceryListEntry::from(&entry)))
+        }
+        Ok(VersionedUpdate::Conflict(current)) => {
+            tracing::info!(
+                "rejected stale update to entry {}, now at version {}",
+                id,
+                current.version
+            );
+            Err(ApiError::conflict(
+                format!(
+                    "entry {} was changed, it is now at version {}",
+                    id, current.version
+                ),
+                &ApiGroceryListEntry::from(&current),
+            ))
+        }
+        Ok(VersionedUpdate::NotFound) => {
+            Err(ApiError::not_found(format!("entry {} not found", id)))
+        }
+        Err(e) => {
+            tracing::error!("failed to update entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to update entry"))
+        }
+    }
+}
+
+/// patch_entry changes only the fields in the body, leaving the rest of the
+/// entry as it is. Without a version the patch applies to the entry as it is
+/// now, and an entry that changes while it is being applied is still a 409.
+#[utoipa::path(
+    patch,
+    path = "/api/entries/{id}",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to patch")),
+    request_body = PatchGroceryListEntry,
+    responses(
+        (status = 200, description = "the patched entry", body = ApiGroceryListEntry),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+        (status = 409, description = "the entry changed since the client last saw it, or the category it moves to is full", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn patch_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<PatchGroceryListEntry>,
+) -> Result<Json<ApiGroceryListEntry>, ApiError> {
+    let version = match payload.version {
+        Some(version) => version,
+        None => state
+            .get_entry(id)
+            .await
+            .ok()
+            .filter(|entry| entry.deleted_at.is_none())
+            .map(|entry| entry.version)
+            .ok_or_else(|| ApiError::not_found(format!("entry {} not found", id)))?,
+    };
+
+    update_entry(
+        State(state),
+        Path(id),
+        ValidJson(payload.into_update(version)),
+    )
+    .await
+}
+
+/// update_entries applies a batch of updates in one transaction. If any of
+/// them can't be applied none of them are, and the error names the entry that
+/// failed.
+#[utoipa::path(
+    patch,
+    path = "/api/entries/batch",
+    tag = "entries",
+    request_body = [BatchUpdateEntry],
+    responses(
+        (status = 200, description = "the updated entries", body = [ApiGroceryListEntry]),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+        (status = 409, description = "an entry changed since the client last saw it, or a category it moves to is full", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn update_entries(
+    State(state): State<AppState>,
+    ValidJson(payloads): ValidJson<Vec<BatchUpdateEntry>>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    tracing::info!("PATCH /api/entries/batch with {} updates", payloads.len());
+
+    let mut seen = std::collections::HashSet::new();
+    let mut errors = FieldErrors::new();
+    let mut updates = Vec::with_capacity(payloads.len());
+    for (i, payload) in payloads.into_iter().enumerate() {
+        if !seen.insert(payload.id) {
+            return Err(ApiError::bad_request(format!(
+                "entry {} is updated more than once",
+                payload.id
+            )));
+        }
+        match parse_update_payload(payload.fields) {
+            Ok(fields) => updates.push((payload.id, fields)),
+            Err(e) => errors.extend(&format!("[{}].fields", i), e),
+        }
+    }
+    errors.into_result()?;
+
+    let mut befores = HashMap::new();
+    for (id, _) in &updates {
+        if let Ok(entry) = state.get_entry(*id).await {
+            befores.insert(*id, entry);
+        }
+    }
+    match state.update_entries(updates).await {
+        Ok(BatchUpdate::Updated(entries)) => {
+            tracing::info!("updated {} entries", entries.len());
+            for entry in &entries {
+                state
+                    .audit(
+                        AuditTable::Entries,
+                        entry.id,
+                        befores.get(&entry.id),
+                        Some(entry),
+                    )
+                    .await;
+                state.publish(ChangeEvent::EntryUpdated { id: entry.id });
+            }
+            Ok(Json(entries.iter().map(Into::into).collect()))
+        }
+        Ok(BatchUpdate::Conflict(id, current)) => Err(ApiError::conflict(
+            format!(
+                "entry {} was changed, it is now at version {}, so none of the updates were applied",
+                id, current.version
+            ),
+            &ApiGroceryListEntry::from(&*current),
+        )),
+        Ok(BatchUpdate::NotFound(id)) => Err(ApiError::not_found(format!(
+            "entry {} not found, so none of the updates were applied",
+            id
+        ))),
+        Err(e) => {
+            tracing::error!("failed to update entries: {}", e);
+            Err(ApiError::database(&e, "failed to update entries"))
+        }
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/entries/{id}/complete",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to check off or back on")),
+    responses(
+        (status = 200, description = "the toggled entry", body = ApiGroceryListEntry),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn toggle_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiGroceryListEntry>, ApiError> {
+    let before = state.get_entry(id).await.ok();
+    match state.toggle_entry_completed(id).await {
+        Ok(Some(entry)) => {
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), Some(&entry))
+                .await;
+            state.publish(ChangeEvent::EntryUpdated { id });
+            Ok(Json((&entry).into()))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to toggle entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to toggle entry"))
+        }
+    }
+}
+
+/// entry_tags gets the tags an active entry has, to audit a change to them
+/// against
+async fn entry_tags(state: &AppState, id: i64) -> Option<EntryTags> {
+    match state.get_active_entry(id).await {
+        Ok(Some((_, tags))) => Some(EntryTags { entry_id: id, tags }),
+        _ => None,
+    }
+}
+
+/// add_entry_tag puts a tag on an entry. Tags are lower cased and trimmed, and
+/// adding one the entry already has changes nothing.
+#[utoipa::path(
+    post,
+    path = "/api/entries/{id}/tags",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to tag")),
+    request_body = AddTag,
+    responses(
+        (status = 200, description = "every tag the entry has", body = EntryTags),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+        (status = 422, description = "the tag is invalid", body = ErrorBody),
+    ),
+)]
+pub async fn add_entry_tag(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<AddTag>,
+) -> Result<Json<EntryTags>, ApiError> {
+    let mut errors = FieldErrors::new();
+    let Some(tag) = errors.check("tag", normalize_tag(&payload.tag)) else {
+        return Err(errors.into());
+    };
+
+    let before = entry_tags(&state, id).await;
+    match state.add_entry_tag(id, &tag).await {
+        Ok(Some(tags)) => {
+            let after = EntryTags { entry_id: id, tags };
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), Some(&after))
+                .await;
+            state.publish(ChangeEvent::EntryUpdated { id });
+            Ok(Json(after))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to tag entry {} with {}: {}", id, tag, e);
+            Err(ApiError::database(&e, "failed to tag entry"))
+        }
+    }
+}
+
+/// remove_entry_tag takes a tag off an entry. Removing one the entry doesn't
+/// have changes nothing.
+#[utoipa::path(
+    delete,
+    path = "/api/entries/{id}/tags/{tag}",
+    tag = "entries",
+    params(
+        ("id" = i64, Path, description = "the entry to untag"),
+        ("tag" = String, Path, description = "the tag to remove, matched ignoring case"),
+    ),
+    responses(
+        (status = 200, description = "the tags the entry has left", body = EntryTags),
+        (status = 400, description = "the tag is invalid", body = ErrorBody),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn remove_entry_tag(
+    State(state): State<AppState>,
+    Path((id, tag)): Path<(i64, String)>,
+) -> Result<Json<EntryTags>, ApiError> {
+    let tag = normalize_tag(&tag).map_err(ApiError::bad_request)?;
+
+    let before = entry_tags(&state, id).await;
+    match state.remove_entry_tag(id, &tag).await {
+        Ok(Some(tags)) => {
+            let after = EntryTags { entry_id: id, tags };
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), Some(&after))
+                .await;
+            state.publish(ChangeEvent::EntryUpdated { id });
+            Ok(Json(after))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to remove tag {} from entry {}: {}", tag, id, e);
+            Err(ApiError::database(&e, "failed to remove tag from entry"))
+        }
+    }
+}
+
+/// toggle_pin pins an entry so that it comes first in its category, or unpins
+/// it if it already is
+#[utoipa::path(
+    post,
+    path = "/api/entries/{id}/pin",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to pin or unpin")),
+    responses(
+        (status = 200, description = "the entry with its pin toggled", body = ApiGroceryListEntry),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn toggle_pin(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiGroceryListEntry>, ApiError> {
+    let before = state.get_entry(id).await.ok();
+    match state.toggle_entry_pinned(id).await {
+        Ok(Some(entry)) => {
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), Some(&entry))
+                .await;
+            state.publish(ChangeEvent::EntryUpdated { id });
+            Ok(Json((&entry).into()))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to toggle pin of entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to toggle pin"))
+        }
+    }
+}
+
+/// get_entry_history gets every time the item with the given name was checked
+/// off, most recent first, so the first one is when it was last bought
+#[utoipa::path(
+    get,
+    path = "/api/entries/{id}/history",
+    tag = "entries",
+    params(
+        ("id" = String, Path, description = "the item's description, matched ignoring case"),
+        HistoryQuery,
+    ),
+    responses(
+        (status = 200, description = "every purchase of the item, most recent first", body = [PurchaseEvent]),
+    ),
+)]
+pub async fn get_entry_history(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<PurchaseEvent>>, ApiError> {
+    state
+        .get_purchase_history(&name, query.list_id)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            tracing::error!("failed to get purchase history of {}: {}", name, e);
+            ApiError::database(&e, "failed to get purchase history")
+        })
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/entries/{id}",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to move to the trash")),
+    responses(
+        (status = 204, description = "the entry was moved to the trash"),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn delete_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let before = state.get_entry(id).await.ok();
+    match state.delete_entry(id).await {
+        Ok(true) => {
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), None)
+                .await;
+            state.publish(ChangeEvent::EntryDeleted { id });
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to delete entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to delete entry"))
+        }
+    }
+}
+
+/// delete_completed_entries moves every completed entry on the list to the
+/// trash at once, where they can still be restored from
+#[utoipa::path(
+    delete,
+    path = "/api/entries/completed",
+    tag = "entries",
+    params(ClearCompletedQuery),
+    responses(
+        (status = 200, description = "the entries that were moved to the trash", body = ClearedEntries),
+    ),
+)]
+pub async fn delete_completed_entries(
+    State(state): State<AppState>,
+    Query(params): Query<ClearCompletedQuery>,
+) -> Result<Json<ClearedEntries>, ApiError> {
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+    tracing::info!("DELETE /api/entries/completed for list {}", list_id);
+
+    let completed = EntryFilter {
+        completed: Some(true),
+        ..EntryFilter::default()
+    };
+    let befores: HashMap<i64, GroceryListEntry> = state
+        .get_active_entries(list_id, EntrySort::Position, completed)
+        .await
+        .map(|entries| entries.into_iter().map(|entry| (entry.id, entry)).collect())
+        .unwrap_or_default();
+    match state.delete_completed_entries(list_id).await {
+        Ok(ids) => {
+            tracing::info!("deleted {} completed entries", ids.len());
+            for id in &ids {
+                state
+                    .audit(AuditTable::Entries, *id, befores.get(id), None)
+                    .await;
+                state.publish(ChangeEvent::EntryDeleted { id: *id });
+            }
+            Ok(Json(ClearedEntries {
+                deleted: ids.len(),
+                ids,
+            }))
+        }
+        Err(e) => {
+            tracing::error!("failed to delete completed entries: {}", e);
+            Err(ApiError::database(&e, "failed to delete completed entries"))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/entries/{id}/restore",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to bring back from the trash")),
+    responses(
+        (status = 200, description = "the restored entry", body = ApiGroceryListEntry),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn restore_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ApiGroceryListEntry>, ApiError> {
+    let before = state.get_entry(id).await.ok();
+    match state.restore_entry(id).await {
+        Ok(Some(entry)) => {
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), Some(&entry))
+                .await;
+            state.publish(ChangeEvent::EntryRestored { id });
+            Ok(Json((&entry).into()))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to restore entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to restore entry"))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/entries/trash",
+    tag = "entries",
+    params(TrashQuery),
+    responses(
+        (status = 200, description = "the entries in the trash", body = [ApiGroceryListEntry]),
+    ),
+)]
+pub async fn get_trash(
+    State(state): State<AppState>,
+    Query(params): Query<TrashQuery>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    tracing::info!("GET /api/entries/trash called");
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+
+    match state.get_deleted_entries(list_id).await {
+        Ok(entries) => Ok(Json(entries.iter().map(Into::into).collect())),
+        Err(e) => {
+            tracing::error!("Failed to get deleted entries: {}", e);
+            Err(ApiError::database(&e, "failed to get deleted entries"))
+        }
+    }
+}
+
+/// move_entry moves an entry into another category, renumbering the entries
+/// in both the category it left and the one it joined
+#[utoipa::path(
+    put,
+    path = "/api/entries/{id}/move",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to move")),
+    request_body = MoveEntry,
+    responses(
+        (status = 200, description = "the moved entry", body = ApiGroceryListEntry),
+        (status = 404, description = "the entry or category doesn't exist", body = ErrorBody),
+        (status = 409, description = "the category is full", body = ErrorBody),
+    ),
+)]
+pub async fn move_entry(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<MoveEntry>,
+) -> Result<Json<ApiGroceryListEntry>, ApiError> {
+    let category_id = payload.category_id;
+    let before = state.get_entry(id).await.ok();
+    match state.move_entry(id, payload).await {
+        Ok(EntryMove::Moved(entry)) => {
+            state
+                .audit(AuditTable::Entries, id, before.as_ref(), Some(&*entry))
+                .await;
+            state.publish(ChangeEvent::EntryUpdated { id });
+            Ok(Json(ApiGroceryListEntry::from(&*entry)))
+        }
+        Ok(EntryMove::EntryNotFound) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Ok(EntryMove::CategoryNotFound) => Err(ApiError::not_found(format!(
+            "category {} not found",
+            category_id
+        ))),
+        Err(e) => {
+            tracing::error!("failed to move entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to move entry"))
+        }
+    }
+}
+
+/// move_entry_up swaps an entry with the one above it in its category, and
+/// returns the entries that moved. An entry already at the top stays there.
+#[utoipa::path(
+    post,
+    path = "/api/entries/{id}/move-up",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to move up")),
+    responses(
+        (status = 200, description = "the entries that moved, in their new order", body = [ApiGroceryListEntry]),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn move_entry_up(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    nudge_entry(state, id, Nudge::Up).await
+}
+
+/// move_entry_down swaps an entry with the one below it in its category, and
+/// returns the entries that moved. An entry already at the bottom stays there.
+#[utoipa::path(
+    post,
+    path = "/api/entries/{id}/move-down",
+    tag = "entries",
+    params(("id" = i64, Path, description = "the entry to move down")),
+    responses(
+        (status = 200, description = "the entries that moved, in their new order", body = [ApiGroceryListEntry]),
+        (status = 404, description = "the entry doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn move_entry_down(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    nudge_entry(state, id, Nudge::Down).await
+}
+
+/// nudge_entry moves an entry one slot up or down for move_entry_up and
+/// move_entry_down
+async fn nudge_entry(
+    state: AppState,
+    id: i64,
+    direction: Nudge,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    match state.nudge_entry(id, direction).await {
+        Ok(Some(entries)) => {
+            if let [first, second] = entries.as_slice() {
+                // a nudge swaps the two positions, so each entry was where the
+                // other one is now
+                for (entry, other) in [(first, second), (second, first)] {
+                    let before = GroceryListEntry {
+                        position: other.position,
+                        ..entry.clone()
+                    };
+                    state
+                        .audit(AuditTable::Entries, entry.id, Some(&before), Some(entry))
+                        .await;
+                }
+                state.publish(ChangeEvent::EntriesReordered {
+                    category_id: first.category_id,
+                });
+            }
+            Ok(Json(entries.iter().map(Into::into).collect()))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("entry {} not found", id))),
+        Err(e) => {
+            tracing::error!("failed to move entry {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to move entry"))
+        }
+    }
+}
+
+/// reorder_entries either moves a single entry or, given the full list of ids
+/// in a category, puts the whole category in that order
+#[utoipa::path(
+    put,
+    path = "/api/entries/reorder",
+    tag = "entries",
+    request_body = ReorderEntries,
+    responses(
+        (status = 204, description = "the entries were reordered"),
+        (status = 400, description = "the ids are wrong", body = ErrorBody),
+        (status = 409, description = "the category the entry moves to is full", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn reorder_entries(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<ReorderEntries>,
+) -> Result<StatusCode, ApiError> {
+    // an entry moved within its own category doesn't say which one that is,
+    // so look it up to know which category to tell clients about
+    let category_id = match &payload {
+        ReorderEntries::Move(reorder) => match reorder.new_category_id {
+            Some(category_id) => Some(category_id),
+            None => state
+                .get_entry(reorder.id)
+                .await
+                .ok()
+                .map(|entry| entry.category_id),
+        },
+        ReorderEntries::Order(order) => Some(order.category_id),
+    };
+
+    let result = match payload {
+        ReorderEntries::Move(reorder) => state.reorder_entries(reorder).await,
+        ReorderEntries::Order(order) => state.order_entries(order).await,
+    };
+
+    match result {
+        Ok(ReorderOutcome::Reordered) => {
+            if let Some(category_id) = category_id {
+                state.publish(ChangeEvent::EntriesReordered { category_id });
+            }
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(ReorderOutcome::InvalidIds(invalid)) => Err(ApiError::bad_request(invalid)),
+        Err(e) => {
+            tracing::error!("Failed to reorder: {}", e);
+            Err(ApiError::database(&e, "failed to reorder"))
+        }
+    }
+}
+
+/// parse_search_terms splits a search query into the terms that all have to
+/// match. Words are separate terms, and a phrase in double quotes is kept as
+/// one, with a missing closing quote ending the phrase at the end of the query.
+fn parse_search_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for (i, part) in query.split('"').enumerate() {
+        // the parts between quotes are the phrases
+        if i % 2 == 1 {
+            let phrase = part.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !phrase.is_empty() {
+                terms.push(phrase);
+            }
+        } else {
+            terms.extend(part.split_whitespace().map(str::to_string));
+        }
+    }
+    terms
+}
+
+/// search_entries finds the entries of a list whose description, notes, tags
+/// or category match every word and quoted phrase of the query, with the ones
+/// that match by description first
+#[utoipa::path(
+    get,
+    path = "/api/entries/search",
+    tag = "entries",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "the matching entries", body = [ApiGroceryListEntry]),
+        (status = 400, description = "the query is empty", body = ErrorBody),
+    ),
+)]
+pub async fn search_entries(
+    State(state): State<AppState>,
+    Query(params): Query<SearchQuery>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    tracing::info!("GET /api/entries/search called with q: '{}'", params.q);
+
+    let query = params.q.trim();
+    let terms = parse_search_terms(query);
+    if terms.is_empty() {
+        return Err(ApiError::bad_request("search query must not be empty"));
+    }
+
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+    match state.search_entries(list_id, &terms).await {
+        Ok(entries) => {
+            tracing::info!("found {} entries matching '{}'", entries.len(), query);
+            Ok(Json(entries.iter().map(Into::into).collect()))
+        }
+        Err(e) => {
+            tracing::error!("failed to search entries: {}", e);
+            Err(ApiError::database(&e, "failed to search entries"))
+        }
+    }
+}
+
+/// suggestions_limit resolves the requested number of suggestions, defaulting
+/// when none was requested and clamping requests over the maximum
+pub(crate) fn suggestions_limit(limit: Option<i64>) -> Result<i64, String> {
+    match limit {
+        None => Ok(database::DEFAULT_SUGGESTIONS_LIMIT),
+        Some(limit) if limit < 1 => Err(format!("limit must be at least 1, got {}", limit)),
+        Some(limit) => Ok(limit.min(database::MAX_SUGGESTIONS_LIMIT)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/entries/suggestions",
+    tag = "entries",
+    params(SuggestionsQuery),
+    responses(
+        (status = 200, description = "descriptions of past entries that match the query", body = [String]),
+    ),
+)]
+pub async fn get_suggestions(
+    State(state): State<AppState>,
+    Query(params): Query<SuggestionsQuery>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    tracing::info!(
+        "GET /api/entries/suggestions called with query: '{}'",
+        params.query
+    );
+    let limit = suggestions_limit(params.limit).map_err(ApiError::bad_request)?;
+
+    match state.get_suggestions(&params.query, limit).await {
+        Ok(suggestions) => {
+            tracing::info!("Successfully retrieved {} suggestions", suggestions.len());
+            Ok(Json(suggestions))
+        }
+        Err(e) => {
+            tracing::error!("Failed to get suggestions: {}", e);
+            Err(ApiError::database(&e, "failed to get suggestions"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{category, entry, memory_state};
+
+    fn test_parse_entry_input(input: &str, expected: (&str, &str, &str)) {
+        let actual = parse_entry_input(input);
+        assert_eq!((expected.0.to_owned(), expected.1.to_owned(), expected.2.to_owned()), actual);
+    }
+
+    #[test]
+    fn test_inputs() {
+        test_parse_entry_input("2lb chicken", ("2lb", "chicken", ""));
+        test_parse_entry_input("2 chicken", ("2", "chicken", ""));
+        test_parse_entry_input("2lb chicken thighs", ("2lb", "chicken thighs", ""));
+        test_parse_entry_input("2lb chicken thighs (boneless)", ("2lb", "chicken thighs", "boneless"));
+        test_parse_entry_input("2lb chicken - skinless", ("2lb", "chicken", "skinless"));
+        test_parse_entry_input("2.5lb chicken", ("2.5lb", "chicken", ""));
+        test_parse_entry_input(".5lb chicken", (".5lb", "chicken", ""));
+        test_parse_entry_input("2.25lb carrot", ("2.25lb", "carrot", ""));
+        test_parse_entry_input(".carrot", ("", ".carrot", ""));
+        test_parse_entry_input("2.carrot", ("", "2.carrot", ""));
+    }
+
+    #[test]
+    fn test_split_quantity() {
+        assert_eq!(("2".to_owned(), "lb".to_owned()), split_quantity("2lb"));
+        assert_eq!(("2".to_owned(), "".to_owned()), split_quantity("2"));
+        assert_eq!((".5".to_owned(), "lb".to_owned()), split_quantity(".5lb"));
+        assert_eq!(("2.25".to_owned(), "cups".to_owned()), split_quantity("2.25cups"));
+        assert_eq!(("".to_owned(), "".to_owned()), split_quantity(""));
+    }
+
+    #[test]
+    fn test_validate_quantity() {
+        assert!(validate_quantity("").is_ok());
+        assert!(validate_quantity("2").is_ok());
+        assert!(validate_quantity(".5").is_ok());
+        assert!(validate_quantity("0").is_ok());
+        assert!(validate_quantity("-2").is_err());
+        assert!(validate_quantity("2-3").is_err());
+    }
+
+    #[test]
+    fn test_validate_priority() {
+        assert!(validate_priority(0).is_ok());
+        assert!(validate_priority(database::MAX_ENTRY_PRIORITY).is_ok());
+        assert!(validate_priority(-1).is_err());
+        assert!(validate_priority(database::MAX_ENTRY_PRIORITY + 1).is_err());
+    }
+
+    #[test]
+    fn test_validate_description() {
+        assert_eq!(Ok("milk".to_string()), validate_description("  milk "));
+        assert_eq!(
+            Err("description must not be empty".to_string()),
+            validate_description("   ")
+        );
+        assert_eq!(
+            Err("description must be at most 200 characters, got 300".to_string()),
+            validate_description(&"a".repeat(300))
+        );
+        assert!(validate_description(&"a".repeat(database::MAX_ENTRY_DESCRIPTION_LEN)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_price() {
+        assert!(validate_price_cents(0).is_ok());
+        assert_eq!(
+            Err("price_cents must be between 0 and 100000000, got -5".to_string()),
+            validate_price_cents(-5)
+        );
+        assert_eq!(Ok("EUR".to_string()), validate_currency(" eur "));
+        assert_eq!(Ok(String::new()), validate_currency(""));
+        assert!(validate_currency("dollars").is_err());
+
+        let update: UpdateGroceryListEntry =
+            serde_json::from_value(serde_json::json!({"version": 1, "price_cents": null})).unwrap();
+        assert_eq!(Some(None), update.price_cents);
+        let update: UpdateGroceryListEntry =
+            serde_json::from_value(serde_json::json!({"version": 1})).unwrap();
+        assert_eq!(None, update.price_cents);
+    }
+
+    #[test]
+    fn test_validate_notes() {
+        assert_eq!(Ok("ripe ones".to_string()), validate_notes(" ripe ones  "));
+        assert_eq!(Ok(String::new()), validate_notes("   "));
+        assert_eq!(
+            Err("notes must be at most 1000 characters, got 1001".to_string()),
+            validate_notes(&"a".repeat(1001))
+        );
+
+        let created = parse_create_payload(CreateGroceryListEntry {
+            quantity: Some("6".to_string()),
+            notes: Some("  ".to_string()),
+            ..entry("bananas")
+        })
+        .unwrap();
+        assert_eq!(None, created.notes);
+    }
+
+    #[test]
+    fn test_suggestions_limit() {
+        assert_eq!(
+            Ok(database::DEFAULT_SUGGESTIONS_LIMIT),
+            suggestions_limit(None)
+        );
+        assert_eq!(Ok(5), suggestions_limit(Some(5)));
+        assert_eq!(
+            Ok(database::MAX_SUGGESTIONS_LIMIT),
+            suggestions_limit(Some(database::MAX_SUGGESTIONS_LIMIT + 1))
+        );
+        assert!(suggestions_limit(Some(0)).is_err());
+        assert!(suggestions_limit(Some(-3)).is_err());
+    }
+
+    #[test]
+    fn test_validate_page() {
+        assert!(validate_page(None, None).is_ok());
+        assert!(validate_page(Some(1), Some(0)).is_ok());
+        assert!(validate_page(None, Some(20)).is_ok());
+        assert!(validate_page(Some(0), None).is_err());
+        assert!(validate_page(None, Some(-1)).is_err());
+    }
+
+    #[test]
+    fn test_parse_changed_since() {
+        assert_eq!(
+            parse_changed_since("2026-01-02T15:04:05Z").unwrap().to_rfc3339(),
+            "2026-01-02T15:04:05+00:00"
+        );
+        assert_eq!(
+            parse_changed_since("2026-01-02T10:04:05-05:00").unwrap().to_rfc3339(),
+            "2026-01-02T15:04:05+00:00"
+        );
+        assert!(parse_changed_since("2026-01-02").is_err());
+        assert!(parse_changed_since("yesterday").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_entry_returns_location() {
+        let state = memory_state().await;
+
+        let payload = entry("2 apples");
+        let response = create_entry(
+            State(state.clone()),
+            Query(CreateEntryQuery {
+                merge: false,
+                position: EntryPlacement::Bottom,
+            }),
+            HeaderMap::new(),
+            ValidJson(payload),
+        )
+        .await
+        .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(StatusCode::CREATED, response.status());
+        let location = response.headers()[header::LOCATION]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let entry: ApiGroceryListEntry = serde_json::from_slice(&body).unwrap();
+        assert_eq!(format!("/api/entries/{}", entry.id), location);
+        assert_eq!("apples", entry.description);
+        assert_eq!("2", entry.quantity);
+        assert!(entry.position.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_create_entry_at_top() {
+        let state = memory_state().await;
+
+        for (description, position) in [
+            ("milk", EntryPlacement::Bottom),
+            ("eggs", EntryPlacement::Bottom),
+            ("bread", EntryPlacement::Top),
+        ] {
+            let payload = CreateGroceryListEntry {
+                quantity: Some(String::new()),
+                ..entry(description)
+            };
+            let response = create_entry(
+                State(state.clone()),
+                Query(CreateEntryQuery {
+                    merge: false,
+                    position,
+                }),
+                HeaderMap::new(),
+                ValidJson(payload),
+            )
+            .await
+            .unwrap();
+            assert_eq!(StatusCode::CREATED, response.status());
+        }
+
+        let entries = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        let order: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.description.as_str(), entry.position))
+            .collect();
+        assert_eq!(
+            vec![("bread", Some(1)), ("milk", Some(2)), ("eggs", Some(3))],
+            order
+        );
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(Ok("organic".to_string()), normalize_tag("  Organic "));
+        assert!(normalize_tag("   ").is_err());
+        assert!(normalize_tag(&"a".repeat(database::MAX_TAG_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_parse_search_terms() {
+        assert_eq!(vec!["oat", "milk"], parse_search_terms("oat  milk"));
+        assert_eq!(
+            vec!["get", "oat milk", "soon"],
+            parse_search_terms("get \"oat   milk\" soon")
+        );
+        assert_eq!(vec!["oat milk"], parse_search_terms("\"oat milk"));
+        assert!(parse_search_terms(" \"  \" ").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_entries() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description, notes) in [
+            (1, "cereal", Some("goes with oat milk")),
+            (2, "bread", None),
+            (3, "Oat Milk", None),
+            (4, "milk", Some("not oat")),
+        ] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    notes: notes.map(str::to_string),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        state.add_entry_tag(ids[1], "oat").await.unwrap();
+
+        let search = |q: &str| {
+            search_entries(
+                State(state.clone()),
+                Query(SearchQuery {
+                    q: q.to_string(),
+                    list_id: None,
+                }),
+            )
+        };
+        let ids_of = |Json(entries): Json<Vec<ApiGroceryListEntry>>| {
+            entries.iter().map(|e| e.id).collect::<Vec<_>>()
+        };
+        let oat = ids_of(search("OAT").await.unwrap());
+        let phrase = ids_of(search("\"oat milk\"").await.unwrap());
+        let words = ids_of(search("milk oat").await.unwrap());
+        let empty = search(" \"\" ").await.unwrap_err();
+
+        state.pool.close().await;
+
+        // a match in the description ranks above one in notes or tags
+        assert_eq!(vec![ids[2], ids[0], ids[1], ids[3]], oat);
+        assert_eq!(vec![ids[2], ids[0]], phrase);
+        assert_eq!(vec![ids[2], ids[3], ids[0]], words);
+        assert_eq!(StatusCode::BAD_REQUEST, empty.status);
+    }
+
+    #[tokio::test]
+    async fn test_search_entries_in_list() {
+        use crate::models::{category::CreateCategory, list::CreateList};
+
+        let state = memory_state().await;
+
+        let hardware = state
+            .create_list(CreateList {
+                name: "hardware".to_string(),
+            })
+            .await
+            .unwrap();
+        let mill = state
+            .create_category(CreateCategory {
+                list_id: Some(hardware.id),
+                ..category("Oat Mill")
+            })
+            .await
+            .unwrap();
+        let milk = state
+            .create_entry(CreateGroceryListEntry {
+                position: Some(1),
+                ..entry("oat milk")
+            })
+            .await
+            .unwrap();
+        // not visible until tomorrow
+        state
+            .create_entry(CreateGroceryListEntry {
+                position: Some(2),
+                visible_from: Some(Utc::now() + chrono::Duration::days(1)),
+                ..entry("oat bran")
+            })
+            .await
+            .unwrap();
+        let mut tools = Vec::new();
+        for (position, description) in [(1, "sawdust"), (2, "oat board"), (3, "sandpaper")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(mill.id),
+                    list_id: Some(hardware.id),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            tools.push(entry.id);
+        }
+        state.toggle_entry_pinned(tools[2]).await.unwrap();
+
+        let search = |list_id| {
+            search_entries(
+                State(state.clone()),
+                Query(SearchQuery {
+                    q: "oat".to_string(),
+                    list_id,
+                }),
+            )
+        };
+        let ids_of = |Json(entries): Json<Vec<ApiGroceryListEntry>>| {
+            entries.iter().map(|e| e.id).collect::<Vec<_>>()
+        };
+        let groceries = ids_of(search(None).await.unwrap());
+        let hardware = ids_of(search(Some(hardware.id)).await.unwrap());
+
+        state.pool.close().await;
+
+        // the oat mill only matches in its own list
+        assert_eq!(vec![milk.id], groceries);
+        // by description, then pinned
+        assert_eq!(vec![tools[1], tools[2], tools[0]], hardware);
+    }
+
+    #[tokio::test]
+    async fn test_entry_tags() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs"), (3, "bread")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+
+        let tag = |id, tag: &str| {
+            add_entry_tag(
+                State(state.clone()),
+                Path(id),
+                ValidJson(AddTag {
+                    tag: tag.to_string(),
+                }),
+            )
+        };
+        let _ = tag(ids[0], "Organic").await.unwrap();
+        let _ = tag(ids[0], "sale").await.unwrap();
+        let Json(milk) = tag(ids[0], " organic ").await.unwrap();
+        let _ = tag(ids[2], "organic").await.unwrap();
+        let blank = tag(ids[1], " ").await.unwrap_err();
+        let missing = tag(9999, "sale").await.unwrap_err();
+
+        let Json(bread) =
+            remove_entry_tag(State(state.clone()), Path((ids[2], "ORGANIC".to_string())))
+                .await
+                .unwrap();
+
+        let entries = |tag: Option<&str>| {
+            get_entries(
+                State(state.clone()),
+                Query(EntriesQuery {
+                    list_id: None,
+                    changed_since: None,
+                    sort: EntrySort::Position,
+                    tag: tag.map(str::to_string),
+                    completed: None,
+                    include_scheduled: None,
+                    limit: None,
+                    offset: None,
+                }),
+            )
+        };
+        let body = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<Vec<ApiGroceryListEntry>>(&body).unwrap()
+        };
+        let organic = body(entries(Some("Organic")).await.unwrap()).await;
+        let all = body(entries(None).await.unwrap()).await;
+
+        state.pool.close().await;
+
+        assert_eq!(vec!["organic", "sale"], milk.tags);
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, blank.status);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+        assert!(bread.tags.is_empty());
+        assert_eq!(
+            vec![ids[0]],
+            organic.iter().map(|e| e.id).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(milk.tags), all[0].tags);
+        assert_eq!(Some(Vec::new()), all[1].tags);
+    }
+
+    #[tokio::test]
+    async fn test_toggle_pin() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs"), (3, "bread"), (4, "butter")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+
+        let Json(butter) = toggle_pin(State(state.clone()), Path(ids[3]))
+            .await
+            .unwrap();
+        let Json(eggs) = toggle_pin(State(state.clone()), Path(ids[1]))
+            .await
+            .unwrap();
+        let _ = toggle_entry(State(state.clone()), Path(ids[1]))
+            .await
+            .unwrap();
+        let order = |state: AppState| async move {
+            state
+                .get_active_entries(
+                    database::DEFAULT_LIST_ID,
+                    EntrySort::Position,
+                    EntryFilter::default(),
+                )
+                .await
+                .unwrap()
+                .iter()
+                .map(|entry| entry.id)
+                .collect::<Vec<_>>()
+        };
+        let pinned = order(state.clone()).await;
+
+        let Json(unpinned) = toggle_pin(State(state.clone()), Path(ids[3]))
+            .await
+            .unwrap();
+        let after_unpin = order(state.clone()).await;
+        let missing = toggle_pin(State(state.clone()), Path(9999))
+            .await
+            .unwrap_err();
+
+        state.pool.close().await;
+
+        assert!(butter.pinned);
+        assert!(eggs.pinned);
+        assert_eq!(vec![ids[1], ids[3], ids[0], ids[2]], pinned);
+        assert!(!unpinned.pinned);
+        assert_eq!(Some(4), unpinned.position);
+        assert_eq!(vec![ids[1], ids[0], ids[2], ids[3]], after_unpin);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+    }
+
+    #[tokio::test]
+    async fn test_patch_entry() {
+        let state = memory_state().await;
+
+        let entry = state
+            .create_entry(CreateGroceryListEntry {
+                position: Some(1),
+                quantity: Some("2".to_string()),
+                unit: Some("l".to_string()),
+                notes: Some("oat".to_string()),
+                price_cents: Some(350),
+                currency: Some("USD".to_string()),
+                category_id: Some(database::DEFAULT_CATEGORY_ID),
+                ..entry("milk")
+            })
+            .await
+            .unwrap();
+
+        let patch = |body: serde_json::Value| {
+            patch_entry(
+                State(state.clone()),
+                Path(entry.id),
+                ValidJson(serde_json::from_value(body).unwrap()),
+            )
+        };
+        let Json(noted) = patch(serde_json::json!({"notes": "whole"})).await.unwrap();
+        let Json(cleared) = patch(serde_json::json!({"quantity": null, "price_cents": null}))
+            .await
+            .unwrap();
+        let stale = patch(serde_json::json!({"version": entry.version, "notes": null}))
+            .await
+            .unwrap_err();
+
+        state.pool.close().await;
+
+        assert_eq!(Some("whole".to_string()), noted.notes);
+        assert_eq!("milk", noted.description);
+        assert_eq!("2", noted.quantity);
+        assert_eq!(Some(350), noted.price_cents);
+
+        assert_eq!("", cleared.quantity);
+        assert_eq!("l", cleared.unit);
+        assert_eq!(None, cleared.price_cents);
+        assert_eq!(Some("whole".to_string()), cleared.notes);
+        assert_eq!(Some("USD".to_string()), cleared.currency);
+        assert_eq!(entry.version + 2, cleared.version);
+
+        assert_eq!(StatusCode::CONFLICT, stale.status);
+    }
+
+    #[tokio::test]
+    async fn test_get_entry() {
+        let state = memory_state().await;
+
+        let entry = state
+            .create_entry(CreateGroceryListEntry {
+                position: Some(1),
+                quantity: Some("2".to_string()),
+                unit: Some("l".to_string()),
+                category_id: Some(database::DEFAULT_CATEGORY_ID),
+                ..entry("milk")
+            })
+            .await
+            .unwrap();
+        state.add_entry_tag(entry.id, "organic").await.unwrap();
+
+        let Json(fetched) = get_entry(State(state.clone()), Path(entry.id))
+            .await
+            .unwrap();
+        let response = get_entries(
+            State(state.clone()),
+            Query(EntriesQuery {
+                list_id: None,
+                changed_since: None,
+                sort: EntrySort::Position,
+                tag: None,
+                completed: None,
+                include_scheduled: None,
+                limit: None,
+                offset: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let listed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let missing = get_entry(State(state.clone()), Path(entry.id + 1))
+            .await
+            .unwrap_err();
+        state.delete_entry(entry.id).await.unwrap();
+        let trashed = get_entry(State(state.clone()), Path(entry.id))
+            .await
+            .unwrap_err();
+
+        state.pool.close().await;
+
+        assert_eq!(Some(vec!["organic".to_string()]), fetched.tags);
+        assert_eq!(listed[0], serde_json::to_value(&fetched).unwrap());
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+        assert_eq!(StatusCode::NOT_FOUND, trashed.status);
+    }
+
+    #[tokio::test]
+    async fn test_delete_completed_entries() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs"), (3, "bread")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        state.toggle_entry_completed(ids[0]).await.unwrap();
+        state.toggle_entry_completed(ids[2]).await.unwrap();
+
+        let Json(cleared) = delete_completed_entries(
+            State(state.clone()),
+            Query(ClearCompletedQuery { list_id: None }),
+        )
+        .await
+        .unwrap();
+        let remaining = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap();
+        let trash = state
+            .get_deleted_entries(database::DEFAULT_LIST_ID)
+            .await
+            .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(2, cleared.deleted);
+        assert_eq!(vec![ids[2], ids[0]], cleared.ids);
+        assert_eq!(1, remaining.len());
+        assert_eq!("eggs", remaining[0].description);
+        assert_eq!(Some(1), remaining[0].position);
+        assert_eq!(2, trash.len());
+    }
+
+    #[tokio::test]
+    async fn test_reorder_entries_renumbers() {
+        use crate::models::grocery_entry::{OrderEntries, ReorderEntry};
+
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs"), (3, "bread"), (4, "tea")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+
+        let order = || async {
+            state
+                .get_active_entries(
+                    database::DEFAULT_LIST_ID,
+                    EntrySort::Position,
+                    EntryFilter::default(),
+                )
+                .await
+                .unwrap()
+                .iter()
+                .map(|entry| (entry.position.unwrap(), entry.id))
+                .collect::<Vec<_>>()
+        };
+        let reorder = |payload| reorder_entries(State(state.clone()), ValidJson(payload));
+
+        let shuffled = vec![ids[2], ids[0], ids[3], ids[1]];
+        reorder(ReorderEntries::Order(OrderEntries {
+            category_id: database::DEFAULT_CATEGORY_ID,
+            ids: shuffled.clone(),
+        }))
+        .await
+        .unwrap();
+        let ordered = order().await;
+
+        // positions far out of range just put the entry at either end
+        reorder(ReorderEntries::Move(ReorderEntry {
+            id: ids[2],
+            new_position: Some(1_000_000),
+            new_category_id: None,
+        }))
+        .await
+        .unwrap();
+        let moved_last = order().await;
+        reorder(ReorderEntries::Move(ReorderEntry {
+            id: ids[1],
+            new_position: Some(-7),
+            new_category_id: None,
+        }))
+        .await
+        .unwrap();
+        let moved_first = order().await;
+
+        state.pool.close().await;
+
+        let numbered = |ids: Vec<i64>| (1..).zip(ids).collect::<Vec<_>>();
+        assert_eq!(numbered(shuffled), ordered);
+        assert_eq!(numbered(vec![ids[0], ids[3], ids[1], ids[2]]), moved_last);
+        assert_eq!(numbered(vec![ids[1], ids[0], ids[3], ids[2]]), moved_first);
+    }
+
+    #[tokio::test]
+    async fn test_entry_history() {
+        let state = memory_state().await;
+
+        let entry = state
+            .create_entry(CreateGroceryListEntry {
+                position: Some(1),
+                quantity: Some("2".to_string()),
+                unit: Some("l".to_string()),
+                category_id: Some(database::DEFAULT_CATEGORY_ID),
+                ..entry("Milk")
+            })
+            .await
+            .unwrap();
+
+        // unchecking an entry isn't a purchase, but checking it off again is
+        for _ in 0..3 {
+            let _ = toggle_entry(State(state.clone()), Path(entry.id))
+                .await
+                .unwrap();
+        }
+
+        let Json(history) = get_entry_history(
+            State(state.clone()),
+            Path("milk".to_string()),
+            Query(HistoryQuery { list_id: None }),
+        )
+        .await
+        .unwrap();
+        let Json(other_list) = get_entry_history(
+            State(state.clone()),
+            Path("milk".to_string()),
+            Query(HistoryQuery { list_id: Some(2) }),
+        )
+        .await
+        .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(2, history.len());
+        assert!(history[0].id > history[1].id);
+        assert_eq!(Some(entry.id), history[0].entry_id);
+        assert_eq!("Milk", history[0].description);
+        assert_eq!("2", history[0].quantity);
+        assert_eq!("l", history[0].unit);
+        assert!(other_list.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_suggestions_tolerate_typos() {
+        let state = memory_state().await;
+
+        for (position, description) in [(1, "broccoli"), (2, "bread"), (3, "carrots")] {
+            state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+        }
+
+        let suggest = |query: &str| {
+            get_suggestions(
+                State(state.clone()),
+                Query(SuggestionsQuery {
+                    query: query.to_string(),
+                    limit: None,
+                }),
+            )
+        };
+        let Json(misspelled) = suggest("brocolli").await.unwrap();
+        let Json(partial) = suggest("bre").await.unwrap();
+        let Json(unrelated) = suggest("xyz").await.unwrap();
+
+        state.pool.close().await;
+
+        let trimmed = |suggestions: Vec<String>| {
+            suggestions
+                .iter()
+                .map(|s| s.trim().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["broccoli"], trimmed(misspelled));
+        // the prefix match comes before the one that is a typo away
+        assert_eq!(vec!["bread", "broccoli"], trimmed(partial));
+        assert!(unrelated.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reorder_entries_rejects_duplicate_ids() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+
+        let payload = ReorderEntries::Order(crate::models::grocery_entry::OrderEntries {
+            category_id: database::DEFAULT_CATEGORY_ID,
+            ids: vec![ids[0], ids[0]],
+        });
+        let error = reorder_entries(State(state.clone()), ValidJson(payload))
+            .await
+            .unwrap_err();
+
+        state.pool.close().await;
+
+        assert_eq!(StatusCode::BAD_REQUEST, error.status);
+        assert_eq!("bad_request", error.code);
+        let message = error.message;
+        assert!(message.contains(&format!("duplicate ids [{}]", ids[0])));
+        assert!(message.contains(&format!("missing ids [{}]", ids[1])));
+    }
+
+    #[tokio::test]
+    async fn test_missing_entry_is_not_found() {
+        let state = memory_state().await;
+
+        let update = UpdateGroceryListEntry {
+            version: 1,
+            description: Some("milk".to_string()),
+            completed: None,
+            quantity: None,
+            unit: None,
+            notes: None,
+            price_cents: None,
+            currency: None,
+            visible_from: None,
+            category_id: None,
+            position: None,
+            priority: None,
+        };
+        let updated = update_entry(State(state.clone()), Path(9999), ValidJson(update))
+            .await
+            .unwrap_err();
+        let deleted = delete_entry(State(state.clone()), Path(9999))
+            .await
+            .unwrap_err();
+
+        state.pool.close().await;
+
+        assert_eq!(StatusCode::NOT_FOUND, updated.status);
+        assert_eq!(StatusCode::NOT_FOUND, deleted.status);
+        assert_eq!("entry 9999 not found", deleted.message);
+    }
+
+    #[tokio::test]
+    async fn test_move_entry_up_and_down() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs"), (3, "bread")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+
+        let Json(moved) = move_entry_up(State(state.clone()), Path(ids[2]))
+            .await
+            .unwrap();
+        let Json(top) = move_entry_up(State(state.clone()), Path(ids[0]))
+            .await
+            .unwrap();
+        let Json(bottom) = move_entry_down(State(state.clone()), Path(ids[1]))
+            .await
+            .unwrap();
+        let missing = move_entry_down(State(state.clone()), Path(9999))
+            .await
+            .unwrap_err();
+        let order: Vec<i64> = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|entry| entry.id)
+            .collect();
+
+        state.pool.close().await;
+
+        assert_eq!(
+            vec![(ids[2], Some(2)), (ids[1], Some(3))],
+            moved.iter().map(|e| (e.id, e.position)).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![ids[0]], top.iter().map(|e| e.id).collect::<Vec<_>>());
+        assert_eq!(
+            vec![ids[1]],
+            bottom.iter().map(|e| e.id).collect::<Vec<_>>()
+        );
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+        assert_eq!(vec![ids[0], ids[2], ids[1]], order);
+    }
+
+    #[tokio::test]
+    async fn test_move_entry() {
+        let state = memory_state().await;
+
+        let dairy = state.create_category(category("dairy")).await.unwrap();
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "cheese"), (2, "eggs"), (3, "bread")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+
+        let Json(moved) = move_entry(
+            State(state.clone()),
+            Path(ids[0]),
+            ValidJson(MoveEntry {
+                category_id: dairy.id,
+                position: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let missing = move_entry(
+            State(state.clone()),
+            Path(ids[1]),
+            ValidJson(MoveEntry {
+                category_id: 9999,
+                position: None,
+            }),
+        )
+        .await
+        .unwrap_err();
+        let left_behind: Vec<_> = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.category_id == database::DEFAULT_CATEGORY_ID)
+            .map(|entry| (entry.id, entry.position))
+            .collect();
+
+        state.pool.close().await;
+
+        assert_eq!(dairy.id, moved.category_id);
+        assert_eq!(Some(1), moved.position);
+        assert_eq!(2, moved.version);
+        assert_eq!(vec![(ids[1], Some(1)), (ids[2], Some(2))], left_behind);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+    }
+
+    #[tokio::test]
+    async fn test_update_entries_is_all_or_nothing() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        let complete = |id| BatchUpdateEntry {
+            id,
+            fields: UpdateGroceryListEntry {
+                version: 1,
+                description: None,
+                completed: Some(true),
+                quantity: None,
+                unit: None,
+                notes: None,
+                price_cents: None,
+                currency: None,
+                visible_from: None,
+                category_id: None,
+                position: None,
+                priority: None,
+            },
+        };
+
+        let failed = update_entries(
+            State(state.clone()),
+            ValidJson(vec![complete(ids[0]), complete(9999)]),
+        )
+        .await
+        .unwrap_err();
+        let untouched = state.get_entry(ids[0]).await.unwrap();
+        let Json(updated) = update_entries(
+            State(state.clone()),
+            ValidJson(vec![complete(ids[0]), complete(ids[1])]),
+        )
+        .await
+        .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(StatusCode::NOT_FOUND, failed.status);
+        assert!(failed.message.starts_with("entry 9999 not found"));
+        assert!(untouched.completed_at.is_none());
+        assert_eq!(2, updated.len());
+        assert!(updated.iter().all(|entry| entry.completed));
     }
 }
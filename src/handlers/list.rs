@@ -0,0 +1,587 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::database::{self, EntryFilter};
+use crate::error::{ApiError, ErrorBody};
+use crate::models::archive::ArchivedTrip;
+use crate::models::audit::AuditTable;
+use crate::models::event::ChangeEvent;
+use crate::models::grocery_entry::{ApiGroceryListEntry, EntrySort};
+use crate::models::list::{CreateList, List, ListShare, SharedList, UpdateList};
+use crate::state::AppState;
+use crate::validation::{FieldErrors, ValidJson};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ArchivesQuery {
+    list_id: Option<i64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RestoreArchiveQuery {
+    #[serde(default)]
+    categories_only: bool,
+}
+
+/// validate_list_name checks that a list's name, if it is being set, isn't
+/// blank
+fn validate_list_name(name: Option<&str>) -> Result<(), FieldErrors> {
+    let mut errors = FieldErrors::new();
+    if name.is_some_and(|name| name.trim().is_empty()) {
+        errors.add("name", "list name must not be empty");
+    }
+    errors.into_result()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/lists",
+    tag = "lists",
+    responses((status = 200, description = "every list", body = [List])),
+)]
+pub async fn get_lists(State(state): State<AppState>) -> Result<Json<Vec<List>>, ApiError> {
+    tracing::info!("GET /api/lists called");
+    match state.get_all_lists().await {
+        Ok(lists) => {
+            tracing::info!("Successfully retrieved {} lists", lists.len());
+            Ok(Json(lists))
+        }
+        Err(e) => {
+            tracing::error!("Failed to get lists: {}", e);
+            Err(ApiError::database(&e, "failed to get lists"))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/lists/{id}",
+    tag = "lists",
+    params(("id" = i64, Path, description = "the list")),
+    responses(
+        (status = 200, description = "the list", body = List),
+        (status = 404, description = "the list doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn get_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<List>, ApiError> {
+    match state.get_list(id).await {
+        Ok(Some(list)) => Ok(Json(list)),
+        Ok(None) => Err(ApiError::not_found(format!("list {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to get list {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to get list"))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/lists",
+    tag = "lists",
+    request_body = CreateList,
+    responses(
+        (status = 200, description = "the new list", body = List),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn create_list(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreateList>,
+) -> Result<Json<List>, ApiError> {
+    tracing::info!("POST /api/lists called with name: '{}'", payload.name);
+    validate_list_name(Some(&payload.name))?;
+
+    match state.create_list(payload).await {
+        Ok(list) => {
+            tracing::info!("Successfully created list with id: {}", list.id);
+            state
+                .audit(AuditTable::Lists, list.id, None, Some(&list))
+                .await;
+            state.publish(ChangeEvent::ListCreated { id: list.id });
+            Ok(Json(list))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create list: {}", e);
+            Err(ApiError::database(&e, "failed to create list"))
+        }
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/lists/{id}",
+    tag = "lists",
+    params(("id" = i64, Path, description = "the list to update")),
+    request_body = UpdateList,
+    responses(
+        (status = 200, description = "the updated list", body = List),
+        (status = 404, description = "the list doesn't exist", body = ErrorBody),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn update_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    ValidJson(payload): ValidJson<UpdateList>,
+) -> Result<Json<List>, ApiError> {
+    validate_list_name(payload.name.as_deref())?;
+
+    let before = state.get_list(id).await.ok().flatten();
+    match state.update_list(id, payload).await {
+        Ok(Some(list)) => {
+            state
+                .audit(AuditTable::Lists, id, before.as_ref(), Some(&list))
+                .await;
+            state.publish(ChangeEvent::ListUpdated { id });
+            Ok(Json(list))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("list {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to update list {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to update list"))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/lists/{id}",
+    tag = "lists",
+    params(("id" = i64, Path, description = "the list to delete")),
+    responses(
+        (status = 204, description = "the list was deleted"),
+        (status = 404, description = "the list doesn't exist", body = ErrorBody),
+        (status = 405, description = "the default list can't be deleted", body = ErrorBody),
+    ),
+)]
+pub async fn delete_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    // don't allow deleting the default list
+    if id == database::DEFAULT_LIST_ID {
+        return Err(ApiError::method_not_allowed(
+            "the default list can't be deleted",
+        ));
+    }
+
+    let before = state.get_list(id).await.ok().flatten();
+    match state.delete_list(id).await {
+        Ok(true) => {
+            state
+                .audit(AuditTable::Lists, id, before.as_ref(), None)
+                .await;
+            state.publish(ChangeEvent::ListDeleted { id });
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Ok(false) => Err(ApiError::not_found(format!("list {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to delete list {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to delete list"))
+        }
+    }
+}
+
+/// share_list makes a read-only link to the list, or gets the one it already
+/// has, which stays the same until it is revoked with unshare_list. It is a
+/// POST since it can create the link, so it needs the api token like other
+/// changes do.
+#[utoipa::path(
+    post,
+    path = "/api/lists/{id}/share",
+    tag = "lists",
+    params(("id" = i64, Path, description = "the list to share")),
+    responses(
+        (status = 200, description = "the list's read-only share", body = ListShare),
+        (status = 404, description = "the list doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn share_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ListShare>, ApiError> {
+    match state.share_list(id).await {
+        Ok(Some(share)) => Ok(Json(share)),
+        Ok(None) => Err(ApiError::not_found(format!("list {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to share list {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to share list"))
+        }
+    }
+}
+
+/// unshare_list revokes the list's read-only link
+#[utoipa::path(
+    delete,
+    path = "/api/lists/{id}/share",
+    tag = "lists",
+    params(("id" = i64, Path, description = "the list to stop sharing")),
+    responses(
+        (status = 204, description = "the share was revoked"),
+        (status = 404, description = "the list isn't shared", body = ErrorBody),
+    ),
+)]
+pub async fn unshare_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    match state.unshare_list(id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::not_found(format!("list {} is not shared", id))),
+        Err(e) => {
+            tracing::error!("Failed to unshare list {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to unshare list"))
+        }
+    }
+}
+
+/// get_shared_list shows the list shared with token, to anyone who has it.
+/// Only reads are routed here, so a shared list can't be changed through it.
+#[utoipa::path(
+    get,
+    path = "/api/shared/{token}",
+    tag = "lists",
+    params(("token" = String, Path, description = "the token of the share")),
+    responses(
+        (status = 200, description = "the shared list", body = SharedList),
+        (status = 404, description = "the share was revoked or never existed", body = ErrorBody),
+    ),
+)]
+pub async fn get_shared_list(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<SharedList>, ApiError> {
+    let shared = async {
+        let Some(list_id) = state.get_shared_list_id(&token).await? else {
+            return Ok(None);
+        };
+        let Some(list) = state.get_list(list_id).await? else {
+            return Ok(None);
+        };
+        let categories = state.get_all_categories(list_id).await?;
+        // entries scheduled for later aren't on the list yet for anyone
+        let visible = EntryFilter {
+            hide_scheduled: true,
+            ..EntryFilter::default()
+        };
+        let entries = state
+            .get_active_entries(list_id, EntrySort::Position, visible)
+            .await?
+            .iter()
+            .map(Into::into)
+            .collect();
+
+        anyhow::Ok(Some(SharedList {
+            list,
+            categories,
+            entries,
+        }))
+    };
+
+    match shared.await {
+        Ok(Some(shared)) => Ok(Json(shared)),
+        // the token isn't echoed back, since it is a secret
+        Ok(None) => Err(ApiError::not_found("shared list not found")),
+        Err(e) => {
+            tracing::error!("Failed to get shared list: {}", e);
+            Err(ApiError::database(&e, "failed to get shared list"))
+        }
+    }
+}
+
+/// archive_list ends a shopping trip, keeping a record of everything on the
+/// list and then clearing it
+#[utoipa::path(
+    post,
+    path = "/api/lists/{id}/archive",
+    tag = "archives",
+    params(("id" = i64, Path, description = "the list to archive")),
+    responses(
+        (status = 200, description = "the archived trip", body = ArchivedTrip),
+        (status = 404, description = "the list doesn't exist", body = ErrorBody),
+        (status = 422, description = "the list has no entries to archive", body = ErrorBody),
+    ),
+)]
+pub async fn archive_list(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ArchivedTrip>, ApiError> {
+    tracing::info!("POST /api/lists/{}/archive called", id);
+    let list = state.get_list(id).await.map_err(|e| {
+        tracing::error!("Failed to get list {}: {}", id, e);
+        ApiError::database(&e, "failed to get list")
+    })?;
+    if list.is_none() {
+        return Err(ApiError::not_found(format!("list {} not found", id)));
+    }
+
+    match state.archive_list(id).await {
+        Ok(Some(trip)) => {
+            tracing::info!("Archived {} entries from list {}", trip.entries.len(), id);
+            state.publish(ChangeEvent::ListArchived { list_id: id });
+            Ok(Json(trip))
+        }
+        Ok(None) => Err(ApiError::unprocessable(format!(
+            "list {} has no entries to archive",
+            id
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to archive list {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to archive list"))
+        }
+    }
+}
+
+/// get_archives gets past trips, most recent first, optionally only those for
+/// one list
+#[utoipa::path(
+    get,
+    path = "/api/archives",
+    tag = "archives",
+    params(ArchivesQuery),
+    responses(
+        (status = 200, description = "past trips, most recent first", body = [ArchivedTrip]),
+    ),
+)]
+pub async fn get_archives(
+    State(state): State<AppState>,
+    Query(params): Query<ArchivesQuery>,
+) -> Result<Json<Vec<ArchivedTrip>>, ApiError> {
+    tracing::info!("GET /api/archives called");
+    match state.get_archived_trips(params.list_id).await {
+        Ok(trips) => Ok(Json(trips)),
+        Err(e) => {
+            tracing::error!("Failed to get archived trips: {}", e);
+            Err(ApiError::database(&e, "failed to get archived trips"))
+        }
+    }
+}
+
+/// restore_archive adds everything from a past trip back to its list, so
+/// that a regular shop can be started from the last one
+#[utoipa::path(
+    post,
+    path = "/api/archives/{id}/restore",
+    tag = "archives",
+    params(("id" = i64, Path, description = "the archived trip to restore"), RestoreArchiveQuery),
+    responses(
+        (status = 200, description = "the restored entries", body = [ApiGroceryListEntry]),
+        (status = 404, description = "the archived trip doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn restore_archive(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<RestoreArchiveQuery>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    tracing::info!(
+        "POST /api/archives/{}/restore called with categories_only: {}",
+        id,
+        params.categories_only
+    );
+    match state
+        .restore_archived_trip(id, params.categories_only)
+        .await
+    {
+        Ok(Some(entries)) => {
+            tracing::info!(
+                "Restored {} entries from archived trip {}",
+                entries.len(),
+                id
+            );
+            for entry in &entries {
+                state.publish(ChangeEvent::EntryCreated { id: entry.id });
+            }
+            Ok(Json(entries.iter().map(Into::into).collect()))
+        }
+        Ok(None) => Err(ApiError::not_found(format!(
+            "archived trip {} not found",
+            id
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to restore archived trip {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to restore archived trip"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::grocery_entry::CreateGroceryListEntry;
+    use crate::test_support::{entry, memory_state};
+
+    #[tokio::test]
+    async fn test_archive_list() {
+        let state = memory_state().await;
+
+        state
+            .create_entry(CreateGroceryListEntry {
+                position: Some(1),
+                category_id: Some(database::DEFAULT_CATEGORY_ID),
+                ..entry("milk")
+            })
+            .await
+            .unwrap();
+
+        let Json(trip) = archive_list(State(state.clone()), Path(database::DEFAULT_LIST_ID))
+            .await
+            .unwrap();
+        let remaining = state
+            .get_active_entries(
+                database::DEFAULT_LIST_ID,
+                EntrySort::Position,
+                EntryFilter::default(),
+            )
+            .await
+            .unwrap();
+        let empty = archive_list(State(state.clone()), Path(database::DEFAULT_LIST_ID))
+            .await
+            .unwrap_err();
+        let Json(trips) =
+            get_archives(State(state.clone()), Query(ArchivesQuery { list_id: None }))
+                .await
+                .unwrap();
+        let Json(restored) = restore_archive(
+            State(state.clone()),
+            Path(trip.id),
+            Query(RestoreArchiveQuery {
+                categories_only: false,
+            }),
+        )
+        .await
+        .unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(1, trip.entries.len());
+        assert_eq!("milk", trip.entries[0].description);
+        assert!(remaining.is_empty());
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, empty.status);
+        assert_eq!(1, trips.len());
+        assert_eq!(trip.id, trips[0].id);
+        assert_eq!(1, restored.len());
+        assert_eq!("milk", restored[0].description);
+        assert_ne!(trip.entries[0].id, restored[0].id);
+        assert!(!restored[0].completed);
+    }
+
+    #[tokio::test]
+    async fn test_list_changes_are_published_and_audited() {
+        use crate::models::list::{CreateList, UpdateList};
+
+        let state = memory_state().await;
+        let mut events = state.subscribe();
+
+        let Json(list) = create_list(
+            State(state.clone()),
+            ValidJson(CreateList {
+                name: "hardware".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+        let Json(_) = update_list(
+            State(state.clone()),
+            Path(list.id),
+            ValidJson(UpdateList {
+                name: Some("tools".to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+        delete_list(State(state.clone()), Path(list.id))
+            .await
+            .unwrap();
+        let published: Vec<_> = std::iter::from_fn(|| events.try_recv().ok()).collect();
+        let log = state.get_audit_log(10).await.unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(
+            vec![
+                ChangeEvent::ListCreated { id: list.id },
+                ChangeEvent::ListUpdated { id: list.id },
+                ChangeEvent::ListDeleted { id: list.id },
+            ],
+            published
+        );
+        let actions: Vec<&str> = log.iter().map(|entry| entry.action.as_str()).collect();
+        assert_eq!(vec!["delete", "update", "create"], actions);
+        assert!(log
+            .iter()
+            .all(|entry| entry.table_name == "lists" && entry.row_id == list.id));
+        assert_eq!("hardware", log[1].diff["name"]["before"]);
+        assert_eq!("tools", log[1].diff["name"]["after"]);
+    }
+
+    #[tokio::test]
+    async fn test_share_list() {
+        let state = memory_state().await;
+
+        // eggs are scheduled for tomorrow, so they aren't shared yet
+        for (position, description, visible_from) in [
+            (1, "milk", None),
+            (
+                2,
+                "eggs",
+                Some(chrono::Utc::now() + chrono::Duration::days(1)),
+            ),
+        ] {
+            state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    visible_from,
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+        }
+
+        let list_id = database::DEFAULT_LIST_ID;
+        let Json(share) = share_list(State(state.clone()), Path(list_id))
+            .await
+            .unwrap();
+        let Json(again) = share_list(State(state.clone()), Path(list_id))
+            .await
+            .unwrap();
+        let Json(shared) = get_shared_list(State(state.clone()), Path(share.token.clone()))
+            .await
+            .unwrap();
+        let unshared = unshare_list(State(state.clone()), Path(list_id))
+            .await
+            .unwrap();
+        let revoked = get_shared_list(State(state.clone()), Path(share.token.clone()))
+            .await
+            .unwrap_err();
+        let Json(reshared) = share_list(State(state.clone()), Path(list_id))
+            .await
+            .unwrap();
+        let missing = share_list(State(state.clone()), Path(42))
+            .await
+            .unwrap_err();
+
+        state.pool.close().await;
+
+        assert_eq!(32, share.token.len());
+        assert_eq!(share.token, again.token);
+        assert_eq!(list_id, shared.list.id);
+        assert_eq!(1, shared.categories.len());
+        assert_eq!(1, shared.entries.len());
+        assert_eq!("milk", shared.entries[0].description);
+        assert_eq!(StatusCode::NO_CONTENT, unshared);
+        assert_eq!(StatusCode::NOT_FOUND, revoked.status);
+        assert_ne!(share.token, reshared.token);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status);
+    }
+}
@@ -2,3 +2,17 @@ pub mod grocery;
 pub use grocery::*;
 pub mod category;
 pub use category::*;
+pub mod list;
+pub use list::*;
+pub mod export;
+pub use export::*;
+pub mod events;
+pub use events::*;
+pub mod template;
+pub use template::*;
+pub mod summary;
+pub use summary::*;
+pub mod audit;
+pub use audit::*;
+#[cfg(test)]
+mod tests;
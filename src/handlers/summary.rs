@@ -0,0 +1,191 @@
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::database;
+use crate::error::{ApiError, ErrorBody};
+use crate::models::summary::{CostSummary, Summary};
+use crate::state::AppState;
+use crate::timestamp;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CostQuery {
+    list_id: Option<i64>,
+}
+
+/// get_summary counts the entries, categories and lists, cheaply enough to
+/// poll from a dashboard
+#[utoipa::path(
+    get,
+    path = "/api/summary",
+    tag = "summary",
+    responses((status = 200, description = "the counts", body = Summary)),
+)]
+pub async fn get_summary(State(state): State<AppState>) -> Result<Json<Summary>, ApiError> {
+    tracing::info!("GET /api/summary called");
+    match state.get_summary().await {
+        Ok(summary) => Ok(Json(Summary {
+            generated_at_local: timestamp::local(summary.generated_at, state.config().timezone),
+            ..summary
+        })),
+        Err(e) => {
+            tracing::error!("Failed to get summary: {}", e);
+            Err(ApiError::database(&e, "failed to get summary"))
+        }
+    }
+}
+
+/// get_cost_summary adds up what the entries still to buy on a list will cost
+#[utoipa::path(
+    get,
+    path = "/api/summary/cost",
+    tag = "summary",
+    params(CostQuery),
+    responses(
+        (status = 200, description = "what the entries still to buy cost", body = CostSummary),
+        (status = 404, description = "the list doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn get_cost_summary(
+    State(state): State<AppState>,
+    Query(query): Query<CostQuery>,
+) -> Result<Json<CostSummary>, ApiError> {
+    let list_id = query.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+
+    match state.get_list(list_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(ApiError::not_found(format!("list {} not found", list_id))),
+        Err(e) => {
+            tracing::error!("Failed to get list {}: {}", list_id, e);
+            return Err(ApiError::database(&e, "failed to get cost summary"));
+        }
+    }
+
+    match state.get_cost_summary(list_id).await {
+        Ok(summary) => Ok(Json(summary)),
+        Err(e) => {
+            tracing::error!("Failed to get cost summary of list {}: {}", list_id, e);
+            Err(ApiError::database(&e, "failed to get cost summary"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{entry, memory_db, memory_state};
+    use crate::{
+        config::Config,
+        models::{grocery_entry::CreateGroceryListEntry, summary::CostTotal},
+    };
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_get_summary() {
+        let db = Arc::new(memory_db().await);
+        let state = AppState::new(db.clone(), Config::default());
+        let in_new_york = AppState::new(
+            db,
+            Config {
+                timezone: Some(chrono_tz::America::New_York),
+                ..Config::default()
+            },
+        );
+
+        let mut ids = Vec::new();
+        for (position, description) in [(1, "milk"), (2, "eggs"), (3, "bread")] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        state.toggle_entry_completed(ids[0]).await.unwrap();
+        state.delete_entry(ids[1]).await.unwrap();
+
+        let Json(summary) = get_summary(State(state.clone())).await.unwrap();
+        let Json(local) = get_summary(State(in_new_york)).await.unwrap();
+
+        state.pool.close().await;
+
+        assert_eq!(2, summary.entries);
+        assert_eq!(1, summary.completed_entries);
+        assert_eq!(1, summary.categories);
+        assert_eq!(1, summary.lists);
+
+        assert_eq!(None, summary.generated_at_local);
+        assert!(serde_json::to_value(&summary)
+            .unwrap()
+            .get("generated_at_local")
+            .is_none());
+        let generated_at_local = local.generated_at_local.unwrap();
+        assert_eq!(local.generated_at, generated_at_local);
+        assert!([-5 * 3600, -4 * 3600].contains(&generated_at_local.offset().local_minus_utc()));
+    }
+
+    #[tokio::test]
+    async fn test_get_cost_summary() {
+        let state = memory_state().await;
+
+        let mut ids = Vec::new();
+        for (position, description, price_cents, currency) in [
+            (1, "milk", Some(249), Some("USD")),
+            (2, "eggs", Some(399), Some("USD")),
+            (3, "bread", None, None),
+            (4, "cheese", Some(1250), Some("USD")),
+            (5, "tea", Some(500), Some("GBP")),
+        ] {
+            let entry = state
+                .create_entry(CreateGroceryListEntry {
+                    position: Some(position),
+                    price_cents,
+                    currency: currency.map(str::to_string),
+                    category_id: Some(database::DEFAULT_CATEGORY_ID),
+                    ..entry(description)
+                })
+                .await
+                .unwrap();
+            ids.push(entry.id);
+        }
+        // what has already been bought isn't part of the projected spend
+        state.toggle_entry_completed(ids[3]).await.unwrap();
+
+        let Json(summary) =
+            get_cost_summary(State(state.clone()), Query(CostQuery { list_id: None }))
+                .await
+                .unwrap();
+        let missing =
+            get_cost_summary(State(state.clone()), Query(CostQuery { list_id: Some(42) })).await;
+
+        state.pool.close().await;
+
+        assert_eq!(
+            vec![
+                CostTotal {
+                    currency: Some("GBP".to_string()),
+                    total_cents: 500,
+                    entries: 1,
+                },
+                CostTotal {
+                    currency: Some("USD".to_string()),
+                    total_cents: 648,
+                    entries: 2,
+                },
+            ],
+            summary.totals
+        );
+        assert_eq!(1, summary.unpriced_entries);
+        assert_eq!(
+            axum::http::StatusCode::NOT_FOUND,
+            missing.unwrap_err().status
+        );
+    }
+}
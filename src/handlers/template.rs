@@ -0,0 +1,180 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use super::grocery::validate_description;
+use crate::database;
+use crate::error::{ApiError, ErrorBody};
+use crate::models::event::ChangeEvent;
+use crate::models::grocery_entry::ApiGroceryListEntry;
+use crate::models::template::{CreateTemplate, Template, TemplateItem};
+use crate::state::AppState;
+use crate::validation::{FieldErrors, ValidJson};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ApplyTemplateQuery {
+    list_id: Option<i64>,
+}
+
+/// validate_template checks that a template has a name and that each of its
+/// items has a description an entry could have, trimming the descriptions
+fn validate_template(template: CreateTemplate) -> Result<CreateTemplate, FieldErrors> {
+    let mut errors = FieldErrors::new();
+    if template.name.trim().is_empty() {
+        errors.add("name", "template name must not be empty");
+    }
+    let items = template
+        .items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, item)| {
+            let description = errors.check(
+                &format!("items[{}].description", i),
+                validate_description(&item.description),
+            )?;
+            Some(TemplateItem {
+                description,
+                ..item
+            })
+        })
+        .collect();
+
+    errors.into_result()?;
+    Ok(CreateTemplate { items, ..template })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates",
+    tag = "templates",
+    responses((status = 200, description = "every template", body = [Template])),
+)]
+pub async fn get_templates(State(state): State<AppState>) -> Result<Json<Vec<Template>>, ApiError> {
+    tracing::info!("GET /api/templates called");
+    match state.get_templates().await {
+        Ok(templates) => Ok(Json(templates)),
+        Err(e) => {
+            tracing::error!("Failed to get templates: {}", e);
+            Err(ApiError::database(&e, "failed to get templates"))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/templates",
+    tag = "templates",
+    request_body = CreateTemplate,
+    responses(
+        (status = 200, description = "the new template", body = Template),
+        (status = 422, description = "the body has invalid fields", body = ErrorBody),
+    ),
+)]
+pub async fn create_template(
+    State(state): State<AppState>,
+    ValidJson(payload): ValidJson<CreateTemplate>,
+) -> Result<Json<Template>, ApiError> {
+    tracing::info!("POST /api/templates called with name: '{}'", payload.name);
+    let payload = validate_template(payload)?;
+
+    match state.create_template(payload).await {
+        Ok(template) => {
+            tracing::info!("Successfully created template with id: {}", template.id);
+            Ok(Json(template))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create template: {}", e);
+            Err(ApiError::database(&e, "failed to create template"))
+        }
+    }
+}
+
+/// apply_template adds the template's items to a list, the default list unless
+/// list_id is given, leaving out any that are already on it
+#[utoipa::path(
+    post,
+    path = "/api/templates/{id}/apply",
+    tag = "templates",
+    params(("id" = i64, Path, description = "the template to apply"), ApplyTemplateQuery),
+    responses(
+        (status = 200, description = "the entries that were added", body = [ApiGroceryListEntry]),
+        (status = 404, description = "the template doesn't exist", body = ErrorBody),
+    ),
+)]
+pub async fn apply_template(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<ApplyTemplateQuery>,
+) -> Result<Json<Vec<ApiGroceryListEntry>>, ApiError> {
+    let list_id = params.list_id.unwrap_or(database::DEFAULT_LIST_ID);
+    tracing::info!(
+        "POST /api/templates/{}/apply called for list {}",
+        id,
+        list_id
+    );
+
+    let list = state.get_list(list_id).await.map_err(|e| {
+        tracing::error!("Failed to get list {}: {}", list_id, e);
+        ApiError::database(&e, "failed to get list")
+    })?;
+    if list.is_none() {
+        return Err(ApiError::not_found(format!("list {} not found", list_id)));
+    }
+
+    match state.apply_template(id, list_id).await {
+        Ok(Some(entries)) => {
+            tracing::info!("Added {} entries from template {}", entries.len(), id);
+            for entry in &entries {
+                state.publish(ChangeEvent::EntryCreated { id: entry.id });
+            }
+            Ok(Json(entries.iter().map(Into::into).collect()))
+        }
+        Ok(None) => Err(ApiError::not_found(format!("template {} not found", id))),
+        Err(e) => {
+            tracing::error!("Failed to apply template {}: {}", id, e);
+            Err(ApiError::database(&e, "failed to apply template"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_template() {
+        let item = |description: &str| TemplateItem {
+            description: description.to_string(),
+            category_id: None,
+        };
+        let template = |name: &str, items| CreateTemplate {
+            name: name.to_string(),
+            items,
+        };
+
+        let valid = validate_template(template("weekly", vec![item(" milk ")])).unwrap();
+        assert_eq!("milk", valid.items[0].description);
+        assert!(validate_template(template("  ", vec![item("milk")])).is_err());
+        let mut errors = FieldErrors::new();
+        errors.add("items[1].description", "description must not be empty");
+        errors.add(
+            "items[2].description",
+            format!(
+                "description must be at most {} characters, got 300",
+                database::MAX_ENTRY_DESCRIPTION_LEN
+            ),
+        );
+        assert_eq!(
+            Err(errors),
+            validate_template(template(
+                "weekly",
+                vec![item("milk"), item(" "), item(&"a".repeat(300))]
+            ))
+            .map(|_| ())
+        );
+    }
+}
@@ -0,0 +1,870 @@
+//! Tests that drive the handlers against an in-memory database, so they don't
+//! touch disk. memory_state gives each test a database of its own.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+use super::{
+    add_entry_tag, create_category, create_entries, create_entry, delete_completed_entries,
+    delete_entry, get_categories, get_entries, get_scheduled_entries, move_entry, patch_entry,
+    reorder_categories, restore_entry, toggle_entry, toggle_pin, update_category,
+};
+use crate::config::Config;
+use crate::database::{self, Database, EntryFilter};
+use crate::error::ApiError;
+use crate::models::category::{Category, CreateCategory, ReorderCategories};
+use crate::models::grocery_entry::{
+    ApiGroceryListEntry, CreateGroceryListEntry, EntrySort, MoveEntry, ReorderEntries,
+};
+use crate::models::list::CreateList;
+use crate::models::tag::AddTag;
+use crate::state::AppState;
+use crate::test_support::{category, entry, memory_state};
+use crate::validation::ValidJson;
+
+/// add_entry creates the entry through the handler, returning what was stored
+async fn add_entry(
+    state: &AppState,
+    payload: CreateGroceryListEntry,
+) -> Result<ApiGroceryListEntry, ApiError> {
+    let response: Response = create_entry(
+        State(state.clone()),
+        Query(serde_json::from_value(serde_json::json!({})).unwrap()),
+        HeaderMap::new(),
+        ValidJson(payload),
+    )
+    .await?;
+    assert_eq!(StatusCode::CREATED, response.status());
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    Ok(serde_json::from_slice(&body).unwrap())
+}
+
+fn assert_invalid(error: ApiError, field: &str) {
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, error.status);
+    assert!(
+        error
+            .fields
+            .as_ref()
+            .is_some_and(|fields| fields.contains_key(field)),
+        "{} should be invalid: {:?}",
+        field,
+        error.fields
+    );
+}
+
+#[tokio::test]
+async fn test_entry_description_is_trimmed_and_keeps_its_case() {
+    let state = memory_state().await;
+
+    let padded = add_entry(&state, entry("  Whole Milk \t")).await.unwrap();
+    let parsed = add_entry(&state, entry(" 2 Large EGGS  - Free Range "))
+        .await
+        .unwrap();
+    let given = add_entry(
+        &state,
+        CreateGroceryListEntry {
+            quantity: Some("1".to_string()),
+            ..entry("\n Oat MILK \n")
+        },
+    )
+    .await
+    .unwrap();
+
+    state.pool.close().await;
+
+    assert_eq!("Whole Milk", padded.description);
+    assert_eq!("2", parsed.quantity);
+    assert_eq!("Large EGGS", parsed.description);
+    assert_eq!(Some("Free Range".to_string()), parsed.notes);
+    assert_eq!("Oat MILK", given.description);
+}
+
+#[tokio::test]
+async fn test_entry_description_length() {
+    let state = memory_state().await;
+    let max = database::MAX_ENTRY_DESCRIPTION_LEN;
+
+    let longest = add_entry(&state, entry(&"a".repeat(max))).await.unwrap();
+    let too_long = add_entry(&state, entry(&"a".repeat(max + 1)))
+        .await
+        .unwrap_err();
+    // the length is counted in characters, not bytes
+    let accented = add_entry(&state, entry(&"é".repeat(max))).await.unwrap();
+    // and only once the description has been trimmed
+    let padded = add_entry(&state, entry(&format!("  {}  ", "b".repeat(max))))
+        .await
+        .unwrap();
+    let blank = add_entry(&state, entry(" \t\n ")).await.unwrap_err();
+    let empty = add_entry(&state, entry("")).await.unwrap_err();
+
+    state.pool.close().await;
+
+    assert_eq!(max, longest.description.chars().count());
+    assert_invalid(too_long, "description");
+    assert_eq!(max, accented.description.chars().count());
+    assert_eq!("b".repeat(max), padded.description);
+    assert_invalid(blank, "description");
+    assert_invalid(empty, "description");
+}
+
+#[tokio::test]
+async fn test_category_name_is_trimmed_and_keeps_its_case() {
+    let state = memory_state().await;
+
+    let Json(frozen) = create_category(
+        State(state.clone()),
+        ValidJson(category("  Frozen Foods \t")),
+    )
+    .await
+    .unwrap();
+    let Json(epicerie) =
+        create_category(State(state.clone()), ValidJson(category("ÉPICERIE Fine")))
+            .await
+            .unwrap();
+    let same_frozen = create_category(State(state.clone()), ValidJson(category("frozen foods ")))
+        .await
+        .unwrap_err();
+    let same_epicerie =
+        create_category(State(state.clone()), ValidJson(category(" épicerie fine")))
+            .await
+            .unwrap_err();
+    let blank = create_category(State(state.clone()), ValidJson(category("   ")))
+        .await
+        .unwrap_err();
+
+    let names: Vec<String> = state
+        .get_all_categories(database::DEFAULT_LIST_ID)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|category| category.name)
+        .collect();
+    state.pool.close().await;
+
+    assert_eq!("Frozen Foods", frozen.name);
+    assert_eq!("ÉPICERIE Fine", epicerie.name);
+    for duplicate in [same_frozen, same_epicerie] {
+        assert_eq!(StatusCode::CONFLICT, duplicate.status);
+        assert_eq!("already_exists", duplicate.code);
+    }
+    assert_invalid(blank, "name");
+    assert_eq!(
+        1,
+        names.iter().filter(|name| *name == "Frozen Foods").count()
+    );
+}
+
+#[tokio::test]
+async fn test_category_max_entries() {
+    let state = memory_state().await;
+
+    let too_small = create_category(
+        State(state.clone()),
+        ValidJson(CreateCategory {
+            max_entries: Some(0),
+            ..category("Snacks")
+        }),
+    )
+    .await
+    .unwrap_err();
+    let Json(snacks) = create_category(
+        State(state.clone()),
+        ValidJson(CreateCategory {
+            max_entries: Some(2),
+            ..category("Snacks")
+        }),
+    )
+    .await
+    .unwrap();
+    let to_snacks = || {
+        ValidJson(MoveEntry {
+            category_id: snacks.id,
+            position: None,
+        })
+    };
+
+    let chips = add_entry(&state, entry("chips")).await.unwrap();
+    let pretzels = add_entry(&state, entry("pretzels")).await.unwrap();
+    let milk = add_entry(&state, entry("milk")).await.unwrap();
+    for id in [chips.id, pretzels.id] {
+        let Json(moved) = move_entry(State(state.clone()), Path(id), to_snacks())
+            .await
+            .unwrap();
+        assert_eq!(snacks.id, moved.category_id);
+    }
+    let full_move = move_entry(State(state.clone()), Path(milk.id), to_snacks())
+        .await
+        .unwrap_err();
+    // chips were last in snacks, so that is where new ones go
+    let more_chips = add_entry(&state, entry("chips")).await.unwrap_err();
+    let more_chips_in_bulk = create_entries(State(state.clone()), ValidJson(vec![entry("chips")]))
+        .await
+        .unwrap_err();
+
+    delete_entry(State(state.clone()), Path(chips.id))
+        .await
+        .unwrap();
+    let Json(moved) = move_entry(State(state.clone()), Path(milk.id), to_snacks())
+        .await
+        .unwrap();
+    // milk took the place chips left, so chips cannot come back from the trash
+    let full_restore = restore_entry(State(state.clone()), Path(chips.id))
+        .await
+        .unwrap_err();
+
+    let Json(unlimited) = update_category(
+        State(state.clone()),
+        Path(snacks.id),
+        ValidJson(
+            serde_json::from_value(
+                serde_json::json!({"version": snacks.version, "max_entries": null}),
+            )
+            .unwrap(),
+        ),
+    )
+    .await
+    .unwrap();
+    let more_pretzels = add_entry(&state, entry("pretzels")).await.unwrap();
+
+    state.pool.close().await;
+
+    assert_invalid(too_small, "max_entries");
+    assert_eq!(Some(2), snacks.max_entries);
+    for full in [full_move, more_chips, more_chips_in_bulk, full_restore] {
+        assert_eq!(StatusCode::CONFLICT, full.status);
+        assert_eq!("category_full", full.code);
+        let current = full.current.unwrap();
+        assert_eq!(2, current["count"]);
+        assert_eq!(2, current["max_entries"]);
+        assert_eq!(snacks.id, current["category_id"]);
+    }
+    assert_eq!(snacks.id, moved.category_id);
+    assert_eq!(None, unlimited.max_entries);
+    assert_eq!(snacks.id, more_pretzels.category_id);
+}
+
+#[tokio::test]
+async fn test_entry_changes_are_audited() {
+    let state = memory_state().await;
+
+    let milk = add_entry(&state, entry("milk")).await.unwrap();
+    let Json(_) = toggle_pin(State(state.clone()), Path(milk.id))
+        .await
+        .unwrap();
+    let Json(_) = add_entry_tag(
+        State(state.clone()),
+        Path(milk.id),
+        ValidJson(AddTag {
+            tag: "dairy".to_string(),
+        }),
+    )
+    .await
+    .unwrap();
+    let Json(_) = toggle_entry(State(state.clone()), Path(milk.id))
+        .await
+        .unwrap();
+    let Json(_) = delete_completed_entries(
+        State(state.clone()),
+        Query(serde_json::from_value(serde_json::json!({})).unwrap()),
+    )
+    .await
+    .unwrap();
+    let Json(_) = restore_entry(State(state.clone()), Path(milk.id))
+        .await
+        .unwrap();
+
+    let mut log = state.get_audit_log(10).await.unwrap();
+    log.reverse();
+    state.pool.close().await;
+
+    let actions: Vec<_> = log
+        .iter()
+        .map(|change| (change.row_id, change.action.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            (milk.id, "create"),
+            (milk.id, "update"),
+            (milk.id, "update"),
+            (milk.id, "update"),
+            (milk.id, "delete"),
+            (milk.id, "update"),
+        ],
+        actions
+    );
+    assert_eq!(serde_json::json!(true), log[1].diff["pinned"]["after"]);
+    assert_eq!(serde_json::json!(["dairy"]), log[2].diff["tags"]["after"]);
+    assert!(log[3].diff["completed_at"]["after"].is_string());
+    assert!(log[5].diff["deleted_at"]["before"].is_string());
+}
+
+/// create_with_key creates the entry through the handler, sending key as its
+/// Idempotency-Key, and returns the status with the entry responded with
+async fn create_with_key(
+    state: &AppState,
+    key: &str,
+    description: &str,
+) -> Result<(StatusCode, ApiGroceryListEntry), ApiError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(super::IDEMPOTENCY_KEY, key.parse().unwrap());
+    let response = create_entry(
+        State(state.clone()),
+        Query(serde_json::from_value(serde_json::json!({})).unwrap()),
+        headers,
+        ValidJson(entry(description)),
+    )
+    .await?;
+
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    Ok((status, serde_json::from_slice(&body).unwrap()))
+}
+
+#[tokio::test]
+async fn test_idempotency_key() {
+    let state = memory_state().await;
+
+    let (created_status, created) = create_with_key(&state, "retry-1", "milk").await.unwrap();
+    // a retry is answered with the first entry even if its body changed
+    let (retried_status, retried) = create_with_key(&state, "retry-1", "oat milk")
+        .await
+        .unwrap();
+    let (_, other) = create_with_key(&state, "retry-2", "milk").await.unwrap();
+    // a request that got past the lookup at the same time as the first one
+    // loses at the insert, and adds nothing
+    let raced = state
+        .create_entry_at_bottom(entry("milk"), Some("retry-1"))
+        .await
+        .unwrap_err();
+    let blank = create_with_key(&state, "  ", "milk").await.unwrap_err();
+    let too_long = create_with_key(
+        &state,
+        &"k".repeat(database::MAX_IDEMPOTENCY_KEY_LEN + 1),
+        "milk",
+    )
+    .await
+    .unwrap_err();
+
+    sqlx::query("UPDATE idempotency_keys SET created_at = datetime('now', '-25 hours') WHERE key = 'retry-2'")
+        .execute(&state.pool)
+        .await
+        .unwrap();
+    let (expired_status, expired) = create_with_key(&state, "retry-2", "milk").await.unwrap();
+    sqlx::query("UPDATE idempotency_keys SET created_at = datetime('now', '-25 hours')")
+        .execute(&state.pool)
+        .await
+        .unwrap();
+    let purged = state.purge_expired_idempotency_keys().await.unwrap();
+
+    let entries = state
+        .get_active_entries(
+            database::DEFAULT_LIST_ID,
+            EntrySort::Position,
+            EntryFilter::default(),
+        )
+        .await
+        .unwrap();
+    state.pool.close().await;
+
+    assert_eq!(StatusCode::CREATED, created_status);
+    assert_eq!(StatusCode::OK, retried_status);
+    assert_eq!(created.id, retried.id);
+    assert!(raced.is::<database::IdempotencyKeyUsed>());
+    assert_eq!("milk", retried.description);
+    assert_ne!(created.id, other.id);
+    assert_eq!(StatusCode::BAD_REQUEST, blank.status);
+    assert_eq!(StatusCode::BAD_REQUEST, too_long.status);
+
+    assert_eq!(StatusCode::CREATED, expired_status);
+    assert_ne!(other.id, expired.id);
+    assert_eq!(2, purged);
+    assert_eq!(3, entries.len());
+}
+
+#[tokio::test]
+async fn test_reorder_categories_in_list() {
+    let state = memory_state().await;
+
+    let hardware = state
+        .create_list(CreateList {
+            name: "hardware".to_string(),
+        })
+        .await
+        .unwrap();
+    state.create_category(category("produce")).await.unwrap();
+    for name in ["tools", "paint"] {
+        state
+            .create_category(CreateCategory {
+                list_id: Some(hardware.id),
+                ..category(name)
+            })
+            .await
+            .unwrap();
+    }
+    let ids = |categories: Vec<Category>| -> Vec<i64> {
+        categories.iter().map(|category| category.id).collect()
+    };
+    let groceries = ids(state
+        .get_all_categories(database::DEFAULT_LIST_ID)
+        .await
+        .unwrap());
+    let tools = ids(state.get_all_categories(hardware.id).await.unwrap());
+    let reorder = |payload: serde_json::Value| {
+        reorder_categories(
+            State(state.clone()),
+            ValidJson(serde_json::from_value(payload).unwrap()),
+        )
+    };
+
+    let reversed: Vec<i64> = tools.iter().rev().copied().collect();
+    let ordered = reorder(serde_json::json!({"list_id": hardware.id, "ids": reversed}))
+        .await
+        .unwrap();
+    let moved = reorder(serde_json::json!({
+        "list_id": hardware.id,
+        "id": reversed[0],
+        "new_position": 3,
+    }))
+    .await
+    .unwrap();
+    let mut mixed = tools.clone();
+    mixed.push(groceries[1]);
+    let mixed_order = reorder(serde_json::json!({"list_id": hardware.id, "ids": mixed}))
+        .await
+        .unwrap_err();
+    let wrong_list = reorder(serde_json::json!({
+        "list_id": hardware.id,
+        "id": groceries[1],
+        "new_position": 1,
+    }))
+    .await
+    .unwrap_err();
+    let after_groceries = ids(state
+        .get_all_categories(database::DEFAULT_LIST_ID)
+        .await
+        .unwrap());
+    let after_tools = ids(state.get_all_categories(hardware.id).await.unwrap());
+
+    state.pool.close().await;
+
+    assert_eq!(StatusCode::NO_CONTENT, ordered);
+    assert_eq!(StatusCode::NO_CONTENT, moved);
+    assert_eq!(StatusCode::BAD_REQUEST, mixed_order.status);
+    assert_eq!(StatusCode::BAD_REQUEST, wrong_list.status);
+    assert_eq!(groceries, after_groceries);
+    assert_eq!(vec![reversed[1], reversed[2], reversed[0]], after_tools);
+}
+
+/// reorder_body_error is what ValidJson rejects body with when extracting a
+/// T from it
+async fn reorder_body_error<T: serde::de::DeserializeOwned>(body: serde_json::Value) -> ApiError {
+    use axum::extract::FromRequest;
+
+    let request = axum::extract::Request::builder()
+        .header("content-type", "application/json")
+        .body(axum::body::Body::from(body.to_string()))
+        .unwrap();
+    ValidJson::<T>::from_request(request, &())
+        .await
+        .err()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_reorder_body_errors() {
+    let no_list = reorder_body_error::<ReorderCategories>(serde_json::json!({"ids": [1, 2]})).await;
+    let no_position = reorder_body_error::<ReorderCategories>(serde_json::json!({
+        "list_id": 1,
+        "id": 2,
+    }))
+    .await;
+    let no_category =
+        reorder_body_error::<ReorderEntries>(serde_json::json!({"ids": [1, 2]})).await;
+
+    assert_invalid(no_list, "list_id");
+    assert_invalid(no_position, "new_position");
+    assert_invalid(no_category, "category_id");
+}
+
+#[tokio::test]
+async fn test_category_counts() {
+    let state = memory_state().await;
+
+    let Json(dairy) = create_category(State(state.clone()), ValidJson(category("Dairy")))
+        .await
+        .unwrap();
+    let milk = add_entry(&state, entry("milk")).await.unwrap();
+    add_entry(&state, entry("eggs")).await.unwrap();
+    let bread = add_entry(&state, entry("bread")).await.unwrap();
+    let Json(completed) = toggle_entry(State(state.clone()), Path(milk.id))
+        .await
+        .unwrap();
+    delete_entry(State(state.clone()), Path(bread.id))
+        .await
+        .unwrap();
+
+    let query = |params: serde_json::Value| Query(serde_json::from_value(params).unwrap());
+    let Json(counted) = get_categories(
+        State(state.clone()),
+        query(serde_json::json!({"include": "counts"})),
+    )
+    .await
+    .unwrap();
+    let Json(lean) = get_categories(State(state.clone()), query(serde_json::json!({})))
+        .await
+        .unwrap();
+    let unknown = get_categories(
+        State(state.clone()),
+        query(serde_json::json!({"include": "entries"})),
+    )
+    .await
+    .unwrap_err();
+
+    state.pool.close().await;
+
+    let counts: Vec<(i64, Option<i64>, Option<i64>)> = counted
+        .iter()
+        .map(|counted| {
+            (
+                counted.category.id,
+                counted.total_entries,
+                counted.completed_entries,
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (milk.category_id, Some(2), Some(1)),
+            (dairy.id, Some(0), Some(0))
+        ],
+        counts
+    );
+
+    assert!(completed.completed);
+    let lean = serde_json::to_value(&lean).unwrap();
+    assert_eq!("Dairy", lean[1]["name"]);
+    assert!(lean[0].get("total_entries").is_none());
+    assert!(lean[0].get("completed_entries").is_none());
+    assert_eq!(StatusCode::BAD_REQUEST, unknown.status);
+}
+
+#[tokio::test]
+async fn test_completed_filter() {
+    let state = memory_state().await;
+
+    let milk = add_entry(&state, entry("milk")).await.unwrap();
+    let eggs = add_entry(&state, entry("eggs")).await.unwrap();
+    let bread = add_entry(&state, entry("bread")).await.unwrap();
+    let Json(completed) = toggle_entry(State(state.clone()), Path(milk.id))
+        .await
+        .unwrap();
+
+    let entries = |params: serde_json::Value| {
+        get_entries(
+            State(state.clone()),
+            Query(serde_json::from_value(params).unwrap()),
+        )
+    };
+    let body = |response: Response| async move {
+        axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap()
+    };
+    let ids = |body: &[u8]| -> Vec<i64> {
+        serde_json::from_slice::<Vec<ApiGroceryListEntry>>(body)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.id)
+            .collect()
+    };
+
+    let done = body(
+        entries(serde_json::json!({"completed": "true"}))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let outstanding = body(
+        entries(serde_json::json!({"completed": "false"}))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let all = body(entries(serde_json::json!({})).await.unwrap()).await;
+    let page = body(
+        entries(serde_json::json!({"completed": "false", "limit": 1}))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let invalid = entries(serde_json::json!({"completed": "yes"}))
+        .await
+        .unwrap_err();
+    let with_delta = entries(serde_json::json!({
+        "completed": "true",
+        "changed_since": "2026-01-02T15:04:05Z",
+    }))
+    .await
+    .unwrap_err();
+
+    state.pool.close().await;
+
+    assert!(completed.completed);
+    assert_eq!(vec![milk.id], ids(&done));
+    assert_eq!(vec![eggs.id, bread.id], ids(&outstanding));
+    assert_eq!(3, ids(&all).len());
+    let page: serde_json::Value = serde_json::from_slice(&page).unwrap();
+    assert_eq!(2, page["total"]);
+    assert_eq!(eggs.id, page["items"][0]["id"]);
+    assert_eq!(Some(1), page["next_offset"].as_i64());
+    assert_eq!(StatusCode::BAD_REQUEST, invalid.status);
+    assert_eq!(StatusCode::BAD_REQUEST, with_delta.status);
+}
+
+#[tokio::test]
+async fn test_changed_since_as_of() {
+    let state = memory_state().await;
+
+    let delta = |since: String| {
+        let state = state.clone();
+        async move {
+            let response = get_entries(
+                State(state),
+                Query(serde_json::from_value(serde_json::json!({"changed_since": since})).unwrap()),
+            )
+            .await
+            .unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        }
+    };
+
+    let milk = add_entry(&state, entry("milk")).await.unwrap();
+    let first = delta((Utc::now() - Duration::minutes(1)).to_rfc3339()).await;
+    // most likely within the same second as first's as_of, which still counts
+    let eggs = add_entry(&state, entry("eggs")).await.unwrap();
+    let second = delta(first["as_of"].as_str().unwrap().to_string()).await;
+    // in the same order as the list, pinned entries first
+    let Json(_) = toggle_pin(State(state.clone()), Path(eggs.id))
+        .await
+        .unwrap();
+    let pinned = delta((Utc::now() - Duration::minutes(1)).to_rfc3339()).await;
+
+    state.pool.close().await;
+
+    assert_eq!(milk.id, first["entries"][0]["id"]);
+    assert!(second["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|entry| entry["id"] == eggs.id));
+    assert!(second["as_of"].as_str().unwrap() >= first["as_of"].as_str().unwrap());
+    assert_eq!(eggs.id, pinned["entries"][0]["id"]);
+    assert_eq!(milk.id, pinned["entries"][1]["id"]);
+}
+
+#[tokio::test]
+async fn test_scheduled_entries() {
+    let state = memory_state().await;
+
+    let since = Utc::now() - Duration::minutes(1);
+    let milk = add_entry(&state, entry("milk")).await.unwrap();
+    let eggs = add_entry(
+        &state,
+        CreateGroceryListEntry {
+            visible_from: Some(Utc::now() + Duration::days(1)),
+            ..entry("eggs")
+        },
+    )
+    .await
+    .unwrap();
+    let bread = add_entry(
+        &state,
+        CreateGroceryListEntry {
+            visible_from: Some(Utc::now() - Duration::days(1)),
+            ..entry("bread")
+        },
+    )
+    .await
+    .unwrap();
+
+    let entries = |params: serde_json::Value| {
+        get_entries(
+            State(state.clone()),
+            Query(serde_json::from_value(params).unwrap()),
+        )
+    };
+    let body = |response: Response| async move {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+    };
+    let ids = |entries: &serde_json::Value| -> Vec<i64> {
+        entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["id"].as_i64().unwrap())
+            .collect()
+    };
+    let scheduled = || {
+        get_scheduled_entries(
+            State(state.clone()),
+            Query(serde_json::from_value(serde_json::json!({})).unwrap()),
+        )
+    };
+
+    let visible = body(entries(serde_json::json!({})).await.unwrap()).await;
+    let all = body(
+        entries(serde_json::json!({"include_scheduled": "true"}))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let page = body(entries(serde_json::json!({"limit": 10})).await.unwrap()).await;
+    let delta = body(
+        entries(serde_json::json!({"changed_since": since.to_rfc3339()}))
+            .await
+            .unwrap(),
+    )
+    .await;
+    let invalid = entries(serde_json::json!({"include_scheduled": "1"}))
+        .await
+        .unwrap_err();
+    let Json(upcoming) = scheduled().await.unwrap();
+
+    let Json(shown) = patch_entry(
+        State(state.clone()),
+        Path(eggs.id),
+        ValidJson(serde_json::from_value(serde_json::json!({"visible_from": null})).unwrap()),
+    )
+    .await
+    .unwrap();
+    let shown_visible = body(entries(serde_json::json!({})).await.unwrap()).await;
+    let Json(upcoming_after) = scheduled().await.unwrap();
+
+    state.pool.close().await;
+
+    assert_eq!(vec![milk.id, bread.id], ids(&visible));
+    assert_eq!(vec![milk.id, eggs.id, bread.id], ids(&all));
+    assert_eq!(2, page["total"]);
+    assert_eq!(vec![milk.id, bread.id], ids(&delta["entries"]));
+    assert_eq!(serde_json::json!([eggs.id]), delta["deleted_ids"]);
+    assert_eq!(StatusCode::BAD_REQUEST, invalid.status);
+    assert_eq!(
+        vec![eggs.id],
+        upcoming.iter().map(|entry| entry.id).collect::<Vec<_>>()
+    );
+    assert_eq!(eggs.visible_from, upcoming[0].visible_from);
+    assert_eq!(None, shown.visible_from);
+    assert_eq!(vec![milk.id, eggs.id, bread.id], ids(&shown_visible));
+    assert!(upcoming_after.is_empty());
+}
+
+#[tokio::test]
+async fn test_create_entries_on_one_connection() {
+    // everything create_entries looks up has to go through its transaction,
+    // or a pool of one connection waits on itself until the acquire timeout
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(std::time::Duration::from_secs(1))
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    let db = Database::from_pool(pool).await.unwrap();
+    let state = AppState::new(Arc::new(db), Config::default());
+
+    let created = state
+        .create_entries(vec![entry("milk"), entry("eggs")])
+        .await
+        .unwrap();
+
+    state.pool.close().await;
+
+    assert_eq!(2, created.len());
+    assert!(created
+        .iter()
+        .all(|entry| entry.category_id == database::DEFAULT_CATEGORY_ID));
+}
+
+#[tokio::test]
+async fn test_merge_entry() {
+    let state = memory_state().await;
+
+    let merge = |payload: CreateGroceryListEntry| {
+        create_entry(
+            State(state.clone()),
+            Query(serde_json::from_value(serde_json::json!({"merge": true})).unwrap()),
+            HeaderMap::new(),
+            ValidJson(payload),
+        )
+    };
+    let body = |response: Response| async move {
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (
+            status,
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+        )
+    };
+
+    let milk = add_entry(&state, entry("2 milk")).await.unwrap();
+    let (merged_status, merged) = body(merge(entry("3 milk")).await.unwrap()).await;
+
+    let eggs = add_entry(&state, entry("eggs")).await.unwrap();
+    let Json(_) = toggle_entry(State(state.clone()), Path(eggs.id))
+        .await
+        .unwrap();
+    let (done_status, done) = body(merge(entry("eggs")).await.unwrap()).await;
+
+    // quantities from before units were split out keep theirs attached
+    let flour = state
+        .create_entries(vec![CreateGroceryListEntry {
+            quantity: Some("2lb".to_string()),
+            ..entry("flour")
+        }])
+        .await
+        .unwrap()
+        .remove(0);
+    let (unsplit_status, unsplit) = body(merge(entry("flour")).await.unwrap()).await;
+
+    let bread = add_entry(
+        &state,
+        CreateGroceryListEntry {
+            visible_from: Some(Utc::now() + Duration::days(1)),
+            ..entry("bread")
+        },
+    )
+    .await
+    .unwrap();
+    let (scheduled_status, scheduled) = body(merge(entry("bread")).await.unwrap()).await;
+
+    state.pool.close().await;
+
+    assert_eq!(StatusCode::OK, merged_status);
+    assert_eq!(milk.id, merged["id"]);
+    assert_eq!("5", merged["quantity"]);
+    for (status, added, existing) in [
+        (done_status, done, eggs.id),
+        (unsplit_status, unsplit, flour.id),
+        (scheduled_status, scheduled, bread.id),
+    ] {
+        assert_eq!(StatusCode::CREATED, status);
+        assert_ne!(existing, added["id"]);
+    }
+}
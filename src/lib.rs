@@ -0,0 +1,1246 @@
+mod auth;
+pub mod config;
+pub mod database;
+mod error;
+mod handlers;
+mod models;
+mod openapi;
+mod rate_limit;
+pub mod state;
+pub mod telemetry;
+#[cfg(test)]
+mod test_support;
+mod timeout;
+mod timestamp;
+mod validation;
+mod write_limit;
+
+use anyhow::Context;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, DefaultBodyLimit, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
+    middleware,
+    response::{Html, IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
+    Json, Router,
+};
+use metrics_exporter_prometheus::PrometheusHandle;
+use rust_embed::Embed;
+use sqlx::{sqlite::SqliteConnectOptions, Acquire, Connection, SqliteConnection, SqlitePool};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::string::FromUtf8Error;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::time::interval;
+use tower_http::{
+    compression::{
+        predicate::{NotForContentType, Predicate},
+        CompressionLayer, DefaultPredicate,
+    },
+    cors::CorsLayer,
+    limit::RequestBodyLimitLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
+
+use auth::ApiToken;
+use database::Database;
+use error::ApiError;
+use handlers::{
+    add_entry_tag, apply_template, archive_list, category, create_category, create_entries,
+    create_entry, create_list, create_template, delete_category, delete_completed_entries,
+    delete_entry, delete_list, duplicate_category, export, export_csv, get_archives, get_audit_log,
+    get_categories, get_category, get_cost_summary, get_entries, get_entry, get_entry_history,
+    get_list, get_lists, get_scheduled_entries, get_shared_list, get_summary, get_templates,
+    get_trash, grocery, import, move_entry, move_entry_down, move_entry_up, patch_entry,
+    remove_entry_tag, reorder_categories, reorder_entries, reset_entry_order, restore_archive,
+    restore_entry, search_entries, share_list, sort_categories, stream_events, subscribe_events,
+    toggle_entry, toggle_pin, unshare_list, update_category, update_entries, update_entry,
+    update_list,
+};
+use rate_limit::RateLimiter;
+use state::{AppState, DemoResetStatus};
+use write_limit::WriteLimit;
+
+static INDEX_HTML: &str = "index.html";
+
+/// API_BASE_PATH is where the api is served from, which the frontend is told
+/// through index.html
+static API_BASE_PATH: &str = "/api";
+
+/// HASHED_ASSETS_DIR is where vite writes build output with a content hash in
+/// the filename, which makes those files safe to cache forever
+static HASHED_ASSETS_DIR: &str = "assets/";
+
+/// VERSION, GIT_COMMIT and BUILD_TIMESTAMP identify the build that is running.
+/// The commit and timestamp, in seconds since the epoch, are set by build.rs.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const GIT_COMMIT: &str = env!("GL_GIT_COMMIT");
+const BUILD_TIMESTAMP: &str = env!("GL_BUILD_TIMESTAMP");
+
+/// TRASH_PURGE_INTERVAL_SECS is how often entries past their trash retention
+/// are permanently deleted
+const TRASH_PURGE_INTERVAL_SECS: u64 = 60 * 60;
+
+#[derive(Embed)]
+#[folder = "./ts/dist"]
+struct Assets;
+
+/// build_router is the whole app: the api, the frontend, and the layers the
+/// config asks for around them. /metrics is served when there is a
+/// metrics_handle, which is separate from the state since the recorder it
+/// reads from can only be installed once per process.
+pub fn build_router(state: AppState, metrics_handle: Option<PrometheusHandle>) -> Router {
+    let config = state.config();
+    let cors = cors_layer(config.cors_origins.clone());
+    let rate_limiter = (config.rate_limit_per_minute > 0)
+        .then(|| RateLimiter::new(config.rate_limit_per_minute, config.trust_proxy));
+    let api_token = config
+        .api_token
+        .clone()
+        .map(|token| ApiToken::new(token, config.api_token_protects_reads));
+    let write_limit =
+        (config.max_concurrent_writes > 0).then(|| WriteLimit::new(config.max_concurrent_writes));
+    let request_timeout = config.request_timeout;
+    let max_body_bytes = config.max_body_bytes;
+
+    let app = Router::new()
+        .fallback(static_handler)
+        .route("/api/entries", get(get_entries))
+        .route("/api/entries", post(create_entry))
+        .route("/api/entries/bulk", post(create_entries))
+        .route("/api/entries/batch", patch(update_entries))
+        .route("/api/entries/scheduled", get(get_scheduled_entries))
+        .route("/api/entries/:id", get(get_entry))
+        .route("/api/entries/:id", put(update_entry))
+        .route("/api/entries/:id", patch(patch_entry))
+        .route("/api/entries/:id", delete(delete_entry))
+        .route("/api/entries/:id/complete", patch(toggle_entry))
+        .route("/api/entries/:id/pin", post(toggle_pin))
+        .route("/api/entries/:id/restore", post(restore_entry))
+        .route("/api/entries/:id/move", put(move_entry))
+        .route("/api/entries/:id/move-up", post(move_entry_up))
+        .route("/api/entries/:id/move-down", post(move_entry_down))
+        .route("/api/entries/:id/tags", post(add_entry_tag))
+        .route("/api/entries/:id/tags/:tag", delete(remove_entry_tag))
+        // axum needs the same name for the segment as the routes above, but
+        // here it is the name of the item rather than an entry id
+        .route("/api/entries/:id/history", get(get_entry_history))
+        .route("/api/entries/trash", get(get_trash))
+        .route("/api/entries/completed", delete(delete_completed_entries))
+        .route("/api/entries/reorder", put(reorder_entries))
+        .route("/api/entries/suggestions", get(grocery::get_suggestions))
+        .route("/api/entries/search", get(search_entries))
+        .route("/api/categories", get(get_categories))
+        .route("/api/categories", post(create_category))
+        .route("/api/categories/:id", get(get_category))
+        .route("/api/categories/:id", put(update_category))
+        .route("/api/categories/:id", delete(delete_category))
+        .route("/api/categories/reorder", put(reorder_categories))
+        .route("/api/categories/sort-alpha", put(sort_categories))
+        .route("/api/categories/:id/reset-order", put(reset_entry_order))
+        .route("/api/categories/:id/duplicate", post(duplicate_category))
+        .route(
+            "/api/categories/suggestions",
+            get(category::get_suggestions),
+        )
+        .route("/api/lists", get(get_lists))
+        .route("/api/lists", post(create_list))
+        .route("/api/lists/:id", get(get_list))
+        .route("/api/lists/:id", put(update_list))
+        .route("/api/lists/:id", delete(delete_list))
+        .route("/api/lists/:id/archive", post(archive_list))
+        .route("/api/lists/:id/share", post(share_list))
+        .route("/api/lists/:id/share", delete(unshare_list))
+        .route("/api/shared/:token", get(get_shared_list))
+        .route("/api/archives", get(get_archives))
+        .route("/api/archives/:id/restore", post(restore_archive))
+        .route("/api/templates", get(get_templates))
+        .route("/api/templates", post(create_template))
+        .route("/api/templates/:id/apply", post(apply_template))
+        .route("/api/audit", get(get_audit_log))
+        .route("/api/summary", get(get_summary))
+        .route("/api/summary/cost", get(get_cost_summary))
+        .route("/api/export", get(export))
+        .route("/api/export.csv", get(export_csv))
+        .route("/api/import", post(import))
+        .route("/api/ws", get(subscribe_events))
+        .route("/api/events", get(stream_events))
+        .route("/health", get(health_check))
+        .route("/ready", get(readiness_check))
+        .route("/api/version", get(version))
+        .route("/api/demo/reset", post(demo_reset))
+        .route("/api/demo/status", get(demo_status))
+        .route("/api/openapi.json", get(openapi::openapi_json))
+        .route("/api/docs", get(openapi::swagger_ui))
+        .with_state(state);
+
+    let app = match metrics_handle {
+        Some(handle) => app
+            .route("/metrics", get(telemetry::metrics).with_state(handle))
+            .route_layer(middleware::from_fn(telemetry::track_requests)),
+        None => app,
+    };
+
+    // writes queue inside the token check so that requests without the token
+    // are turned away rather than taking up a slot
+    let app = match write_limit {
+        Some(limit) => app.layer(middleware::from_fn_with_state(
+            limit,
+            write_limit::limit_writes,
+        )),
+        None => app,
+    };
+
+    // the token is checked inside the rate limit so guessing it is throttled
+    let app = match api_token {
+        Some(token) => app.layer(middleware::from_fn_with_state(token, auth::require_token)),
+        None => app,
+    };
+
+    // layers run outside in, from the last added, so the request id is set
+    // before the trace span is opened and copied onto the response after
+    let app = match rate_limiter {
+        Some(limiter) => app.layer(middleware::from_fn_with_state(
+            limiter,
+            rate_limit::limit_requests,
+        )),
+        None => app,
+    };
+
+    // the timeout runs inside the trace span so timed out requests are logged
+    // with their request id, and inside cors so the 503 still gets its headers
+    app.layer(middleware::from_fn_with_state(
+        request_timeout,
+        timeout::limit_duration,
+    ))
+    .layer(body_limit_layer(max_body_bytes))
+    .layer(middleware::from_fn(telemetry::scope_request_id))
+    .layer(TraceLayer::new_for_http().make_span_with(telemetry::make_request_span))
+    .layer(PropagateRequestIdLayer::x_request_id())
+    .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+    .layer(compression_layer())
+    .layer(cors)
+}
+
+/// compression_layer gzip or brotli compresses responses for clients that
+/// accept it. Content that is already compressed, like images and woff2
+/// fonts, is sent as is, and so are event streams, which compression would
+/// hold back until a buffer filled.
+fn compression_layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(
+        DefaultPredicate::new()
+            .and(NotForContentType::const_new("font/woff2"))
+            .and(NotForContentType::const_new("application/zip"))
+            .and(NotForContentType::const_new("application/gzip")),
+    )
+}
+
+/// body_limit_layer rejects request bodies over max_bytes with 413. It
+/// replaces the limit axum puts on extractors by default, so that the
+/// configured limit is the only one.
+fn body_limit_layer(max_bytes: usize) -> (DefaultBodyLimit, RequestBodyLimitLayer) {
+    (
+        DefaultBodyLimit::disable(),
+        RequestBodyLimitLayer::new(max_bytes),
+    )
+}
+
+/// cors_layer builds the CORS policy from a comma separated list of allowed
+/// origins, e.g. `https://gl.example.com,http://localhost:5173`. When no list
+/// is configured every origin is allowed.
+fn cors_layer(origins: Option<String>) -> CorsLayer {
+    let Some(origins) = origins else {
+        tracing::info!("GL_CORS_ORIGINS is not set, allowing all origins");
+        return CorsLayer::permissive();
+    };
+
+    let allowed_origins: Vec<HeaderValue> = origins
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .filter_map(|origin| {
+            let is_origin = origin
+                .parse::<Uri>()
+                .is_ok_and(|uri| uri.scheme().is_some() && uri.authority().is_some());
+
+            match HeaderValue::from_str(origin) {
+                Ok(value) if is_origin => Some(value),
+                _ => {
+                    tracing::warn!("Skipping invalid CORS origin '{}'", origin);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    tracing::info!("Allowing CORS origins: {:?}", allowed_origins);
+
+    CorsLayer::new()
+        .allow_origin(allowed_origins)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static(handlers::IDEMPOTENCY_KEY),
+        ])
+}
+
+/// shutdown_signal completes when the process is asked to stop, either by
+/// ctrl-c or by SIGTERM (e.g. from a container runtime)
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install ctrl-c handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("shutting down gracefully");
+}
+
+/// health_check is the liveness probe. It only shows that the process is up
+/// and serving requests, so it never touches the database. The demo also
+/// fails it once its reset task has stopped, since a restart is what brings
+/// that back.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "meta",
+    responses(
+        (status = 200, description = "the process is up"),
+        (status = 503, description = "the demo reset task has stopped"),
+    ),
+)]
+async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    if state.is_demo() && !state.demo_reset_status().task_running {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(with_timestamp(
+                &state,
+                serde_json::json!({
+                    "status": "demo reset task stopped",
+                    "check": "liveness",
+                    "description": "the demo database is no longer reset on schedule",
+                }),
+            )),
+        );
+    }
+
+    (
+        StatusCode::OK,
+        Json(with_timestamp(
+            &state,
+            serde_json::json!({
+                "status": "alive",
+                "check": "liveness",
+                "description": "the process is running; see /ready for whether it can reach the database",
+            }),
+        )),
+    )
+}
+
+/// with_timestamp adds the time to a probe's body, in UTC and also in local
+/// time when GL_TZ is set
+fn with_timestamp(state: &AppState, mut body: serde_json::Value) -> serde_json::Value {
+    let now = chrono::Utc::now();
+    body["timestamp"] = timestamp::format(now).into();
+    if let Some(local) = timestamp::local(now, state.config().timezone) {
+        body["timestamp_local"] = serde_json::json!(local);
+    }
+    body
+}
+
+/// version reports which build is running, to confirm that a deploy rolled
+/// out
+#[utoipa::path(
+    get,
+    path = "/api/version",
+    tag = "meta",
+    responses(
+        (status = 200, description = "the version, commit and build time of the running build"),
+    ),
+)]
+async fn version() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "version": VERSION,
+        "commit": GIT_COMMIT,
+        "built_at": built_at(),
+    }))
+}
+
+/// built_at is when the running build was made, as an RFC 3339 timestamp
+pub fn built_at() -> String {
+    BUILD_TIMESTAMP
+        .parse()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(timestamp::format)
+        .unwrap_or_default()
+}
+
+/// readiness_check is the readiness probe. It fails with 503 while the
+/// database can't be reached, so traffic is held back until it can.
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "meta",
+    responses(
+        (status = 200, description = "the database is reachable"),
+        (status = 503, description = "the database can't be reached"),
+    ),
+)]
+async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<serde_json::Value>) {
+    let (status_code, status, database) = match state.ping().await {
+        Ok(()) => (StatusCode::OK, "ready", "up"),
+        Err(e) => {
+            tracing::error!("Readiness check failed to reach database: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, "not ready", "down")
+        }
+    };
+
+    (
+        status_code,
+        Json(with_timestamp(
+            &state,
+            serde_json::json!({
+                "status": status,
+                "check": "readiness",
+                "description": "whether the database is reachable; see /health for whether the process is alive",
+                "database": database,
+            }),
+        )),
+    )
+}
+
+/// demo_reset resets the demo database on demand, rather than waiting for the
+/// next scheduled reset
+#[utoipa::path(
+    post,
+    path = "/api/demo/reset",
+    tag = "meta",
+    responses(
+        (status = 200, description = "the demo database was reset"),
+        (status = 403, description = "gl isn't running as the demo"),
+    ),
+)]
+async fn demo_reset(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    if !state.is_demo() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let forwarded_for = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("-");
+    tracing::info!(
+        "manual demo reset triggered by {} (x-forwarded-for: {})",
+        addr,
+        forwarded_for
+    );
+
+    let result = reset_database(&state.pool, &state.config().demo_db_path)
+        .await
+        .map_err(|e| e.to_string());
+    if let Err(e) = &result {
+        tracing::error!("Failed to reset database: {}", e);
+    }
+    state.record_demo_reset(result.clone());
+
+    result
+        .map(|()| StatusCode::OK)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// demo_status reports when the demo database was last reset, whether the
+/// last reset failed and when the next one is due
+#[utoipa::path(
+    get,
+    path = "/api/demo/status",
+    tag = "meta",
+    responses(
+        (status = 200, description = "how the demo resets have gone", body = DemoResetStatus),
+        (status = 403, description = "gl isn't running as the demo"),
+    ),
+)]
+async fn demo_status(State(state): State<AppState>) -> Result<Json<DemoResetStatus>, StatusCode> {
+    if !state.is_demo() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(Json(state.demo_reset_status()))
+}
+
+/// static_handler serves the frontend for every path that isn't a route, with
+/// index.html standing in for the frontend's own routes. Unknown /api paths
+/// are a 404 error rather than the frontend, so api clients can tell. A HEAD
+/// request gets the same headers as a GET, without the body. With a
+/// static_dir configured the frontend is served from there instead.
+async fn static_handler(
+    State(state): State<AppState>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let path = uri.path().trim_start_matches('/');
+
+    if path == "api" || path.starts_with("api/") {
+        return ApiError::not_found(format!("no api route at {}", uri.path())).into_response();
+    }
+
+    if let Some(dir) = &state.config().static_dir {
+        return serve_static_dir(dir, state.is_demo(), method, uri, headers).await;
+    }
+
+    if path.is_empty() || path == INDEX_HTML {
+        return index_html(state.is_demo()).await;
+    }
+
+    match Assets::get(path) {
+        Some(content) => {
+            let mime = mime_guess::from_path(path).first_or_octet_stream();
+            let etag = etag(&content.metadata.sha256_hash());
+            let cache_control = if path.starts_with(HASHED_ASSETS_DIR) {
+                "public, max-age=31536000, immutable"
+            } else {
+                "no-cache"
+            };
+
+            if etag_matches(&headers, &etag) {
+                return (
+                    StatusCode::NOT_MODIFIED,
+                    [
+                        (header::ETAG, etag.as_str()),
+                        (header::CACHE_CONTROL, cache_control),
+                    ],
+                )
+                    .into_response();
+            }
+
+            let content_length = content.data.len().to_string();
+            let headers = [
+                (header::CONTENT_TYPE, mime.as_ref()),
+                (header::CONTENT_LENGTH, content_length.as_str()),
+                (header::ETAG, etag.as_str()),
+                (header::CACHE_CONTROL, cache_control),
+            ];
+
+            if method == Method::HEAD {
+                return headers.into_response();
+            }
+            (headers, content.data).into_response()
+        }
+        None => {
+            if path.contains('.') {
+                return not_found().await;
+            }
+
+            index_html(state.is_demo()).await
+        }
+    }
+}
+
+/// serve_static_dir serves the frontend from dir for static_handler. Files are
+/// read on every request, and index.html stands in for the frontend's routes
+/// like it does for the embedded frontend.
+async fn serve_static_dir(
+    dir: &Path,
+    is_demo: bool,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+) -> Response {
+    let path = uri.path().trim_start_matches('/');
+    if path.is_empty() || path == INDEX_HTML {
+        return static_dir_index_html(dir, is_demo).await;
+    }
+
+    let mut request = axum::http::Request::new(Body::empty());
+    *request.method_mut() = method;
+    *request.uri_mut() = uri.clone();
+    *request.headers_mut() = headers;
+
+    let response = match ServeDir::new(dir).try_call(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::error!("Failed to serve {} from {}: {}", path, dir.display(), e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    if response.status() == StatusCode::NOT_FOUND && !path.contains('.') {
+        return static_dir_index_html(dir, is_demo).await;
+    }
+
+    response.map(Body::new).into_response()
+}
+
+/// static_dir_index_html serves index.html from dir with its placeholders
+/// filled in, reading it afresh each time so changes show up straight away
+async fn static_dir_index_html(dir: &Path, is_demo: bool) -> Response {
+    match tokio::fs::read_to_string(dir.join(INDEX_HTML)).await {
+        Ok(template) => (
+            [(header::CACHE_CONTROL, "no-cache")],
+            Html(render_index(&template, &index_placeholders(is_demo))),
+        )
+            .into_response(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => not_found().await,
+        Err(e) => {
+            tracing::error!(
+                "Failed to read {} from {}: {}",
+                INDEX_HTML,
+                dir.display(),
+                e
+            );
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// index_html serves index.html with its placeholders filled in. That is only
+/// done for the first request, since the values can't change while gl is
+/// running.
+async fn index_html(is_demo: bool) -> Response {
+    static INDEX: OnceLock<Option<Result<String, FromUtf8Error>>> = OnceLock::new();
+
+    let index = INDEX.get_or_init(|| {
+        Assets::get(INDEX_HTML).map(|content| {
+            String::from_utf8(content.data.to_vec())
+                .map(|template| render_index(&template, &index_placeholders(is_demo)))
+        })
+    });
+
+    match index {
+        // index.html is templated per instance, so always revalidate it
+        Some(Ok(index)) => {
+            ([(header::CACHE_CONTROL, "no-cache")], Html(index.as_str())).into_response()
+        }
+        Some(Err(e)) => {
+            tracing::error!("index.html is not valid utf-8: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        None => not_found().await,
+    }
+}
+
+/// index_placeholders are what the placeholders in index.html are replaced
+/// with, which is the runtime config the frontend reads from
+/// window.APP_CONFIG. The values are JavaScript literals.
+fn index_placeholders(is_demo: bool) -> [(&'static str, String); 3] {
+    let string_literal = |value: &str| serde_json::Value::from(value).to_string();
+
+    [
+        ("__IS_DEMO__", is_demo.to_string()),
+        ("__VERSION__", string_literal(VERSION)),
+        ("__API_BASE__", string_literal(API_BASE_PATH)),
+    ]
+}
+
+/// render_index replaces each placeholder in the index.html template with its
+/// value
+fn render_index(template: &str, placeholders: &[(&str, String)]) -> String {
+    placeholders
+        .iter()
+        .fold(template.to_string(), |index, (placeholder, value)| {
+            index.replace(placeholder, value)
+        })
+}
+
+/// etag builds a strong ETag from an asset's content hash
+fn etag(hash: &[u8]) -> String {
+    let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("\"{}\"", hex)
+}
+
+/// etag_matches checks whether the request's If-None-Match header lists the
+/// given ETag, i.e. whether the client already has this version of the asset
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+async fn not_found() -> Response {
+    (StatusCode::NOT_FOUND, "404").into_response()
+}
+
+/// Resets the database by executing SQL commands to clear and repopulate data
+async fn reset_database(
+    pool: &SqlitePool,
+    demo_db_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut pool = pool.acquire().await?;
+    // Attach the demo database
+    sqlx::query("ATTACH DATABASE ? AS demo")
+        .bind(demo_db_path.to_str().unwrap())
+        .execute(&mut *pool)
+        .await?;
+
+    // Get all table names from the main database
+    let tables: Vec<(String,)> = sqlx::query_as(
+        "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
+    )
+    .fetch_all(&mut *pool)
+    .await?;
+
+    // Begin transaction
+    let mut tx = pool.begin().await?;
+
+    // Delete all data from each table
+    for (table_name,) in &tables {
+        let delete_sql = format!("DELETE FROM main.{}", table_name);
+        sqlx::query(&delete_sql).execute(&mut *tx).await?;
+    }
+
+    // Copy data from demo database to main database
+    for (table_name,) in &tables {
+        let insert_sql = format!(
+            "INSERT INTO main.{} SELECT * FROM demo.{}",
+            table_name, table_name
+        );
+        sqlx::query(&insert_sql).execute(&mut *tx).await?;
+    }
+
+    // Commit transaction
+    tx.commit().await?;
+
+    // Detach the demo database
+    sqlx::query("DETACH DATABASE demo")
+        .execute(&mut *pool)
+        .await?;
+
+    // Run VACUUM to clean up and reset the database file
+    sqlx::query("VACUUM").execute(&mut *pool).await?;
+
+    tracing::debug!(
+        "Database reset completed at {}",
+        timestamp::format(chrono::Utc::now())
+    );
+
+    Ok(())
+}
+
+/// check_demo_database makes sure the seed database can be read and has the
+/// same tables, with the same columns in the same order, as the main one.
+/// Resets copy every table across with `SELECT *`, so a seed that has fallen
+/// behind the migrations would only fail once the first reset runs.
+pub async fn check_demo_database(pool: &SqlitePool, demo_db_path: &Path) -> anyhow::Result<()> {
+    let options = SqliteConnectOptions::new()
+        .filename(demo_db_path)
+        .read_only(true);
+    let mut seed = SqliteConnection::connect_with(&options)
+        .await
+        .with_context(|| format!("can't open demo database {}", demo_db_path.display()))?;
+    let seed_tables = table_columns(&mut seed)
+        .await
+        .with_context(|| format!("can't read demo database {}", demo_db_path.display()))?;
+    seed.close().await?;
+
+    let mut conn = pool.acquire().await?;
+    let tables = table_columns(&mut conn).await?;
+
+    let mismatched: Vec<&str> = tables
+        .iter()
+        .filter(|(table, columns)| seed_tables.get(*table) != Some(*columns))
+        .map(|(table, _)| table.as_str())
+        .collect();
+    if !mismatched.is_empty() {
+        anyhow::bail!(
+            "demo database {} doesn't match the schema, these tables differ: {}",
+            demo_db_path.display(),
+            mismatched.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// table_columns gets the columns of each table in the database, in order
+async fn table_columns(
+    conn: &mut SqliteConnection,
+) -> anyhow::Result<BTreeMap<String, Vec<String>>> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT m.name, p.name FROM sqlite_master m JOIN pragma_table_info(m.name) p
+        WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'
+        ORDER BY m.name, p.cid",
+    )
+    .fetch_all(conn)
+    .await?;
+
+    let mut tables: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (table, column) in rows {
+        tables.entry(table).or_default().push(column);
+    }
+    Ok(tables)
+}
+
+/// Spawns a background task that resets the database every demo_reset_interval,
+/// recording how each reset went in the state
+pub fn spawn_database_reset_task(state: AppState) -> tokio::task::JoinHandle<()> {
+    spawn_reset_loop(state, |state| async move {
+        reset_database(&state.pool, &state.config().demo_db_path)
+            .await
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// spawn_reset_loop runs reset every demo_reset_interval. Each reset runs as
+/// its own task, so one that panics is recorded as a failed reset instead of
+/// taking the schedule down with it.
+fn spawn_reset_loop<F, Fut>(state: AppState, reset: F) -> tokio::task::JoinHandle<()>
+where
+    F: Fn(AppState) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(), String>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let _running = ResetTaskRunning::new(state.clone());
+        let reset_interval = state.config().demo_reset_interval;
+        let mut ticker = interval(reset_interval);
+
+        loop {
+            ticker.tick().await;
+            if let Ok(reset_interval) = chrono::Duration::from_std(reset_interval) {
+                state.schedule_demo_reset(chrono::Utc::now() + reset_interval);
+            }
+
+            tracing::debug!("Starting database reset...");
+
+            let result = match tokio::spawn(reset(state.clone())).await {
+                Ok(result) => result,
+                Err(e) if e.is_panic() => Err(format!("reset panicked: {}", panic_message(e))),
+                Err(e) => Err(format!("reset was cancelled: {}", e)),
+            };
+            if let Err(e) = &result {
+                tracing::error!("Failed to reset database: {}", e);
+            }
+            state.record_demo_reset(result);
+        }
+    })
+}
+
+/// ResetTaskRunning marks the scheduled reset task as running for as long as
+/// it is alive, however it ends
+struct ResetTaskRunning(AppState);
+
+impl ResetTaskRunning {
+    fn new(state: AppState) -> Self {
+        state.set_demo_reset_task_running(true);
+        Self(state)
+    }
+}
+
+impl Drop for ResetTaskRunning {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            tracing::error!("the demo reset task panicked, the demo database won't be reset");
+        }
+        self.0.set_demo_reset_task_running(false);
+    }
+}
+
+/// panic_message is what a task panicked with, when it was a string
+fn panic_message(e: tokio::task::JoinError) -> String {
+    let payload = e.into_panic();
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// spawn_trash_purge_task periodically removes entries that have been in the
+/// trash for longer than the retention period, along with idempotency keys
+/// that have expired
+pub fn spawn_trash_purge_task(db: Arc<Database>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(TRASH_PURGE_INTERVAL_SECS));
+
+        loop {
+            ticker.tick().await;
+
+            if let Err(e) = db.purge_deleted_entries().await {
+                tracing::error!("Failed to purge deleted entries: {}", e);
+            }
+            if let Err(e) = db.purge_expired_idempotency_keys().await {
+                tracing::error!("Failed to purge expired idempotency keys: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{memory_db, memory_state, memory_state_with, TempPath};
+    use axum::body::Body;
+    use config::Config;
+    use tower::Service;
+
+    #[test]
+    fn test_render_index() {
+        let template = "window.APP_CONFIG = { isDemo: __IS_DEMO__, version: __VERSION__, apiBase: __API_BASE__ };";
+        assert_eq!(
+            format!(
+                "window.APP_CONFIG = {{ isDemo: true, version: \"{}\", apiBase: \"/api\" }};",
+                VERSION
+            ),
+            render_index(template, &index_placeholders(true))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_demo_database() {
+        let (path, seed_path) = (TempPath::new("check-main"), TempPath::new("check-seed"));
+        let open = |path: &TempPath| {
+            let url = path.sqlite_url();
+            async move {
+                Database::new(
+                    &url,
+                    Duration::from_millis(database::DEFAULT_BUSY_TIMEOUT_MS),
+                )
+                .await
+                .unwrap()
+            }
+        };
+        let db = open(&path).await;
+        let seed = open(&seed_path).await;
+
+        let missing = check_demo_database(&db.pool, &TempPath::new("check-missing")).await;
+        let matching = check_demo_database(&db.pool, &seed_path).await;
+        sqlx::query("ALTER TABLE tags ADD COLUMN color TEXT")
+            .execute(&seed.pool)
+            .await
+            .unwrap();
+        let behind = check_demo_database(&db.pool, &seed_path).await;
+
+        db.pool.close().await;
+        seed.pool.close().await;
+
+        assert!(missing.is_err());
+        assert!(matching.is_ok());
+        let behind = behind.unwrap_err().to_string();
+        assert!(behind.ends_with("these tables differ: tags"), "{}", behind);
+    }
+
+    #[tokio::test]
+    async fn test_database_connect_retries() {
+        let dir = TempPath::new("retry");
+        let url = format!("sqlite://{}", dir.join("grocery.db").display());
+        let retrying = |connect_attempts| database::PoolConfig {
+            connect_attempts,
+            connect_retry_delay: Duration::from_millis(10),
+            ..database::PoolConfig::default()
+        };
+        let busy_timeout = Duration::from_millis(database::DEFAULT_BUSY_TIMEOUT_MS);
+
+        // the directory doesn't exist, like a volume that is never mounted
+        let started = tokio::time::Instant::now();
+        let missing = Database::with_pool(&url, busy_timeout, retrying(3)).await;
+        let waited = started.elapsed();
+
+        // and here it shows up while the retries are still going
+        let mount = tokio::spawn({
+            let dir = dir.to_path_buf();
+            async move {
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                std::fs::create_dir_all(&dir).unwrap();
+            }
+        });
+        let mounted = Database::with_pool(&url, busy_timeout, retrying(10)).await;
+        mount.await.unwrap();
+
+        if let Ok(db) = &mounted {
+            db.pool.close().await;
+        }
+
+        assert!(missing.is_err());
+        // waiting 10ms and then 20ms between the three attempts
+        assert!(waited >= Duration::from_millis(30), "{:?}", waited);
+        assert!(mounted.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_reset_loop_survives_panics() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let config = Config {
+            is_demo: true,
+            demo_reset_interval: Duration::from_millis(10),
+            ..Config::default()
+        };
+        let state = memory_state_with(config).await;
+
+        let resets = Arc::new(AtomicUsize::new(0));
+        let handle = spawn_reset_loop(state.clone(), {
+            let resets = resets.clone();
+            move |_| {
+                let attempt = resets.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if attempt == 0 {
+                        panic!("demo database is corrupt");
+                    }
+                    Ok(())
+                }
+            }
+        });
+
+        for _ in 0..100 {
+            if state.demo_reset_status().last_reset_at.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let status = state.demo_reset_status();
+        assert!(status.task_running);
+        assert!(status.last_failed_at.is_some());
+        assert_eq!(
+            Some("reset panicked: demo database is corrupt".to_string()),
+            status.last_error
+        );
+        assert!(status.last_reset_at.is_some());
+        assert_eq!(StatusCode::OK, health_check(State(state.clone())).await.0);
+
+        handle.abort();
+        let _ = handle.await;
+        assert!(!state.demo_reset_status().task_running);
+        assert_eq!(
+            StatusCode::SERVICE_UNAVAILABLE,
+            health_check(State(state.clone())).await.0
+        );
+
+        state.pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_probe_timestamps() {
+        let db = Arc::new(memory_db().await);
+        let state = AppState::new(db.clone(), Config::default());
+        let in_tokyo = AppState::new(
+            db,
+            Config {
+                timezone: Some(chrono_tz::Asia::Tokyo),
+                ..Config::default()
+            },
+        );
+
+        let (_, Json(utc)) = health_check(State(state.clone())).await;
+        let (_, Json(local)) = readiness_check(State(in_tokyo)).await;
+
+        state.pool.close().await;
+
+        let timestamp = utc["timestamp"].as_str().unwrap();
+        assert!(timestamp.ends_with('Z'), "{}", timestamp);
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+        assert!(utc.get("timestamp_local").is_none());
+
+        assert!(local["timestamp"].as_str().unwrap().ends_with('Z'));
+        let timestamp_local = local["timestamp_local"].as_str().unwrap();
+        assert!(timestamp_local.ends_with("+09:00"), "{}", timestamp_local);
+        assert!(built_at().is_empty() || built_at().ends_with('Z'));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_api_paths_are_not_found() {
+        let state = memory_state().await;
+
+        let fallback = |uri: &'static str| {
+            static_handler(
+                State(state.clone()),
+                Method::GET,
+                Uri::from_static(uri),
+                HeaderMap::new(),
+            )
+        };
+        let typo = fallback("/api/entrys").await.into_response();
+        let bare = fallback("/api").await.into_response();
+        let page = fallback("/lists/2").await.into_response();
+        let lookalike = fallback("/apiary").await.into_response();
+
+        state.pool.close().await;
+
+        assert_eq!(StatusCode::NOT_FOUND, typo.status());
+        assert_eq!(
+            "application/json",
+            typo.headers()[header::CONTENT_TYPE].to_str().unwrap()
+        );
+        let body = axum::body::to_bytes(typo.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("not_found", body["error"]["code"]);
+        assert_eq!(StatusCode::NOT_FOUND, bare.status());
+
+        for response in [page, lookalike] {
+            assert_eq!(StatusCode::OK, response.status());
+            assert!(response.headers()[header::CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/html"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_head_static_asset() {
+        let state = memory_state().await;
+
+        let asset = Assets::iter()
+            .find(|name| name.as_ref() != INDEX_HTML)
+            .expect("the frontend has been built");
+        let uri: Uri = format!("/{}", asset).parse().unwrap();
+        let get = static_handler(
+            State(state.clone()),
+            Method::GET,
+            uri.clone(),
+            HeaderMap::new(),
+        )
+        .await
+        .into_response();
+        let head = static_handler(State(state.clone()), Method::HEAD, uri, HeaderMap::new())
+            .await
+            .into_response();
+
+        state.pool.close().await;
+
+        let length = Assets::get(&asset).unwrap().data.len().to_string();
+        assert_eq!(StatusCode::OK, head.status());
+        for name in [
+            header::CONTENT_TYPE,
+            header::CONTENT_LENGTH,
+            header::ETAG,
+            header::CACHE_CONTROL,
+        ] {
+            assert_eq!(get.headers()[&name], head.headers()[&name]);
+        }
+        assert_eq!(
+            length,
+            get.headers()[header::CONTENT_LENGTH].to_str().unwrap()
+        );
+        let get_body = axum::body::to_bytes(get.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let head_body = axum::body::to_bytes(head.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(length, get_body.len().to_string());
+        assert!(head_body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_static_dir() {
+        let dir = TempPath::new("static");
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(
+            dir.join(INDEX_HTML),
+            "<script>isDemo = __IS_DEMO__</script>",
+        )
+        .unwrap();
+        std::fs::write(dir.join("assets/app.js"), "console.log('dev')").unwrap();
+
+        let config = Config {
+            static_dir: Some(dir.to_path_buf()),
+            ..Config::default()
+        };
+        let state = memory_state_with(config).await;
+
+        let serve = |method: Method, uri: &'static str| {
+            static_handler(
+                State(state.clone()),
+                method,
+                Uri::from_static(uri),
+                HeaderMap::new(),
+            )
+        };
+        let body = |response: Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+        let script = serve(Method::GET, "/assets/app.js").await.into_response();
+        let script_type = script.headers()[header::CONTENT_TYPE].clone();
+        let script = body(script).await;
+        let index = body(serve(Method::GET, "/").await.into_response()).await;
+        let route = body(serve(Method::GET, "/lists/2").await.into_response()).await;
+        let missing = serve(Method::GET, "/assets/gone.js").await.into_response();
+        let head = serve(Method::HEAD, "/assets/app.js").await.into_response();
+        let api = serve(Method::GET, "/api/entrys").await.into_response();
+        // a change to the directory shows up without restarting
+        std::fs::write(dir.join("assets/app.js"), "console.log('changed')").unwrap();
+        let changed = body(serve(Method::GET, "/assets/app.js").await.into_response()).await;
+
+        state.pool.close().await;
+
+        assert_eq!("console.log('dev')", script);
+        assert!(script_type.to_str().unwrap().contains("javascript"));
+        assert_eq!("<script>isDemo = false</script>", index);
+        assert_eq!(index, route);
+        assert_eq!(StatusCode::NOT_FOUND, missing.status());
+        assert_eq!(StatusCode::OK, head.status());
+        assert_eq!("18", head.headers()[header::CONTENT_LENGTH]);
+        assert_eq!(StatusCode::NOT_FOUND, api.status());
+        assert_eq!("console.log('changed')", changed);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let mut app = Router::new()
+            .route(
+                "/api/entries/bulk",
+                post(|Json(entries): Json<Vec<serde_json::Value>>| async move {
+                    entries.len().to_string()
+                }),
+            )
+            .layer(body_limit_layer(1024));
+
+        let request = |body: String| {
+            axum::http::Request::builder()
+                .method(Method::POST)
+                .uri("/api/entries/bulk")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .call(request(r#"[{"description": "milk"}]"#.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let entries = vec![r#"{"description": "milk"}"#; 100].join(",");
+        let response = app.call(request(format!("[{}]", entries))).await.unwrap();
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, response.status());
+    }
+}
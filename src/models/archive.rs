@@ -0,0 +1,15 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::grocery_entry::ApiGroceryListEntry;
+
+/// ArchivedTrip is a snapshot of a list's entries taken when it was archived
+/// at the end of a shopping trip
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ArchivedTrip {
+    pub id: i64,
+    pub list_id: i64,
+    pub archived_at: DateTime<Utc>,
+    pub entries: Vec<ApiGroceryListEntry>,
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+/// AuditAction is what a change did to the row it names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// AuditTable is a table whose changes are audited
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditTable {
+    Entries,
+    Categories,
+    Lists,
+}
+
+/// AuditEntry is one change made through the api
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub table_name: String,
+    pub row_id: i64,
+    pub action: String,
+    /// diff is each field that changed as `{"field": {"before": ..., "after":
+    /// ...}}`, where a created row has no before and a deleted one no after
+    #[schema(value_type = Object)]
+    pub diff: Value,
+}
+
+/// diff is the fields that differ between before and after, either of which
+/// is missing for a row that is being created or deleted
+pub fn diff<T: Serialize>(before: Option<&T>, after: Option<&T>) -> Value {
+    let fields = |row: Option<&T>| match row.map(serde_json::to_value) {
+        Some(Ok(Value::Object(fields))) => fields,
+        _ => Map::new(),
+    };
+    let (before, after) = (fields(before), fields(after));
+
+    let mut changed = Map::new();
+    for name in before
+        .keys()
+        .chain(after.keys().filter(|name| !before.contains_key(*name)))
+    {
+        let (old, new) = (before.get(name), after.get(name));
+        if old == new {
+            continue;
+        }
+        let mut change = Map::new();
+        if let Some(old) = old {
+            change.insert("before".to_string(), old.clone());
+        }
+        if let Some(new) = new {
+            change.insert("after".to_string(), new.clone());
+        }
+        changed.insert(name.clone(), Value::Object(change));
+    }
+    Value::Object(changed)
+}
@@ -1,30 +1,119 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 
+use super::grocery_entry::{double_option, ApiGroceryListEntry};
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Category {
     pub id: i64,
+    pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i64,
     pub position: i64,
     pub name: String,
     pub is_default_category: bool,
+    pub list_id: i64,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    /// max_entries is the most active entries the category can hold, with
+    /// null meaning there is no limit
+    pub max_entries: Option<i64>,
 }
 
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateCategory {
     pub name: String,
+    pub list_id: Option<i64>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    pub max_entries: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateCategory {
+    /// version is the version of the category the client last saw, the update
+    /// is rejected if the category has changed since
+    pub version: i64,
     pub name: Option<String>,
+    /// color and icon are left alone when missing and cleared when empty
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    /// max_entries is left alone when missing and removes the limit when null
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<i64>)]
+    pub max_entries: Option<Option<i64>>,
+}
+
+/// DuplicatedCategory is a copy of a category along with the copies of its
+/// entries, in order
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicatedCategory {
+    pub category: Category,
+    pub entries: Vec<ApiGroceryListEntry>,
+}
+
+/// CategoryWithEntries is a category, along with its entries in order when
+/// they were asked for
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CategoryWithEntries {
+    #[serde(flatten)]
+    pub category: Category,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<ApiGroceryListEntry>>,
+}
+
+/// CategoryWithCounts is a category, along with how many active entries it
+/// has and how many of those are done when they were asked for
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CategoryWithCounts {
+    #[serde(flatten)]
+    pub category: Category,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_entries: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_entries: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+/// ReorderCategory moves a category to new_position among the categories of
+/// list_id, which it must belong to
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ReorderCategory {
+    pub list_id: i64,
     pub id: i64,
     pub new_position: i64,
 }
+
+/// OrderCategories sets the order of every category in a list at once
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OrderCategories {
+    pub list_id: i64,
+    pub ids: Vec<i64>,
+}
+
+/// ReorderCategories is either a single category moving, or the whole list
+/// of categories being put in a new order
+#[derive(Debug, ToSchema)]
+#[serde(untagged)]
+pub enum ReorderCategories {
+    Move(ReorderCategory),
+    Order(OrderCategories),
+}
+
+impl<'de> Deserialize<'de> for ReorderCategories {
+    /// deserialize tells the two apart by whether ids were sent, rather than
+    /// trying each the way untagged does, so that a body missing a field is
+    /// told which one instead of that it matched neither
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let reorder = if value.get("ids").is_some() {
+            serde_json::from_value(value).map(Self::Order)
+        } else {
+            serde_json::from_value(value).map(Self::Move)
+        };
+        reorder.map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// ChangeEvent is pushed to every connected client when something in a list
+/// changes, so that they can refetch what it names
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    EntryCreated {
+        id: i64,
+    },
+    EntryUpdated {
+        id: i64,
+    },
+    EntryDeleted {
+        id: i64,
+    },
+    EntryRestored {
+        id: i64,
+    },
+    /// EntriesReordered names the category whose entries changed position
+    EntriesReordered {
+        category_id: i64,
+    },
+    CategoryCreated {
+        id: i64,
+    },
+    CategoryUpdated {
+        id: i64,
+    },
+    CategoryDeleted {
+        id: i64,
+    },
+    /// CategoriesReordered names the list whose categories changed position
+    CategoriesReordered {
+        list_id: i64,
+    },
+    ListCreated {
+        id: i64,
+    },
+    ListUpdated {
+        id: i64,
+    },
+    /// ListDeleted means the list and everything on it is gone
+    ListDeleted {
+        id: i64,
+    },
+    /// ListArchived means everything on the list was archived as a trip
+    ListArchived {
+        list_id: i64,
+    },
+}
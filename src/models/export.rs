@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{category::Category, grocery_entry::GroceryListEntry, list::List};
+
+/// EXPORT_SCHEMA_VERSION is the version of the export document this server
+/// writes, and the newest version it knows how to import
+pub const EXPORT_SCHEMA_VERSION: i64 = 1;
+
+/// ExportDocument is a backup of every list, with each list's categories and
+/// each category's entries nested in display order
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportDocument {
+    pub version: i64,
+    pub exported_at: DateTime<Utc>,
+    pub lists: Vec<ExportList>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportList {
+    #[serde(flatten)]
+    pub list: List,
+    pub categories: Vec<ExportCategory>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportCategory {
+    #[serde(flatten)]
+    pub category: Category,
+    pub entries: Vec<GroceryListEntry>,
+}
+
+/// ImportMode is whether an import is added to the existing data or replaces
+/// it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    #[default]
+    Merge,
+    Replace,
+}
+
+/// ImportSummary counts the rows an import created and the existing lists and
+/// categories it merged into
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct ImportSummary {
+    pub lists: usize,
+    pub categories: usize,
+    pub entries: usize,
+    pub merged_lists: usize,
+    pub merged_categories: usize,
+    /// dry_run is whether this is only what the import would do, with nothing
+    /// written
+    pub dry_run: bool,
+    /// id_collisions are the rows in the document whose id is used more than
+    /// once in it or, for entries, already taken. Imported rows always get new
+    /// ids, so these are worth a look before importing.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub id_collisions: Vec<String>,
+}
@@ -1,68 +1,303 @@
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct GroceryListEntry {
     pub id: i64,
     pub completed_at: Option<DateTime<Utc>>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    pub position: i64,
+    pub version: i64,
+    pub position: Option<i64>,
+    pub priority: i64,
     pub quantity: String,
-    pub notes: String,
+    pub unit: String,
+    pub notes: Option<String>,
+    /// price_cents is what the entry costs in all, in the smallest unit of
+    /// currency
+    pub price_cents: Option<i64>,
+    pub currency: Option<String>,
+    /// pinned entries come first in their category. Exports from before pins
+    /// existed have none.
+    #[serde(default)]
+    pub pinned: bool,
+    /// visible_from schedules the entry, which is left out of the list until
+    /// then. Exports from before scheduling existed have none.
+    #[serde(default)]
+    pub visible_from: Option<DateTime<Utc>>,
     pub category_id: i64,
+    pub list_id: i64,
     pub description: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct ApiGroceryListEntry {
     pub id: i64,
     pub completed: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    pub position: i64,
+    pub version: i64,
+    pub position: Option<i64>,
+    pub priority: i64,
     pub quantity: String,
-    pub notes: String,
+    pub unit: String,
+    pub notes: Option<String>,
+    /// price_cents is what the entry costs in all, in the smallest unit of
+    /// currency
+    pub price_cents: Option<i64>,
+    pub currency: Option<String>,
+    /// pinned entries come first in their category
+    pub pinned: bool,
+    /// visible_from is when a scheduled entry shows up in the list
+    pub visible_from: Option<DateTime<Utc>>,
     pub category_id: i64,
+    pub list_id: i64,
     pub description: String,
+    /// tags are filled in when a list's entries are fetched, and left out
+    /// everywhere else
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(skip)]
+    pub tags: Option<Vec<String>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateGroceryListEntry {
     pub description: String,
     pub position: Option<i64>,
+    pub priority: Option<i64>,
     pub quantity: Option<String>,
+    pub unit: Option<String>,
     pub notes: Option<String>,
+    pub price_cents: Option<i64>,
+    pub currency: Option<String>,
+    /// visible_from schedules the entry, which is left out of the list until
+    /// then
+    pub visible_from: Option<DateTime<Utc>>,
     pub category_id: Option<i64>,
+    pub list_id: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateGroceryListEntry {
+    /// version is the version of the entry the client last saw, the update is
+    /// rejected if the entry has changed since
+    pub version: i64,
     pub description: Option<String>,
     pub completed: Option<bool>,
     pub quantity: Option<String>,
+    pub unit: Option<String>,
     pub notes: Option<String>,
+    /// price_cents is left alone when it is left out, and cleared when it is
+    /// sent as null
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<i64>)]
+    pub price_cents: Option<Option<i64>>,
+    /// currency is cleared when it is sent empty
+    pub currency: Option<String>,
+    /// visible_from is left alone when it is left out, and cleared, which
+    /// shows the entry right away, when it is sent as null
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub visible_from: Option<Option<DateTime<Utc>>>,
     pub category_id: Option<i64>,
     pub position: Option<i64>,
+    pub priority: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
+/// PatchGroceryListEntry changes only the fields that are sent. A nullable
+/// field that is sent as null is cleared, which for quantity and unit empties
+/// them.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PatchGroceryListEntry {
+    /// version is the version of the entry the client last saw. When it is
+    /// sent the patch is rejected if the entry has changed since, and when it
+    /// is left out the patch applies to the entry as it is now.
+    pub version: Option<i64>,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub quantity: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub unit: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub notes: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<i64>)]
+    pub price_cents: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub currency: Option<Option<String>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub visible_from: Option<Option<DateTime<Utc>>>,
+    pub category_id: Option<i64>,
+    pub position: Option<i64>,
+    pub priority: Option<i64>,
+}
+
+impl PatchGroceryListEntry {
+    /// into_update is the update that makes this patch to the entry at
+    /// version. Cleared text fields become empty, which the update stores as
+    /// cleared.
+    pub fn into_update(self, version: i64) -> UpdateGroceryListEntry {
+        UpdateGroceryListEntry {
+            version,
+            description: self.description,
+            completed: self.completed,
+            quantity: self.quantity.map(Option::unwrap_or_default),
+            unit: self.unit.map(Option::unwrap_or_default),
+            notes: self.notes.map(Option::unwrap_or_default),
+            price_cents: self.price_cents,
+            currency: self.currency.map(Option::unwrap_or_default),
+            visible_from: self.visible_from,
+            category_id: self.category_id,
+            position: self.position,
+            priority: self.priority,
+        }
+    }
+}
+
+/// double_option tells a field that was sent as null, which is Some(None),
+/// apart from one that was left out, which is None
+pub(crate) fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ReorderEntry {
     pub id: i64,
     pub new_position: Option<i64>,
     pub new_category_id: Option<i64>,
 }
 
+/// BatchUpdateEntry is one update in a batch, applied to the entry with id
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchUpdateEntry {
+    pub id: i64,
+    pub fields: UpdateGroceryListEntry,
+}
+
+/// MoveEntry moves an entry into a category, at position or else at the end
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MoveEntry {
+    pub category_id: i64,
+    pub position: Option<i64>,
+}
+
+/// OrderEntries sets the order of every active entry in a category at once
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct OrderEntries {
+    pub category_id: i64,
+    pub ids: Vec<i64>,
+}
+
+/// ReorderEntries is either a single entry moving, or a whole category being
+/// put in a new order
+#[derive(Debug, ToSchema)]
+#[serde(untagged)]
+pub enum ReorderEntries {
+    Move(ReorderEntry),
+    Order(OrderEntries),
+}
+
+impl<'de> Deserialize<'de> for ReorderEntries {
+    /// deserialize tells the two apart by whether ids were sent, rather than
+    /// trying each the way untagged does, so that a body missing a field is
+    /// told which one instead of that it matched neither
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let reorder = if value.get("ids").is_some() {
+            serde_json::from_value(value).map(Self::Order)
+        } else {
+            serde_json::from_value(value).map(Self::Move)
+        };
+        reorder.map_err(serde::de::Error::custom)
+    }
+}
+
+/// EntrySort is how a list's entries are ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EntrySort {
+    /// Position is the manual order the entries were arranged in
+    #[default]
+    Position,
+    /// Priority puts the most urgent entries first, then falls back to the
+    /// manual order
+    Priority,
+}
+
+/// EntryPlacement is where in its category a new entry goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryPlacement {
+    /// Top puts the entry first, moving the rest of the category down
+    Top,
+    #[default]
+    Bottom,
+}
+
+/// EntriesDelta is what changed in a list since a client last synced
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EntriesDelta {
+    pub entries: Vec<ApiGroceryListEntry>,
+    pub deleted_ids: Vec<i64>,
+    /// as_of is the server's time when the delta was taken, to send as
+    /// changed_since on the next sync
+    pub as_of: DateTime<Utc>,
+}
+
+/// ClearedEntries is which entries were moved to the trash together
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ClearedEntries {
+    pub deleted: usize,
+    pub ids: Vec<i64>,
+}
+
+/// EntriesPage is one page of a list's entries
+#[derive(Debug, Serialize, ToSchema)]
+pub struct EntriesPage {
+    pub items: Vec<ApiGroceryListEntry>,
+    /// total is how many entries there are across all pages
+    pub total: i64,
+    /// next_offset is the offset of the next page, if there is one
+    pub next_offset: Option<i64>,
+}
+
 impl From<&GroceryListEntry> for ApiGroceryListEntry {
     fn from(value: &GroceryListEntry) -> Self {
         Self {
             completed: value.completed_at.is_some(),
             id: value.id,
+            deleted_at: value.deleted_at,
+            created_at: value.created_at,
             updated_at: value.updated_at,
+            version: value.version,
             position: value.position,
+            priority: value.priority,
             quantity: value.quantity.clone(),
+            unit: value.unit.clone(),
             notes: value.notes.clone(),
+            price_cents: value.price_cents,
+            currency: value.currency.clone(),
+            pinned: value.pinned,
+            visible_from: value.visible_from,
             category_id: value.category_id,
+            list_id: value.list_id,
             description: value.description.clone(),
+            tags: None,
         }
     }
 }
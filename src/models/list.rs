@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+use super::{category::Category, grocery_entry::ApiGroceryListEntry};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct List {
+    pub id: i64,
+    pub updated_at: DateTime<Utc>,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateList {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateList {
+    pub name: Option<String>,
+}
+
+/// ListShare is a read-only link to a list, which anyone with the token can
+/// view at /api/shared/:token until it is revoked
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ListShare {
+    pub list_id: i64,
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// SharedList is what a read-only link shows of a list
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SharedList {
+    pub list: List,
+    pub categories: Vec<Category>,
+    pub entries: Vec<ApiGroceryListEntry>,
+}
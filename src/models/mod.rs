@@ -1,2 +1,11 @@
 pub mod grocery_entry;
 pub mod category;
+pub mod list;
+pub mod export;
+pub mod event;
+pub mod archive;
+pub mod template;
+pub mod summary;
+pub mod purchase;
+pub mod tag;
+pub mod audit;
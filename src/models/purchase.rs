@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// PurchaseEvent is one time an entry was checked off, which is taken as it
+/// having been bought
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PurchaseEvent {
+    pub id: i64,
+    /// entry_id is the entry that was checked off, if it hasn't been purged
+    pub entry_id: Option<i64>,
+    pub list_id: i64,
+    pub description: String,
+    pub quantity: String,
+    pub unit: String,
+    pub purchased_at: DateTime<Utc>,
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Summary is how much there is across every list, for showing at a glance
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Summary {
+    /// entries counts the entries on the lists, leaving out archived and
+    /// deleted ones, and completed_entries the ones of those that are done
+    pub entries: i64,
+    pub completed_entries: i64,
+    pub categories: i64,
+    pub lists: i64,
+    pub generated_at: DateTime<Utc>,
+    /// generated_at_local is generated_at in the timezone set with GL_TZ, and
+    /// is left out without one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generated_at_local: Option<DateTime<FixedOffset>>,
+}
+
+/// CostSummary is what the entries still to buy on a list will cost
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CostSummary {
+    pub list_id: i64,
+    /// totals adds up the priced entries in each currency, since prices in
+    /// different currencies can't be added together
+    pub totals: Vec<CostTotal>,
+    /// unpriced_entries counts the entries without a price, which are left
+    /// out of the totals
+    pub unpriced_entries: i64,
+}
+
+/// CostTotal is the sum of the prices of the entries in one currency, or of
+/// the ones priced without a currency
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+pub struct CostTotal {
+    pub currency: Option<String>,
+    pub total_cents: i64,
+    pub entries: i64,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// AddTag is a tag to put on an entry
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddTag {
+    pub tag: String,
+}
+
+/// EntryTags is every tag an entry has, in alphabetical order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EntryTags {
+    pub entry_id: i64,
+    pub tags: Vec<String>,
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Template is a named set of items that are bought together regularly, which
+/// can be added to a list in one go
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Template {
+    pub id: i64,
+    pub name: String,
+    pub items: Vec<TemplateItem>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// TemplateItem is one item in a template. Without a category it goes where a
+/// new entry with its description would.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateItem {
+    pub description: String,
+    pub category_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateTemplate {
+    pub name: String,
+    pub items: Vec<TemplateItem>,
+}
@@ -0,0 +1,169 @@
+use axum::response::{Html, Json};
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        OpenApi as OpenApiDocument,
+    },
+    Modify, OpenApi,
+};
+
+use crate::handlers::{audit, category, events, export, grocery, list, summary, template};
+
+/// SWAGGER_UI_VERSION is the release of swagger-ui that /api/docs loads
+const SWAGGER_UI_VERSION: &str = "5.17.14";
+
+/// ApiDoc is the OpenAPI description of the api, generated from the handlers
+/// and the types they take and return
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "gl", description = "A grocery list that is shared between everyone in the house"),
+    paths(
+        grocery::get_entries,
+        grocery::get_entry,
+        grocery::get_scheduled_entries,
+        grocery::create_entry,
+        grocery::create_entries,
+        grocery::update_entries,
+        grocery::update_entry,
+        grocery::patch_entry,
+        grocery::delete_entry,
+        grocery::toggle_entry,
+        grocery::toggle_pin,
+        grocery::restore_entry,
+        grocery::move_entry,
+        grocery::move_entry_up,
+        grocery::move_entry_down,
+        grocery::add_entry_tag,
+        grocery::remove_entry_tag,
+        grocery::get_entry_history,
+        grocery::get_trash,
+        grocery::delete_completed_entries,
+        grocery::reorder_entries,
+        grocery::get_suggestions,
+        grocery::search_entries,
+        category::get_categories,
+        category::get_category,
+        category::create_category,
+        category::update_category,
+        category::delete_category,
+        category::reorder_categories,
+        category::sort_categories,
+        category::reset_entry_order,
+        category::duplicate_category,
+        category::get_suggestions,
+        list::get_lists,
+        list::create_list,
+        list::get_list,
+        list::update_list,
+        list::delete_list,
+        list::archive_list,
+        list::share_list,
+        list::unshare_list,
+        list::get_shared_list,
+        list::get_archives,
+        list::restore_archive,
+        template::get_templates,
+        template::create_template,
+        template::apply_template,
+        summary::get_summary,
+        summary::get_cost_summary,
+        audit::get_audit_log,
+        export::export,
+        export::export_csv,
+        export::import,
+        events::subscribe_events,
+        events::stream_events,
+        crate::health_check,
+        crate::readiness_check,
+        crate::version,
+        crate::demo_reset,
+        crate::demo_status,
+    ),
+    modifiers(&ApiTokenScheme),
+)]
+pub struct ApiDoc;
+
+/// ApiTokenScheme describes the bearer token that GL_API_TOKEN makes the api
+/// ask for
+struct ApiTokenScheme;
+
+impl Modify for ApiTokenScheme {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "api_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+    }
+}
+
+/// openapi_json serves the OpenAPI description of the api
+pub async fn openapi_json() -> Json<OpenApiDocument> {
+    Json(ApiDoc::openapi())
+}
+
+/// swagger_ui serves a page to browse and try out the api, which reads its
+/// description from /api/openapi.json
+pub async fn swagger_ui() -> Html<String> {
+    Html(format!(
+        r##"<!doctype html>
+<html lang="en">
+  <head>
+    <meta charset="utf-8" />
+    <title>gl api</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui.css" />
+  </head>
+  <body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@{version}/swagger-ui-bundle.js"></script>
+    <script>
+      window.ui = SwaggerUIBundle({{ url: "/api/openapi.json", dom_id: "#swagger-ui" }});
+    </script>
+  </body>
+</html>
+"##,
+        version = SWAGGER_UI_VERSION
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_covers_the_api() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+        for path in [
+            "/api/entries",
+            "/api/entries/{id}",
+            "/api/categories/reorder",
+            "/api/lists/{id}/share",
+            "/api/summary/cost",
+            "/api/import",
+        ] {
+            assert!(doc["paths"][path].is_object(), "{} is missing", path);
+        }
+        assert!(doc["paths"]["/api/entries/{id}"]["put"]["requestBody"].is_object());
+
+        for schema in [
+            "ApiGroceryListEntry",
+            "CreateGroceryListEntry",
+            "Category",
+            "ExportDocument",
+            "ErrorBody",
+        ] {
+            assert!(
+                doc["components"]["schemas"][schema].is_object(),
+                "{} is missing",
+                schema
+            );
+        }
+        assert_eq!(
+            "bearer",
+            doc["components"]["securitySchemes"]["api_token"]["scheme"]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio::time::Instant;
+
+use crate::error::ApiError;
+
+/// UNLIMITED_PATHS are never rate limited, so that health probes keep working
+/// while a client is being throttled
+const UNLIMITED_PATHS: &[&str] = &["/health", "/ready"];
+
+/// PRUNE_THRESHOLD is how many clients are tracked before the ones that have
+/// gone quiet are forgotten
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Bucket is one client's token bucket
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// RateLimiter allows each client ip a burst of per_minute requests, refilled
+/// evenly over the course of a minute. With trust_proxy the client ip is taken
+/// from X-Forwarded-For.
+#[derive(Clone)]
+pub struct RateLimiter {
+    per_minute: u32,
+    trust_proxy: bool,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32, trust_proxy: bool) -> Self {
+        Self {
+            per_minute,
+            trust_proxy,
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// check takes a token from the ip's bucket, or says how long until one is
+    /// available if it is empty
+    fn check(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let capacity = f64::from(self.per_minute);
+        let per_sec = capacity / 60.0;
+        let mut buckets = self.buckets.lock().expect("rate limit buckets poisoned");
+
+        if buckets.len() >= PRUNE_THRESHOLD {
+            // a bucket that would be full again has nothing left to limit
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.refilled_at).as_secs_f64() * per_sec
+                    < capacity
+            });
+        }
+
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            refilled_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_sec).min(capacity);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / per_sec))
+        }
+    }
+}
+
+/// client_ip is the address of the client that made the request. Behind a
+/// trusted proxy that is the last address in X-Forwarded-For, the one the
+/// proxy added, since the ones before it come from the client and can be
+/// anything. Otherwise it is the peer the connection came from.
+fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, trust_proxy: bool) -> Option<IpAddr> {
+    let forwarded = || {
+        headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .next_back()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+    };
+
+    trust_proxy
+        .then(forwarded)
+        .flatten()
+        .or(peer.map(|peer| peer.ip()))
+}
+
+/// limit_requests rejects requests from clients that are over their limit
+/// with 429 and a Retry-After header
+pub async fn limit_requests(
+    State(limiter): State<RateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if UNLIMITED_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let Some(ip) = client_ip(request.headers(), peer, limiter.trust_proxy) else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(ip, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            tracing::info!("rate limited requests from {}", ip);
+            ApiError::too_many_requests(retry_after.as_secs_f64().ceil() as u64).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let limiter = RateLimiter::new(2, false);
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check(ip, start).is_ok());
+        assert!(limiter.check(ip, start).is_ok());
+        assert_eq!(Err(Duration::from_secs(30)), limiter.check(ip, start));
+        assert!(limiter.check(other, start).is_ok());
+        assert!(limiter.check(ip, start + Duration::from_secs(30)).is_ok());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let peer: SocketAddr = "192.168.1.5:4000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(Some(peer.ip()), client_ip(&headers, Some(peer), true));
+
+        // the client made up the first address, the proxy added the last one
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.1".parse().unwrap());
+        assert_eq!(
+            Some("10.0.0.1".parse().unwrap()),
+            client_ip(&headers, Some(peer), true)
+        );
+        assert_eq!(Some(peer.ip()), client_ip(&headers, Some(peer), false));
+
+        headers.insert("x-forwarded-for", "not an ip".parse().unwrap());
+        assert_eq!(Some(peer.ip()), client_ip(&headers, Some(peer), true));
+    }
+}
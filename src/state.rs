@@ -0,0 +1,202 @@
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+use crate::{
+    config::Config,
+    database::Database,
+    models::{
+        audit::{self, AuditAction, AuditTable},
+        event::ChangeEvent,
+    },
+};
+
+/// EVENT_CHANNEL_CAPACITY is how many change events are buffered for each
+/// connected client. A client that falls further behind than this skips the
+/// events it missed rather than holding up everyone else.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// AppState is what every handler can draw on. It is cheap to clone, as axum
+/// does for each request, since everything is behind one Arc.
+#[derive(Clone)]
+pub struct AppState(Arc<AppStateInner>);
+
+struct AppStateInner {
+    db: Arc<Database>,
+    config: Config,
+    events: broadcast::Sender<ChangeEvent>,
+    demo_resets: Mutex<DemoResetStatus>,
+}
+
+/// DemoResetStatus is how the demo database resets have been going, so that
+/// a reset that silently failed can be noticed without reading the logs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct DemoResetStatus {
+    pub last_reset_at: Option<DateTime<Utc>>,
+    pub last_failed_at: Option<DateTime<Utc>>,
+    /// last_error is why the last failed reset failed
+    pub last_error: Option<String>,
+    /// next_reset_at is when the next scheduled reset is due
+    pub next_reset_at: Option<DateTime<Utc>>,
+    /// task_running is whether the task that resets the database on schedule
+    /// is still going
+    pub task_running: bool,
+}
+
+impl AppState {
+    pub fn new(db: Arc<Database>, config: Config) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self(Arc::new(AppStateInner {
+            db,
+            config,
+            events,
+            demo_resets: Mutex::default(),
+        }))
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.0.config
+    }
+
+    /// publish sends a change event to every connected client. With no clients
+    /// connected the event is dropped.
+    pub fn publish(&self, event: ChangeEvent) {
+        let _ = self.0.events.send(event);
+    }
+
+    /// subscribe receives every change event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.0.events.subscribe()
+    }
+
+    /// audit records a change to a row in the audit log, with the fields that
+    /// differ between before and after. A row with no before was created and
+    /// one with no after was deleted. Auditing is best-effort, so a failure is
+    /// logged rather than failing the change it describes.
+    pub async fn audit<T: Serialize>(
+        &self,
+        table: AuditTable,
+        row_id: i64,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) {
+        let action = match (before, after) {
+            (None, _) => AuditAction::Create,
+            (_, None) => AuditAction::Delete,
+            _ => AuditAction::Update,
+        };
+        let diff = audit::diff(before, after);
+
+        if let Err(e) = self.record_audit(table, row_id, action, &diff).await {
+            tracing::error!(
+                "failed to audit {} of {:?} {}: {}",
+                action.as_str(),
+                table,
+                row_id,
+                e
+            );
+        }
+    }
+
+    /// is_demo is whether gl is running as the public demo
+    pub fn is_demo(&self) -> bool {
+        self.0.config.is_demo
+    }
+
+    /// record_demo_reset records how a reset of the demo database went
+    pub fn record_demo_reset(&self, result: Result<(), String>) {
+        let mut status = self.demo_resets();
+        match result {
+            Ok(()) => status.last_reset_at = Some(Utc::now()),
+            Err(e) => {
+                status.last_failed_at = Some(Utc::now());
+                status.last_error = Some(e);
+            }
+        }
+    }
+
+    /// schedule_demo_reset records when the next scheduled reset is due
+    pub fn schedule_demo_reset(&self, at: DateTime<Utc>) {
+        self.demo_resets().next_reset_at = Some(at);
+    }
+
+    /// set_demo_reset_task_running records whether the scheduled reset task is
+    /// still going
+    pub fn set_demo_reset_task_running(&self, running: bool) {
+        self.demo_resets().task_running = running;
+    }
+
+    /// demo_reset_status is how the demo database resets have gone so far
+    pub fn demo_reset_status(&self) -> DemoResetStatus {
+        self.demo_resets().clone()
+    }
+
+    fn demo_resets(&self) -> std::sync::MutexGuard<'_, DemoResetStatus> {
+        self.0
+            .demo_resets
+            .lock()
+            .expect("demo reset status poisoned")
+    }
+}
+
+/// AppState derefs to the database so that handlers can query it directly
+impl Deref for AppState {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.0.db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::memory_state;
+
+    #[tokio::test]
+    async fn test_publish_reaches_subscribers() {
+        let state = memory_state().await;
+
+        // publishing with nobody listening is fine
+        state.publish(ChangeEvent::EntryDeleted { id: 1 });
+
+        let mut events = state.subscribe();
+        state.publish(ChangeEvent::EntryUpdated { id: 3 });
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(ChangeEvent::EntryUpdated { id: 3 }, event);
+        assert_eq!(
+            serde_json::json!({"type": "entry_updated", "id": 3}),
+            serde_json::to_value(&event).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_demo_reset() {
+        let state = memory_state().await;
+        assert_eq!(DemoResetStatus::default(), state.demo_reset_status());
+
+        state.record_demo_reset(Err("demo database is missing".to_string()));
+        let failed = state.demo_reset_status();
+        assert!(failed.last_failed_at.is_some());
+        assert!(failed.last_reset_at.is_none());
+
+        state.record_demo_reset(Ok(()));
+        let status = state.demo_reset_status();
+        assert!(status.last_reset_at.is_some());
+        // the last failure is kept so that it can still be looked into
+        assert_eq!(failed.last_failed_at, status.last_failed_at);
+        assert_eq!(
+            Some("demo database is missing".to_string()),
+            status.last_error
+        );
+
+        state.pool.close().await;
+    }
+}
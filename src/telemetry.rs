@@ -0,0 +1,202 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use tokio::time::{interval, Instant};
+use tower_http::request_id::RequestId;
+use tracing::Span;
+
+use crate::database::Database;
+
+/// GAUGE_REFRESH_INTERVAL_SECS is how often the row count gauges are updated
+const GAUGE_REFRESH_INTERVAL_SECS: u64 = 60;
+
+const HTTP_REQUESTS_TOTAL: &str = "http_requests_total";
+const HTTP_REQUEST_DURATION_SECONDS: &str = "http_request_duration_seconds";
+const ENTRIES: &str = "gl_entries";
+const CATEGORIES: &str = "gl_categories";
+
+/// HTTP_REQUEST_DURATION_BUCKETS are the histogram buckets, in seconds, for
+/// request latency. Most requests are a single sqlite query, so they are
+/// concentrated at the low end.
+const HTTP_REQUEST_DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// LogFormat is how log lines are written, read from GL_LOG_FORMAT
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Pretty is readable multi-line output for running locally
+    #[default]
+    Pretty,
+    /// Json is one JSON object per line, for log aggregators like Loki
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => anyhow::bail!("GL_LOG_FORMAT must be json or pretty, got {:?}", format),
+        }
+    }
+}
+
+tokio::task_local! {
+    /// REQUEST_ID is the id of the request being handled, so that it can be
+    /// reported without threading it through every handler
+    static REQUEST_ID: String;
+}
+
+/// current_request_id is the id of the request being handled, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// request_id_of reads the id that was assigned to the request, or that the
+/// client sent along with it
+fn request_id_of<B>(request: &axum::http::Request<B>) -> Option<&str> {
+    request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+}
+
+/// make_request_span is the span each request is traced in, which carries the
+/// request id so that every log line for a request can be found by it
+pub fn make_request_span<B>(request: &axum::http::Request<B>) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+        request_id = request_id_of(request).unwrap_or_default(),
+    )
+}
+
+/// scope_request_id makes the request id available to current_request_id
+/// while the request is handled
+pub async fn scope_request_id(request: Request, next: Next) -> Response {
+    match request_id_of(&request).map(str::to_string) {
+        Some(request_id) => REQUEST_ID.scope(request_id, next.run(request)).await,
+        None => next.run(request).await,
+    }
+}
+
+/// install_recorder makes Prometheus the global metrics recorder. The handle
+/// it returns renders everything recorded so far.
+pub fn install_recorder() -> Result<PrometheusHandle, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(HTTP_REQUEST_DURATION_SECONDS.to_string()),
+            HTTP_REQUEST_DURATION_BUCKETS,
+        )?
+        .install_recorder()
+}
+
+/// metrics renders the recorded metrics in the Prometheus text format
+pub async fn metrics(State(handle): State<PrometheusHandle>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+        .into_response()
+}
+
+/// track_requests counts requests and their latency by route and status.
+/// Routes are labelled by their pattern, e.g. `/api/entries/:id`, so that ids
+/// don't each become their own series.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("path", path),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    metrics::counter!(HTTP_REQUESTS_TOTAL, &labels).increment(1);
+    metrics::histogram!(HTTP_REQUEST_DURATION_SECONDS, &labels)
+        .record(start.elapsed().as_secs_f64());
+
+    response
+}
+
+/// spawn_gauge_refresh_task periodically records how many entries and
+/// categories there are
+pub fn spawn_gauge_refresh_task(db: Arc<Database>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(Duration::from_secs(GAUGE_REFRESH_INTERVAL_SECS));
+
+        loop {
+            ticker.tick().await;
+
+            match db.count_entries_and_categories().await {
+                Ok((entries, categories)) => {
+                    metrics::gauge!(ENTRIES).set(entries as f64);
+                    metrics::gauge!(CATEGORIES).set(categories as f64);
+                }
+                Err(e) => tracing::error!("Failed to count rows for metrics: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ApiError;
+    use axum::{body::Body, http::StatusCode, routing::get, Router};
+    use tower::Service;
+    use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!(LogFormat::Json, "json".parse().unwrap());
+        assert_eq!(LogFormat::Pretty, " Pretty".parse().unwrap());
+        assert!("logfmt".parse::<LogFormat>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_in_error() {
+        let mut app = Router::new()
+            .route(
+                "/",
+                get(|| async { ApiError::not_found("nothing here").into_response() }),
+            )
+            .layer(axum::middleware::from_fn(scope_request_id))
+            .layer(PropagateRequestIdLayer::x_request_id())
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
+
+        let request = axum::http::Request::builder()
+            .uri("/")
+            .header("x-request-id", "support-ticket-42")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+        assert_eq!("support-ticket-42", response.headers()["x-request-id"]);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!("support-ticket-42", body["error"]["request_id"]);
+    }
+}
@@ -0,0 +1,102 @@
+//! Fixtures shared by the tests across the crate, so that each one doesn't set
+//! up and clean up its own database.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use sqlx::SqlitePool;
+
+use crate::config::Config;
+use crate::database::Database;
+use crate::models::category::CreateCategory;
+use crate::models::grocery_entry::CreateGroceryListEntry;
+use crate::state::AppState;
+
+/// memory_db is a fresh in-memory database with the schema and default
+/// categories in place, which no other test shares
+pub async fn memory_db() -> Database {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    Database::from_pool(pool).await.unwrap()
+}
+
+/// memory_state is app state with the default config backed by memory_db
+pub async fn memory_state() -> AppState {
+    memory_state_with(Config::default()).await
+}
+
+/// memory_state_with is app state with config backed by memory_db
+pub async fn memory_state_with(config: Config) -> AppState {
+    AppState::new(Arc::new(memory_db().await), config)
+}
+
+/// entry is an entry to create with just a description, for tests to fill in
+/// the fields they care about with struct update syntax
+pub fn entry(description: &str) -> CreateGroceryListEntry {
+    CreateGroceryListEntry {
+        description: description.to_string(),
+        position: None,
+        priority: None,
+        quantity: None,
+        unit: None,
+        notes: None,
+        price_cents: None,
+        currency: None,
+        visible_from: None,
+        category_id: None,
+        list_id: None,
+    }
+}
+
+/// category is a category to create with just a name, filled in the same way
+/// as entry
+pub fn category(name: &str) -> CreateCategory {
+    CreateCategory {
+        name: name.to_string(),
+        list_id: None,
+        color: None,
+        icon: None,
+        max_entries: None,
+    }
+}
+
+/// TempPath is a path in the temp directory, unique to the test process, for
+/// tests that need a real file or directory. Whatever ends up there is
+/// removed when it is dropped, sqlite's -wal and -shm files along with it,
+/// even if the test panics.
+pub struct TempPath(PathBuf);
+
+impl TempPath {
+    pub fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("gl-{}-test-{}", name, std::process::id())))
+    }
+
+    /// sqlite_url is the url of a database at the path
+    pub fn sqlite_url(&self) -> String {
+        format!("sqlite://{}", self.0.display())
+    }
+}
+
+impl std::ops::Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            let _ = fs::remove_dir_all(&self.0);
+            return;
+        }
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.0.clone().into_os_string();
+            path.push(suffix);
+            let _ = fs::remove_file(path);
+        }
+    }
+}
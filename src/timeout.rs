@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::error::ApiError;
+
+/// limit_duration fails requests that take longer than the timeout to respond
+/// with 503, rather than leaving the connection hanging on a slow query or a
+/// locked database. Only the time to the response is limited, so streams like
+/// /api/events can stay open for as long as they like once they've started.
+pub async fn limit_duration(
+    State(timeout): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("request to {} timed out after {:?}", path, timeout);
+            ApiError::timeout().into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, middleware, routing::get, Router};
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_slow_request_times_out() {
+        let mut app = Router::new()
+            .route("/fast", get(|| async { "done" }))
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    "done"
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Duration::from_millis(50),
+                limit_duration,
+            ));
+
+        let request = |uri| Request::builder().uri(uri).body(Body::empty()).unwrap();
+
+        let response = app.call(request("/fast")).await.unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = app.call(request("/slow")).await.unwrap();
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, response.status());
+    }
+}
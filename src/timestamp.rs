@@ -0,0 +1,61 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use chrono_tz::Tz;
+
+/// format writes at as an RFC 3339 timestamp in UTC, like
+/// `2026-01-02T15:04:05Z`. That is how timestamps are serialized in responses,
+/// so logs and hand-built json use it too, to line up with them.
+pub fn format(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// local is at in timezone, for the local time fields sent alongside the UTC
+/// ones when GL_TZ is set
+pub fn local(at: DateTime<Utc>, timezone: Option<Tz>) -> Option<DateTime<FixedOffset>> {
+    timezone.map(|timezone| at.with_timezone(&timezone).fixed_offset())
+}
+
+/// parse_timezone parses a timezone name from the tz database, as given in
+/// GL_TZ
+pub fn parse_timezone(name: &str) -> anyhow::Result<Tz> {
+    Tz::from_str(name.trim())
+        .map_err(anyhow::Error::msg)
+        .with_context(|| {
+            format!(
+                "GL_TZ {:?} is not a timezone like Europe/London or America/New_York",
+                name
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        let at = DateTime::parse_from_rfc3339("2026-07-02T15:04:05Z")
+            .unwrap()
+            .to_utc();
+
+        assert_eq!("2026-07-02T15:04:05Z", format(at));
+        assert_eq!(
+            serde_json::to_value(at).unwrap(),
+            serde_json::Value::String(format(at))
+        );
+
+        let london = parse_timezone(" Europe/London ").unwrap();
+        let local_at = local(at, Some(london)).unwrap();
+        assert_eq!(
+            "\"2026-07-02T16:04:05+01:00\"",
+            serde_json::to_string(&local_at).unwrap()
+        );
+        assert_eq!(at, local_at);
+        assert_eq!(None, local(at, None));
+
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+        assert!(parse_timezone("").is_err());
+    }
+}
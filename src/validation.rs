@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    Json,
+};
+use serde::de::DeserializeOwned;
+
+use crate::error::ApiError;
+
+/// FieldErrors collects what is wrong with each field of a request body, so
+/// that every problem can be reported at once rather than one at a time
+#[derive(Debug, Default, PartialEq)]
+pub struct FieldErrors(BTreeMap<String, String>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add records that field is invalid. Only the first problem with a field
+    /// is kept.
+    pub fn add(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.entry(field.into()).or_insert(message.into());
+    }
+
+    /// check records the error if result is one, and otherwise passes its
+    /// value on
+    pub fn check<T>(&mut self, field: &str, result: Result<T, String>) -> Option<T> {
+        result.map_err(|message| self.add(field, message)).ok()
+    }
+
+    /// extend records every error in other under prefix, as for the entries
+    /// in a list like `[2]`
+    pub fn extend(&mut self, prefix: &str, other: FieldErrors) {
+        for (field, message) in other.0 {
+            self.add(format!("{}.{}", prefix, field), message);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// into_result is Ok if no field was invalid
+    pub fn into_result(self) -> Result<(), FieldErrors> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+impl From<FieldErrors> for ApiError {
+    fn from(errors: FieldErrors) -> Self {
+        ApiError::invalid(errors.0)
+    }
+}
+
+/// ValidJson is a JSON request body like `Json`, except that a body with
+/// fields that are missing or have the wrong type is rejected with 422 and
+/// what is wrong with each of them, instead of a single line of text
+pub struct ValidJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<serde_json::Value>::from_request(request, state).await?;
+        serde_path_to_error::deserialize(value)
+            .map(ValidJson)
+            .map_err(|e| field_errors(&e).into())
+    }
+}
+
+/// field_errors turns a failure to deserialize a body into the field it
+/// failed at. serde reports a missing field against the object it is missing
+/// from, so it is moved onto the field itself.
+fn field_errors(e: &serde_path_to_error::Error<serde_json::Error>) -> FieldErrors {
+    let path = e.path().to_string();
+    let message = e.inner().to_string();
+
+    let mut errors = FieldErrors::new();
+    let missing = message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.strip_suffix('`'));
+    match (missing, path.as_str()) {
+        (Some(field), ".") => errors.add(field, "is required"),
+        (Some(field), _) => errors.add(format!("{}.{}", path, field), "is required"),
+        (None, _) => errors.add(path, message),
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode};
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Item {
+        description: String,
+        quantity: Option<String>,
+    }
+
+    async fn extract(body: &str) -> Result<Vec<Item>, ApiError> {
+        let request = Request::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        ValidJson::<Vec<Item>>::from_request(request, &())
+            .await
+            .map(|ValidJson(items)| items)
+    }
+
+    #[tokio::test]
+    async fn test_valid_json() {
+        assert_eq!(
+            1,
+            extract(r#"[{"description": "milk"}]"#).await.unwrap().len()
+        );
+
+        let missing = extract(r#"[{"description": "milk"}, {"quantity": "2"}]"#)
+            .await
+            .unwrap_err();
+        assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, missing.status);
+        assert_eq!(
+            Some(BTreeMap::from([(
+                "[1].description".to_string(),
+                "is required".to_string()
+            )])),
+            missing.fields
+        );
+
+        let wrong_type = extract(r#"[{"description": 3}]"#).await.unwrap_err();
+        assert_eq!(
+            vec!["[0].description"],
+            wrong_type.fields.unwrap().keys().collect::<Vec<_>>()
+        );
+
+        let malformed = extract(r#"[{"description": "#).await.unwrap_err();
+        assert_eq!(StatusCode::BAD_REQUEST, malformed.status);
+        assert!(malformed.fields.is_none());
+    }
+
+    #[test]
+    fn test_field_errors() {
+        let mut errors = FieldErrors::new();
+        assert_eq!(Some(2), errors.check("priority", Ok(2)));
+        assert!(errors.is_empty());
+
+        errors.check::<()>("priority", Err("too high".to_string()));
+        errors.check::<()>("priority", Err("too low".to_string()));
+        let mut entries = FieldErrors::new();
+        entries.extend("[0]", errors);
+
+        assert_eq!(
+            Err(FieldErrors(BTreeMap::from([(
+                "[0].priority".to_string(),
+                "too high".to_string()
+            )]))),
+            entries.into_result()
+        );
+    }
+}
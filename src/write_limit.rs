@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::Response,
+};
+use tokio::sync::Semaphore;
+
+/// WriteLimit bounds how many requests that change something are handled at
+/// once, since sqlite only lets one of them write at a time and the rest just
+/// contend for the lock
+#[derive(Clone)]
+pub struct WriteLimit(Arc<Semaphore>);
+
+impl WriteLimit {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self(Arc::new(Semaphore::new(max_concurrent)))
+    }
+}
+
+/// limit_writes makes a request that changes something wait until fewer than
+/// the limit are being handled. Reads go straight through. The wait counts
+/// towards the request timeout, so a request that queues too long still fails
+/// with 503 rather than hanging.
+pub async fn limit_writes(
+    State(limit): State<WriteLimit>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+
+    let _permit = limit
+        .0
+        .acquire()
+        .await
+        .expect("write limit semaphore closed");
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::StatusCode,
+        middleware,
+        routing::{get, post},
+        Router,
+    };
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+    use tower::Service;
+
+    #[tokio::test]
+    async fn test_writes_queue_past_the_limit() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most_running = Arc::new(AtomicUsize::new(0));
+        let write = {
+            let (running, most_running) = (running.clone(), most_running.clone());
+            move || async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most_running.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                "written"
+            }
+        };
+        let app = Router::new()
+            .route("/write", post(write))
+            .route(
+                "/read",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    "read"
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                WriteLimit::new(2),
+                limit_writes,
+            ));
+
+        let request = |method, uri| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let mut requests = Vec::new();
+        for _ in 0..6 {
+            let mut app = app.clone();
+            requests.push(tokio::spawn(async move {
+                app.call(request(Method::POST, "/write")).await.unwrap()
+            }));
+        }
+        let mut reads = app.clone();
+        let read = reads.call(request(Method::GET, "/read")).await.unwrap();
+
+        for response in requests {
+            assert_eq!(StatusCode::OK, response.await.unwrap().status());
+        }
+        assert_eq!(StatusCode::OK, read.status());
+        assert_eq!(2, most_running.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,185 @@
+//! End to end tests that send requests through the whole router, with every
+//! route, extractor and layer in place, against an in-memory database.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tower::Service;
+
+use grocery_list_backend::{build_router, config::Config, database::Database, state::AppState};
+
+/// app is the router over a fresh in-memory database. The rate limit is off
+/// so that tests don't trip over each other's requests.
+async fn app() -> Router {
+    let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+    let db = Database::from_pool(pool).await.unwrap();
+    let config = Config {
+        rate_limit_per_minute: 0,
+        ..Config::default()
+    };
+    build_router(AppState::new(Arc::new(db), config), None)
+}
+
+/// send makes a request, with body as json when there is one, and returns the
+/// status along with the response body parsed as json. A body that isn't json
+/// is returned as a string, and an empty one as null.
+async fn send(app: &Router, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let request = Request::builder().method(method).uri(uri);
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    // a router is always ready, so it can be called without polling it first
+    let response = app.clone().call(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = match body.is_empty() {
+        true => Value::Null,
+        false => serde_json::from_slice(&body)
+            .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&body).into_owned())),
+    };
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_entry_lifecycle() {
+    let app = app().await;
+
+    let (status, created) = send(
+        &app,
+        Method::POST,
+        "/api/entries",
+        Some(json!({"description": "2 apples"})),
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status);
+    assert_eq!("apples", created["description"]);
+    assert_eq!("2", created["quantity"]);
+    let id = created["id"].as_i64().unwrap();
+
+    let (status, entries) = send(&app, Method::GET, "/api/entries", None).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(1, entries.as_array().unwrap().len());
+    assert_eq!(id, entries[0]["id"]);
+
+    let (status, updated) = send(
+        &app,
+        Method::PUT,
+        &format!("/api/entries/{}", id),
+        Some(json!({"version": created["version"], "description": "green apples"})),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!("green apples", updated["description"]);
+
+    let (status, fetched) = send(&app, Method::GET, &format!("/api/entries/{}", id), None).await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!(updated["version"], fetched["version"]);
+
+    let (status, _) = send(&app, Method::DELETE, &format!("/api/entries/{}", id), None).await;
+    assert_eq!(StatusCode::NO_CONTENT, status);
+
+    let (status, _) = send(&app, Method::GET, &format!("/api/entries/{}", id), None).await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+    let (_, entries) = send(&app, Method::GET, "/api/entries", None).await;
+    assert_eq!(Some(&vec![]), entries.as_array());
+}
+
+#[tokio::test]
+async fn test_category_lifecycle() {
+    let app = app().await;
+
+    let (status, created) = send(
+        &app,
+        Method::POST,
+        "/api/categories",
+        Some(json!({"name": "Produce"})),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status);
+    let id = created["id"].as_i64().unwrap();
+
+    let (status, renamed) = send(
+        &app,
+        Method::PUT,
+        &format!("/api/categories/{}", id),
+        Some(json!({"version": created["version"], "name": "Fruit"})),
+    )
+    .await;
+    assert_eq!(StatusCode::OK, status);
+    assert_eq!("Fruit", renamed["name"]);
+
+    let (status, categories) = send(&app, Method::GET, "/api/categories", None).await;
+    assert_eq!(StatusCode::OK, status);
+    assert!(categories
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|category| category["name"] == "Fruit"));
+
+    let (status, _) = send(
+        &app,
+        Method::DELETE,
+        &format!("/api/categories/{}", id),
+        None,
+    )
+    .await;
+    assert_eq!(StatusCode::NO_CONTENT, status);
+    let (status, _) = send(&app, Method::GET, &format!("/api/categories/{}", id), None).await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+}
+
+#[tokio::test]
+async fn test_errors() {
+    let app = app().await;
+
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/api/entries",
+        Some(json!({"description": "   "})),
+    )
+    .await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert!(body["error"]["fields"]["description"].is_string());
+
+    let (status, body) = send(&app, Method::POST, "/api/entries", Some(json!({}))).await;
+    assert_eq!(StatusCode::UNPROCESSABLE_ENTITY, status);
+    assert_eq!("is required", body["error"]["fields"]["description"]);
+
+    let (status, created) = send(
+        &app,
+        Method::POST,
+        "/api/entries",
+        Some(json!({"description": "milk"})),
+    )
+    .await;
+    assert_eq!(StatusCode::CREATED, status);
+    let uri = format!("/api/entries/{}", created["id"]);
+    let stale = json!({"version": created["version"], "description": "oat milk"});
+    send(&app, Method::PUT, &uri, Some(stale.clone())).await;
+    let (status, body) = send(&app, Method::PUT, &uri, Some(stale)).await;
+    assert_eq!(StatusCode::CONFLICT, status);
+    assert_eq!("oat milk", body["error"]["current"]["description"]);
+
+    let (status, _) = send(&app, Method::DELETE, "/api/entries/1000", None).await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+    let (status, _) = send(&app, Method::GET, "/api/entries/milk", None).await;
+    assert_eq!(StatusCode::BAD_REQUEST, status);
+
+    let (status, body) = send(&app, Method::GET, "/api/entrys", None).await;
+    assert_eq!(StatusCode::NOT_FOUND, status);
+    assert_eq!("not_found", body["error"]["code"]);
+}
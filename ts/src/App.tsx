@@ -9,6 +9,8 @@ declare global {
   interface Window {
     APP_CONFIG: {
       isDemo: boolean;
+      version: string;
+      apiBase: string;
     };
   }
 }
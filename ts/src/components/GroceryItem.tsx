@@ -17,13 +17,13 @@ export default function GroceryItem({
 }: GroceryItemProps) {
   const [checked, setChecked] = useState(item.completed);
 
-  var fullLabel = `${item.quantity} ${item.description}`
-  if (item.notes.length > 0) {
+  var fullLabel = `${item.quantity}${item.unit} ${item.description}`
+  if (item.notes) {
     fullLabel += ` (${item.notes})`;
   }
 
   const handleCheckboxChange = (completed: boolean) => {
-    onUpdate(item.id, { completed })
+    onUpdate(item.id, { completed, version: item.version })
   }
 
   const handleDelete = () => {
@@ -168,7 +168,7 @@ export default function GroceryList({
       });
 
       const draggedCategory: CategoryModel = active.data.current?.category;
-      categoryRepository.reorderCategories(draggedCategory.id, over?.data.current?.category.position);
+      categoryRepository.reorderCategories(draggedCategory.id, draggedCategory.list_id, over?.data.current?.category.position);
     }
 
     const activeContainer = findContainer(active.id);
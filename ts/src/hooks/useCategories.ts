@@ -10,7 +10,7 @@ export type CategoryRepository = {
   createCategory: (name: string) => Promise<any>,
   updateCategory: (id: number, updates: Partial<Category>) => Promise<any>,
   deleteCategory: (id: number) => Promise<any>,
-  reorderCategories: (id: number, newPosition: number) => Promise<any>,
+  reorderCategories: (id: number, listId: number, newPosition: number) => Promise<any>,
   fetchSuggestions: (query: string) => Promise<string[]>,
   getByLabel: (label: string) => Category | undefined,
 }
@@ -51,6 +51,10 @@ export function useCategories(onError: (e: Error) => void): CategoryRepository {
         const newCategory = await response.json()
         setCategories(prev => [...prev, newCategory].sort((a, b) => a.position - b.position))
         return newCategory
+      } else if (response.status === 409) {
+        // a category with the same name already exists, so use that one
+        const body = await response.json()
+        return body.error.current
       }
     } catch (error) {
       console.error('Failed to create category:', error)
@@ -65,8 +69,11 @@ export function useCategories(onError: (e: Error) => void): CategoryRepository {
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify(updates)
       })
-      if (response.ok) {
-        const updatedCategory = await response.json()
+      // a conflict carries the category as it is on the server, which
+      // replaces our stale copy
+      if (response.ok || response.status === 409) {
+        const body = await response.json()
+        const updatedCategory = response.ok ? body : body.error.current
         setCategories(prev => prev.map(category => category.id === id ? updatedCategory : category))
       }
     } catch (error) {
@@ -82,6 +89,10 @@ export function useCategories(onError: (e: Error) => void): CategoryRepository {
       })
       if (response.ok) {
         setCategories(prev => prev.filter(category => category.id !== id))
+      } else if (response.status === 409) {
+        // the category still has entries, which are only deleted with force
+        const body = await response.json()
+        onError(new Error(body.error.message))
       }
     } catch (error) {
       console.error('Failed to delete category:', error)
@@ -89,8 +100,8 @@ export function useCategories(onError: (e: Error) => void): CategoryRepository {
     }
   }, [])
 
-  const reorderCategories = async (id: number, newPosition: number) => {
-    var request: ReorderRequest = { id, new_position: newPosition };
+  const reorderCategories = async (id: number, listId: number, newPosition: number) => {
+    var request: ReorderRequest = { list_id: listId, id, new_position: newPosition };
 
     try {
       await fetch(`${API_BASE}/categories/reorder`, {
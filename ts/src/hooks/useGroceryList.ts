@@ -63,8 +63,11 @@ export function useGroceryList(onError: (e: Error) => void): GroceryListReposito
         headers: { 'Content-Type': 'application/json' },
         body: JSON.stringify(updates)
       })
-      if (response.ok) {
-        const updatedEntry = await response.json()
+      // a conflict carries the entry as it is on the server, which replaces
+      // our stale copy
+      if (response.ok || response.status === 409) {
+        const body = await response.json()
+        const updatedEntry = response.ok ? body : body.error.current
         setEntries(prev => prev.map(entry => entry.id === id ? updatedEntry : entry))
       }
     } catch (error) {
@@ -1,12 +1,18 @@
 export interface Category {
     id: number;
+    created_at: string;
     updated_at: string;
+    version: number;
     position: number;
+    list_id: number;
     name: string;
     is_default_category: boolean;
+    color: string | null;
+    icon: string | null;
 }
 
 export interface ReorderRequest {
+  list_id: number;
   id: number;
   new_position?: number;
 }
@@ -1,12 +1,21 @@
 export interface GroceryListEntry {
   id: number;
   completed: boolean;
+  deleted_at: string | null;
+  created_at: string;
   updated_at: string;
+  version: number;
   position: number;
+  priority: number;
   quantity: string;
-  notes: string;
+  unit: string;
+  notes: string | null;
+  price_cents: number | null;
+  currency: string | null;
+  pinned: boolean;
   description: string;
   category_id: number;
+  tags?: string[];
 }
 
 export interface ReorderRequest {
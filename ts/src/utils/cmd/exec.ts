@@ -32,7 +32,7 @@ export default class Executor {
                 if (category.id === 1) {
                     return new Error(`Cannot rename default category`);
                 }
-                await this.cr.updateCategory(category.id, { name: pr.newName });
+                await this.cr.updateCategory(category.id, { name: pr.newName, version: category.version });
                 break;
 
             case CommandKind.CategoryRemove:
@@ -40,6 +40,9 @@ export default class Executor {
                 if (!categoryToRemove) {
                     return new Error(`Category "${pr.categoryName}" not found`);
                 }
+                if (categoryToRemove.is_default_category) {
+                    return new Error(`Cannot remove default category`);
+                }
                 await this.cr.deleteCategory(categoryToRemove.id);
                 break;
 